@@ -0,0 +1,288 @@
+use anyhow::Result;
+use image::GrayImage;
+use log::debug;
+
+use super::ThresholdMode;
+
+// Ink thresholds as fractions of the scanned area, so they hold at any capture size
+// (at 768x1024 these are roughly 50 pixels on the page, 50 and 400 in the header)
+const MIN_PAGE_INK_FRACTION: f32 = 0.00006;
+const MIN_HEADER_INK_FRACTION: f32 = 0.0006;
+// Header ink above this is clearly a line of typed text
+const ANSWER_HEADER_INK_FRACTION: f32 = 0.005;
+
+// Header band where typed answers start, as fractions of the page size
+// (40..728 x 40..160 on the 768x1024 virtual page)
+const HEADER_TOP: f32 = 0.039;
+const HEADER_BOTTOM: f32 = 0.156;
+const HEADER_LEFT: f32 = 0.052;
+const HEADER_RIGHT: f32 = 0.948;
+
+// Answer page marker: a 4x4 grid of 4-pixel cells drawn in the top margin, clear of
+// the upper-right trigger zone (virtual coordinates, 768x1024 page)
+pub const MARKER_X: i32 = 620;
+pub const MARKER_Y: i32 = 12;
+const MARKER_CELL: i32 = 4;
+const MARKER_CELLS: [[bool; 4]; 4] = [
+    [true, true, true, true],
+    [true, false, false, true],
+    [true, false, true, false],
+    [true, true, false, true],
+];
+// How far from MARKER_X/MARKER_Y the drawn marker may land and still be found
+const MARKER_SEARCH_RADIUS: i32 = 6;
+
+/// Result of looking at the page after the reading page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnswerPageCheck {
+    /// An answer page (typed text from the top) or an empty page; safe to write on
+    IsAnswerPage,
+    /// A page with other content; writing here would mix answers into it
+    NotAnswerPage,
+    /// Some ink in the header, but not clearly typed answers
+    Uncertain,
+}
+
+/// Classify a captured page by where its ink is
+///
+/// Answers are typed from the top of the page, so an answer page always has a
+/// well-filled header band. Ink elsewhere with an empty header means someone
+/// else's content; a lightly inked header could be either.
+pub fn classify_answer_page(png_data: &[u8], mode: ThresholdMode) -> Result<AnswerPageCheck> {
+    let gray = image::load_from_memory(png_data)?.to_luma8();
+    let (width, height) = (gray.width() as f32, gray.height() as f32);
+    let threshold = mode.ink_threshold(&gray);
+
+    let page_ink = ink_fraction(&gray, threshold, 0, 0, gray.width(), gray.height());
+    let header_ink = ink_fraction(
+        &gray,
+        threshold,
+        (width * HEADER_LEFT) as u32,
+        (height * HEADER_TOP) as u32,
+        (width * HEADER_RIGHT) as u32,
+        (height * HEADER_BOTTOM) as u32,
+    );
+    debug!(
+        "Answer page check: {:.5} of header is ink, {:.5} of page",
+        header_ink, page_ink
+    );
+
+    // An empty page is as good as an answer page: there is nothing to overwrite
+    let check = if page_ink < MIN_PAGE_INK_FRACTION || header_ink >= ANSWER_HEADER_INK_FRACTION {
+        AnswerPageCheck::IsAnswerPage
+    } else if header_ink < MIN_HEADER_INK_FRACTION {
+        AnswerPageCheck::NotAnswerPage
+    } else {
+        AnswerPageCheck::Uncertain
+    };
+    Ok(check)
+}
+
+/// The 16x16 answer page marker bitmap (true = ink)
+pub fn answer_page_marker_bitmap() -> Vec<Vec<bool>> {
+    let size = (MARKER_CELLS.len() as i32 * MARKER_CELL) as usize;
+    (0..size)
+        .map(|y| {
+            (0..size)
+                .map(|x| MARKER_CELLS[y / MARKER_CELL as usize][x / MARKER_CELL as usize])
+                .collect()
+        })
+        .collect()
+}
+
+/// Whether the answer page marker is drawn near its spot on a captured page
+///
+/// Template match at cell level: every cell's center pixel must be ink exactly
+/// where the pattern has ink, at some offset within MARKER_SEARCH_RADIUS.
+pub fn has_answer_page_marker(png_data: &[u8], mode: ThresholdMode) -> Result<bool> {
+    let mut gray = image::load_from_memory(png_data)?.to_luma8();
+    if (gray.width(), gray.height()) != (768, 1024) {
+        gray = image::imageops::resize(&gray, 768, 1024, image::imageops::FilterType::Triangle);
+    }
+    let threshold = mode.ink_threshold(&gray);
+
+    let is_ink = |x: i32, y: i32| {
+        x >= 0
+            && y >= 0
+            && (x as u32) < gray.width()
+            && (y as u32) < gray.height()
+            && gray.get_pixel(x as u32, y as u32)[0] < threshold
+    };
+
+    for dy in -MARKER_SEARCH_RADIUS..=MARKER_SEARCH_RADIUS {
+        for dx in -MARKER_SEARCH_RADIUS..=MARKER_SEARCH_RADIUS {
+            let matches = MARKER_CELLS.iter().enumerate().all(|(row, cells)| {
+                cells.iter().enumerate().all(|(col, &expected)| {
+                    let x = MARKER_X + dx + col as i32 * MARKER_CELL + MARKER_CELL / 2;
+                    let y = MARKER_Y + dy + row as i32 * MARKER_CELL + MARKER_CELL / 2;
+                    is_ink(x, y) == expected
+                })
+            });
+            if matches {
+                debug!("Answer page marker found at offset ({}, {})", dx, dy);
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Whether a captured page has (next to) no ink at all
+pub fn is_blank_page(png_data: &[u8], mode: ThresholdMode) -> Result<bool> {
+    let gray = image::load_from_memory(png_data)?.to_luma8();
+    let threshold = mode.ink_threshold(&gray);
+    Ok(ink_fraction(&gray, threshold, 0, 0, gray.width(), gray.height()) < MIN_PAGE_INK_FRACTION)
+}
+
+/// Lowest row with ink on a captured page, in virtual coordinates (768x1024)
+/// None if the page has no ink
+pub fn lowest_ink_row(png_data: &[u8], mode: ThresholdMode) -> Result<Option<i32>> {
+    let gray = image::load_from_memory(png_data)?.to_luma8();
+    let threshold = mode.ink_threshold(&gray);
+    let row = (0..gray.height())
+        .rev()
+        .find(|&y| (0..gray.width()).any(|x| gray.get_pixel(x, y)[0] < threshold));
+    Ok(row.map(|y| (y as f32 * 1024.0 / gray.height() as f32).round() as i32))
+}
+
+/// Fraction of pixels within [left, right) x [top, bottom) darker than `threshold`,
+/// clamped to the image
+fn ink_fraction(
+    gray: &GrayImage,
+    threshold: u8,
+    left: u32,
+    top: u32,
+    right: u32,
+    bottom: u32,
+) -> f32 {
+    let right = right.min(gray.width());
+    let bottom = bottom.min(gray.height());
+    if left >= right || top >= bottom {
+        return 0.0;
+    }
+
+    let mut ink = 0;
+    for y in top..bottom {
+        for x in left..right {
+            if gray.get_pixel(x, y)[0] < threshold {
+                ink += 1;
+            }
+        }
+    }
+    ink as f32 / ((right - left) * (bottom - top)) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, Luma};
+    use std::io::Cursor;
+
+    /// A white page of `width` x `height` with black rectangles given in virtual
+    /// coordinates (768x1024), encoded as PNG
+    fn page(width: u32, height: u32, rects: &[(i32, i32, i32, i32)]) -> Vec<u8> {
+        let (scale_x, scale_y) = (width as f32 / 768.0, height as f32 / 1024.0);
+        let mut gray = GrayImage::from_pixel(width, height, Luma([255]));
+        for &(left, top, w, h) in rects {
+            let x_range = (left as f32 * scale_x) as u32..((left + w) as f32 * scale_x) as u32;
+            for y in (top as f32 * scale_y) as u32..((top + h) as f32 * scale_y) as u32 {
+                for x in x_range.clone() {
+                    gray.put_pixel(x, y, Luma([0]));
+                }
+            }
+        }
+        let mut png = Vec::new();
+        gray.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    // Lines of typed text from the top, as on an answer page
+    const TYPED_LINES: &[(i32, i32, i32, i32)] = &[
+        (60, 60, 600, 6),
+        (60, 90, 520, 6),
+        (60, 120, 560, 6),
+        (60, 150, 300, 6),
+    ];
+    // A drawing in the middle of the page, header empty
+    const DRAWING: &[(i32, i32, i32, i32)] = &[(200, 400, 300, 8), (340, 300, 8, 300)];
+
+    #[test]
+    fn answer_page_detection_at_two_resolutions() {
+        for (width, height) in [(768, 1024), (1404, 1872)] {
+            let check =
+                |rects| classify_answer_page(&page(width, height, rects), ThresholdMode::Fixed);
+            assert_eq!(
+                check(TYPED_LINES).unwrap(),
+                AnswerPageCheck::IsAnswerPage,
+                "{}x{}",
+                width,
+                height
+            );
+            assert_eq!(
+                check(DRAWING).unwrap(),
+                AnswerPageCheck::NotAnswerPage,
+                "{}x{}",
+                width,
+                height
+            );
+            assert_eq!(
+                check(&[]).unwrap(),
+                AnswerPageCheck::IsAnswerPage,
+                "{}x{}",
+                width,
+                height
+            );
+        }
+    }
+
+    /// The marker bitmap as 1x1 rectangles, drawn `offset` from its spot
+    fn marker_rects(offset: (i32, i32)) -> Vec<(i32, i32, i32, i32)> {
+        let mut rects = Vec::new();
+        for (y, row) in answer_page_marker_bitmap().iter().enumerate() {
+            for (x, &ink) in row.iter().enumerate() {
+                if ink {
+                    rects.push((
+                        MARKER_X + offset.0 + x as i32,
+                        MARKER_Y + offset.1 + y as i32,
+                        1,
+                        1,
+                    ));
+                }
+            }
+        }
+        rects
+    }
+
+    #[test]
+    fn drawn_marker_is_detected() {
+        for offset in [(0, 0), (3, -2)] {
+            let png = page(768, 1024, &marker_rects(offset));
+            assert!(
+                has_answer_page_marker(&png, ThresholdMode::Fixed).unwrap(),
+                "{:?}",
+                offset
+            );
+        }
+
+        let mut rects = marker_rects((0, 0));
+        rects.extend_from_slice(TYPED_LINES);
+        let png = page(1404, 1872, &rects);
+        assert!(has_answer_page_marker(&png, ThresholdMode::Fixed).unwrap());
+    }
+
+    #[test]
+    fn plain_ink_is_not_a_marker() {
+        // A solid blot where the marker goes, and an underline through its spot
+        let blot = [(MARKER_X, MARKER_Y, 16, 16)];
+        let line = [(MARKER_X - 40, MARKER_Y + 8, 100, 3)];
+        for rects in [&blot[..], &line[..], TYPED_LINES, &[]] {
+            let png = page(768, 1024, rects);
+            assert!(
+                !has_answer_page_marker(&png, ThresholdMode::Fixed).unwrap(),
+                "{:?}",
+                rects
+            );
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use anyhow::Result;
+use image::{GrayImage, Luma};
+use imageproc::distance_transform::Norm;
+use imageproc::morphology::dilate;
+use log::debug;
+
+use super::{BoundingBox, ThresholdMode};
+
+/// A straight eraser stroke between two points (virtual coordinates)
+pub type EraseStroke = ((i32, i32), (i32, i32));
+
+/// Eraser strokes covering the ink inside `region`, grown by `margin` pixels
+/// Ink is what is darker than `threshold`, or the level `mode` picks if None
+///
+/// The ink mask is dilated so anti-aliased edges and slightly misread boxes are
+/// still covered, then swept row by row over the mask only, alternating direction
+/// so the eraser moves back and forth instead of jumping to the left edge each row.
+/// Returns no strokes if the region holds no ink.
+pub fn contour_erase_strokes(
+    png_data: &[u8],
+    region: &BoundingBox,
+    margin: u8,
+    mode: ThresholdMode,
+    threshold: Option<u8>,
+) -> Result<Vec<EraseStroke>> {
+    let gray = image::load_from_memory(png_data)?.to_luma8();
+    let threshold = threshold.unwrap_or_else(|| mode.ink_threshold(&gray));
+
+    // Clamp the region to the page, leaving room around it for the dilation
+    let pad = margin as i32;
+    let left = (region.x - pad).max(0) as u32;
+    let top = (region.y - pad).max(0) as u32;
+    let right = ((region.x + region.width + pad).max(0) as u32).min(gray.width());
+    let bottom = ((region.y + region.height + pad).max(0) as u32).min(gray.height());
+    if left >= right || top >= bottom {
+        return Ok(Vec::new());
+    }
+
+    // Only ink inside the box itself seeds the mask; the padding is for growth
+    let mut mask = GrayImage::new(right - left, bottom - top);
+    let mut ink = 0;
+    for (x, y, pixel) in mask.enumerate_pixels_mut() {
+        let (page_x, page_y) = ((left + x) as i32, (top + y) as i32);
+        let in_box = page_x >= region.x
+            && page_x < region.x + region.width
+            && page_y >= region.y
+            && page_y < region.y + region.height;
+        if in_box && gray.get_pixel(left + x, top + y)[0] < threshold {
+            *pixel = Luma([255]);
+            ink += 1;
+        }
+    }
+    if ink == 0 {
+        return Ok(Vec::new());
+    }
+    let mask = dilate(&mask, Norm::LInf, margin);
+
+    let mut strokes = Vec::new();
+    for y in 0..mask.height() {
+        let mut runs = Vec::new();
+        let mut run_start = None;
+        for x in 0..=mask.width() {
+            let set = x < mask.width() && mask.get_pixel(x, y)[0] > 0;
+            match (set, run_start) {
+                (true, None) => run_start = Some(x),
+                (false, Some(start)) => {
+                    runs.push((start, x - 1));
+                    run_start = None;
+                }
+                _ => {}
+            }
+        }
+        if y % 2 == 1 {
+            runs.reverse();
+        }
+
+        let page_y = (top + y) as i32;
+        for (start, end) in runs {
+            let (start, end) = ((left + start) as i32, (left + end) as i32);
+            if y % 2 == 1 {
+                strokes.push(((end, page_y), (start, page_y)));
+            } else {
+                strokes.push(((start, page_y), (end, page_y)));
+            }
+        }
+    }
+
+    debug!(
+        "Contour erase: {} ink pixels, {} strokes (margin {})",
+        ink,
+        strokes.len(),
+        margin
+    );
+    Ok(strokes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::ImageFormat;
+    use std::io::Cursor;
+
+    /// A white 200x200 page with a handwritten-ish blob of ink, as PNG
+    fn page_with_ink() -> Vec<u8> {
+        let gray = GrayImage::from_fn(200, 200, |x, y| {
+            let ink = (60..140).contains(&x) && (90..100).contains(&y)
+                || (95..105).contains(&x) && (70..130).contains(&y);
+            Luma([if ink { 0 } else { 255 }])
+        });
+        let mut png = Vec::new();
+        gray.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    /// Pixels swept by the strokes
+    fn erased_pixels(strokes: &[EraseStroke]) -> i32 {
+        strokes
+            .iter()
+            .map(|((x1, _), (x2, _))| (x2 - x1).abs() + 1)
+            .sum()
+    }
+
+    #[test]
+    fn larger_margin_erases_more() {
+        let png = page_with_ink();
+        let region = BoundingBox {
+            x: 50,
+            y: 60,
+            width: 100,
+            height: 80,
+        };
+        let erase = |margin| {
+            contour_erase_strokes(&png, &region, margin, ThresholdMode::Fixed, None).unwrap()
+        };
+
+        let (tight, wide) = (erase(1), erase(6));
+        assert!(
+            erased_pixels(&wide) > erased_pixels(&tight),
+            "margin 6 erased {} pixels, margin 1 erased {}",
+            erased_pixels(&wide),
+            erased_pixels(&tight)
+        );
+        // The wider margin also reaches further above and below the ink
+        let rows =
+            |strokes: &[EraseStroke]| strokes.iter().map(|((_, y), _)| *y).collect::<Vec<_>>();
+        assert!(rows(&wide).iter().min() < rows(&tight).iter().min());
+        assert!(rows(&wide).iter().max() > rows(&tight).iter().max());
+    }
+
+    #[test]
+    fn no_ink_no_strokes() {
+        let png = page_with_ink();
+        let empty = BoundingBox {
+            x: 0,
+            y: 0,
+            width: 40,
+            height: 40,
+        };
+        let strokes = contour_erase_strokes(&png, &empty, 6, ThresholdMode::Fixed, None).unwrap();
+        assert!(strokes.is_empty());
+    }
+}
@@ -1,4 +1,17 @@
+pub mod answer_page;
+pub mod erase_path;
+pub mod preprocess;
+pub mod quality;
+pub mod symbol_match;
+
+use anyhow::Result;
+use image::GrayImage;
+use imageproc::contrast::otsu_level;
+use log::debug;
 use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::llm::TokenUsage;
 
 /// Represents a region of interest on the screen
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -8,3 +21,192 @@ pub struct BoundingBox {
     pub width: i32,
     pub height: i32,
 }
+
+/// A single question/answer pair found on the page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QaItem {
+    pub question: String,
+    pub answer: String,
+    pub question_box: Option<BoundingBox>,
+    pub outline_box: Option<BoundingBox>,
+    /// Verbatim quote from the outlined content supporting the answer (--cite)
+    /// Chosen by the model, so it may not match the page exactly
+    #[serde(default)]
+    pub quote: Option<String>,
+}
+
+/// Information about how an analysis result was produced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisMetadata {
+    /// Seconds since the Unix epoch when the analysis finished
+    pub timestamp: u64,
+    pub model: String,
+    pub usage: Option<TokenUsage>,
+    /// Why the model stopped, e.g. "stop" or "length" (cut off by max_tokens)
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+}
+
+impl AnalysisMetadata {
+    /// Metadata stamped with the current time
+    pub fn now(model: &str, usage: Option<TokenUsage>, finish_reason: Option<String>) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self {
+            timestamp,
+            model: model.to_string(),
+            usage,
+            finish_reason,
+        }
+    }
+}
+
+/// Result from LLM analysis: every Q&A pair found plus metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisResult {
+    pub items: Vec<QaItem>,
+    pub metadata: AnalysisMetadata,
+}
+
+// Fixed grayscale level below which a pixel counts as ink
+pub const FIXED_INK_THRESHOLD: u8 = 128;
+// Otsu levels outside this range come from pages with (next to) no ink, where the
+// histogram split lands in paper noise; they are clamped so noise never counts as ink
+const OTSU_MIN_THRESHOLD: u8 = 64;
+const OTSU_MAX_THRESHOLD: u8 = 192;
+
+/// How the ink/paper grayscale threshold is chosen for ink detection
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ThresholdMode {
+    /// Always FIXED_INK_THRESHOLD
+    Fixed,
+    /// Per image, from its histogram (Otsu's method)
+    #[default]
+    Otsu,
+}
+
+impl ThresholdMode {
+    pub fn from_string(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "fixed" => Ok(ThresholdMode::Fixed),
+            "otsu" => Ok(ThresholdMode::Otsu),
+            _ => Err(anyhow::anyhow!(
+                "Invalid threshold mode: {}. Use fixed or otsu",
+                s
+            )),
+        }
+    }
+
+    /// Grayscale level below which a pixel of `gray` counts as ink
+    pub fn ink_threshold(&self, gray: &GrayImage) -> u8 {
+        match self {
+            ThresholdMode::Fixed => FIXED_INK_THRESHOLD,
+            ThresholdMode::Otsu => {
+                let level = otsu_threshold(gray).clamp(OTSU_MIN_THRESHOLD, OTSU_MAX_THRESHOLD);
+                debug!("Otsu ink threshold: {}", level);
+                level
+            }
+        }
+    }
+}
+
+/// Threshold that best separates the two peaks of the image's histogram (Otsu's method)
+pub fn otsu_threshold(gray: &GrayImage) -> u8 {
+    otsu_level(gray)
+}
+
+/// Fraction (0.0-1.0) of pixels that differ noticeably between two PNG images
+/// Images of different sizes are treated as completely different
+pub fn image_difference(png_a: &[u8], png_b: &[u8]) -> Result<f32> {
+    // Ignore small grayscale changes (e-ink ghosting, dithering)
+    const PIXEL_TOLERANCE: u8 = 32;
+
+    let a = image::load_from_memory(png_a)?.to_luma8();
+    let b = image::load_from_memory(png_b)?.to_luma8();
+    if a.dimensions() != b.dimensions() {
+        return Ok(1.0);
+    }
+
+    let total = a.as_raw().len().max(1);
+    let changed = a
+        .as_raw()
+        .iter()
+        .zip(b.as_raw())
+        .filter(|(pa, pb)| pa.abs_diff(**pb) > PIXEL_TOLERANCE)
+        .count();
+    Ok(changed as f32 / total as f32)
+}
+
+/// Whether the change between two captures is a horizontal stroke along a swipe path
+///
+/// A swipe made while xochitl's pen tool is active draws a line instead of turning
+/// the page: almost all changed pixels then sit in a thin band around the swipe.
+/// `start_x`, `end_x` and `y` are virtual coordinates (768x1024 page).
+pub fn is_swipe_stroke(
+    png_before: &[u8],
+    png_after: &[u8],
+    start_x: i32,
+    end_x: i32,
+    y: i32,
+) -> Result<bool> {
+    // Half height of the band the stroke may wander in, virtual units
+    const BAND_HALF_HEIGHT: i32 = 12;
+    // Share of changed pixels that must be inside the band
+    const MIN_BAND_SHARE: f32 = 0.8;
+    // Changed pixels in the band, relative to the swipe length, that make it a line
+    const MIN_LINE_COVERAGE: f32 = 0.5;
+    const PIXEL_TOLERANCE: u8 = 32;
+
+    let a = image::load_from_memory(png_before)?.to_luma8();
+    let b = image::load_from_memory(png_after)?.to_luma8();
+    if a.dimensions() != b.dimensions() {
+        return Ok(false);
+    }
+
+    let scale_x = a.width() as f32 / 768.0;
+    let scale_y = a.height() as f32 / 1024.0;
+    let left = (start_x.min(end_x) as f32 * scale_x) as u32;
+    let right = (start_x.max(end_x) as f32 * scale_x) as u32;
+    let top = ((y - BAND_HALF_HEIGHT).max(0) as f32 * scale_y) as u32;
+    let bottom = ((y + BAND_HALF_HEIGHT) as f32 * scale_y) as u32;
+
+    let (mut inside, mut outside) = (0usize, 0usize);
+    for (x, py, pa) in a.enumerate_pixels() {
+        if pa[0].abs_diff(b.get_pixel(x, py)[0]) <= PIXEL_TOLERANCE {
+            continue;
+        }
+        if (left..=right).contains(&x) && (top..=bottom).contains(&py) {
+            inside += 1;
+        } else {
+            outside += 1;
+        }
+    }
+
+    let changed = inside + outside;
+    let min_inside = ((right - left) as f32 * MIN_LINE_COVERAGE) as usize;
+    debug!(
+        "Swipe stroke check: {} changed pixels in band, {} outside",
+        inside, outside
+    );
+    Ok(changed > 0 && inside >= min_inside && inside as f32 / changed as f32 >= MIN_BAND_SHARE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    #[test]
+    fn otsu_threshold_separates_two_levels() {
+        // Dark ink on the left third, light paper elsewhere
+        let gray = GrayImage::from_fn(90, 60, |x, _| if x < 30 { Luma([40]) } else { Luma([200]) });
+        let threshold = otsu_threshold(&gray);
+        assert!(
+            (40..200).contains(&threshold),
+            "threshold {} is not between the levels",
+            threshold
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use anyhow::Result;
+use image::GrayImage;
+use imageproc::contrast::{otsu_level, threshold, ThresholdType};
+use log::debug;
+use std::io::Cursor;
+
+/// Optional clean-up applied to the image sent to the LLM
+/// Helps vision models read faint pencil handwriting. Stages run in field order.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Preprocess {
+    /// Stretch the darkest/lightest 1% of pixels to full black/white
+    pub contrast_stretch: bool,
+    /// Unsharp mask to crisp up thin strokes
+    pub sharpen: bool,
+    /// Reduce to pure black/white using an automatic (Otsu) threshold
+    pub binarize: bool,
+}
+
+impl Preprocess {
+    pub fn is_enabled(&self) -> bool {
+        self.contrast_stretch || self.sharpen || self.binarize
+    }
+
+    /// Run the enabled stages on a PNG and return the processed PNG (grayscale)
+    pub fn apply(&self, png_data: &[u8]) -> Result<Vec<u8>> {
+        let mut gray = image::load_from_memory(png_data)?.to_luma8();
+
+        if self.contrast_stretch {
+            gray = Self::stretch_contrast(gray);
+        }
+        if self.sharpen {
+            gray = image::imageops::unsharpen(&gray, 1.0, 4);
+        }
+        if self.binarize {
+            let level = otsu_level(&gray);
+            debug!("Binarizing LLM image at level {}", level);
+            gray = threshold(&gray, level, ThresholdType::Binary);
+        }
+
+        let mut processed = Vec::new();
+        gray.write_to(&mut Cursor::new(&mut processed), image::ImageFormat::Png)?;
+        Ok(processed)
+    }
+
+    /// Linearly map the 1st..99th percentile range onto 0..255
+    fn stretch_contrast(mut gray: GrayImage) -> GrayImage {
+        let mut histogram = [0u64; 256];
+        for pixel in gray.pixels() {
+            histogram[pixel[0] as usize] += 1;
+        }
+
+        let total = gray.width() as u64 * gray.height() as u64;
+        let clip = total / 100;
+        let low = Self::percentile_level(histogram.iter().enumerate(), clip).unwrap_or(0);
+        let high = Self::percentile_level(histogram.iter().enumerate().rev(), clip).unwrap_or(255);
+        if high <= low {
+            return gray;
+        }
+
+        debug!("Stretching LLM image contrast from {}..{}", low, high);
+        let range = (high - low) as f32;
+        for pixel in gray.pixels_mut() {
+            let value = pixel[0].clamp(low, high) - low;
+            pixel[0] = (value as f32 * 255.0 / range).round() as u8;
+        }
+        gray
+    }
+
+    /// First level at which the running histogram count exceeds `clip`
+    fn percentile_level<'a>(
+        mut levels: impl Iterator<Item = (usize, &'a u64)>,
+        clip: u64,
+    ) -> Option<u8> {
+        let mut seen = 0;
+        levels
+            .find(|&(_, &count)| {
+                seen += count;
+                seen > clip
+            })
+            .map(|(level, _)| level as u8)
+    }
+}
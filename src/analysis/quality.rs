@@ -0,0 +1,69 @@
+use anyhow::Result;
+use log::debug;
+
+use super::FIXED_INK_THRESHOLD;
+
+/// Plausibility limits for a capture, checked before it is sent to the LLM
+/// A failed framebuffer read comes back blank or as one flat color, which would
+/// only cost an API call and produce a confusing NONE.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityCheck {
+    /// Less ink than this fraction of the page looks like an empty capture
+    pub min_ink: f32,
+    /// More ink than this fraction looks like a saturated (all black) capture
+    pub max_ink: f32,
+    /// Gray levels with a smaller standard deviation (0-255) are one flat color
+    pub min_contrast: f32,
+}
+
+impl Default for QualityCheck {
+    fn default() -> Self {
+        Self {
+            min_ink: 0.0005,
+            max_ink: 0.95,
+            min_contrast: 4.0,
+        }
+    }
+}
+
+impl QualityCheck {
+    /// Fail with a capture error describing the problem if the PNG looks implausible
+    pub fn check(&self, png_data: &[u8]) -> Result<()> {
+        let gray = image::load_from_memory(png_data)?.to_luma8();
+        let pixels = gray.as_raw();
+        let total = pixels.len().max(1) as f32;
+
+        let ink = pixels.iter().filter(|&&p| p < FIXED_INK_THRESHOLD).count() as f32 / total;
+        let mean = pixels.iter().map(|&p| p as f32).sum::<f32>() / total;
+        let variance = pixels
+            .iter()
+            .map(|&p| (p as f32 - mean).powi(2))
+            .sum::<f32>()
+            / total;
+        let contrast = variance.sqrt();
+        debug!("Capture quality: ink {:.5}, contrast {:.2}", ink, contrast);
+
+        if ink < self.min_ink {
+            anyhow::bail!(
+                "Capture looks blank (ink {:.5} < {}); the screen read may have failed",
+                ink,
+                self.min_ink
+            );
+        }
+        if ink > self.max_ink {
+            anyhow::bail!(
+                "Capture looks saturated (ink {:.3} > {}); the screen read may have failed",
+                ink,
+                self.max_ink
+            );
+        }
+        if contrast < self.min_contrast {
+            anyhow::bail!(
+                "Capture is a single flat color (contrast {:.2} < {}); the screen read may have failed",
+                contrast,
+                self.min_contrast
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+use anyhow::Result;
+use image::GrayImage;
+use log::debug;
+
+use super::ThresholdMode;
+
+// How far from the expected center a drawn symbol may land and still be found
+const SYMBOL_SEARCH_RADIUS: i32 = 8;
+// Lowest score (share of the symbol's ink found, minus the share of stray ink
+// around it) that counts as the symbol being on the page
+const MIN_SYMBOL_MATCH: f32 = 0.6;
+
+/// Which of `candidates` (symbol, bitmap) is drawn centered near `center`
+/// (virtual coordinates), if any
+///
+/// Pen strokes come out slightly thicker or thinner than the bitmap, so a symbol
+/// pixel counts as found with ink within one pixel of it, and ink only counts
+/// against a candidate more than one pixel away from its strokes. Candidates
+/// share the circle, so the digits decide between them: the best score wins.
+pub fn find_symbol(
+    png_data: &[u8],
+    mode: ThresholdMode,
+    center: (i32, i32),
+    candidates: &[(String, Vec<Vec<bool>>)],
+) -> Result<Option<String>> {
+    let mut gray = image::load_from_memory(png_data)?.to_luma8();
+    if (gray.width(), gray.height()) != (768, 1024) {
+        gray = image::imageops::resize(&gray, 768, 1024, image::imageops::FilterType::Triangle);
+    }
+    let threshold = mode.ink_threshold(&gray);
+
+    let mut best: Option<(f32, &str)> = None;
+    for (symbol, bitmap) in candidates {
+        let score = best_score(&gray, threshold, center, bitmap);
+        debug!("Symbol {} scores {:.2} near {:?}", symbol, score, center);
+        if score >= MIN_SYMBOL_MATCH && best.is_none_or(|(best, _)| score > best) {
+            best = Some((score, symbol));
+        }
+    }
+    Ok(best.map(|(_, symbol)| symbol.to_string()))
+}
+
+/// Best match of `bitmap` centered within SYMBOL_SEARCH_RADIUS of `center`
+fn best_score(gray: &GrayImage, threshold: u8, center: (i32, i32), bitmap: &[Vec<bool>]) -> f32 {
+    let height = bitmap.len() as i32;
+    let width = bitmap.first().map_or(0, |row| row.len() as i32);
+    let is_ink = |x: i32, y: i32| {
+        x >= 0
+            && y >= 0
+            && (x as u32) < gray.width()
+            && (y as u32) < gray.height()
+            && gray.get_pixel(x as u32, y as u32)[0] < threshold
+    };
+    let near = |x: i32, y: i32, hit: &dyn Fn(i32, i32) -> bool| {
+        (-1..=1).any(|dy| (-1..=1).any(|dx| hit(x + dx, y + dy)))
+    };
+    let on_bitmap = |x: i32, y: i32| {
+        x >= 0 && y >= 0 && x < width && y < height && bitmap[y as usize][x as usize]
+    };
+
+    let on = bitmap.iter().flatten().filter(|&&pixel| pixel).count();
+    let off = (width * height) as usize - on;
+    if on == 0 || off == 0 {
+        return 0.0;
+    }
+
+    let mut best = f32::MIN;
+    for oy in -SYMBOL_SEARCH_RADIUS..=SYMBOL_SEARCH_RADIUS {
+        for ox in -SYMBOL_SEARCH_RADIUS..=SYMBOL_SEARCH_RADIUS {
+            let left = center.0 + ox - width / 2;
+            let top = center.1 + oy - height / 2;
+            let mut found = 0;
+            let mut stray = 0;
+            for y in 0..height {
+                for x in 0..width {
+                    if on_bitmap(x, y) {
+                        if near(left + x, top + y, &is_ink) {
+                            found += 1;
+                        }
+                    } else if is_ink(left + x, top + y) && !near(x, y, &on_bitmap) {
+                        stray += 1;
+                    }
+                }
+            }
+            let score = found as f32 / on as f32 - stray as f32 / off as f32;
+            best = best.max(score);
+        }
+    }
+    best
+}
@@ -0,0 +1,122 @@
+use anyhow::Result;
+use figment::{
+    providers::{Format, Toml},
+    Figment,
+};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::device::touch::TriggerCorner;
+use crate::workflow::{page_manager::UiLayout, response_format::ResponseFormat, CornerAction};
+
+/// Settings read from the optional TOML config file (--config)
+///
+/// ```toml
+/// instruction = "Answer in German"
+///
+/// [corners]
+/// LR = "answer"
+/// UL = "define"
+///
+/// [ui_layout]
+/// preset = "3.x"
+/// new_page = [[700, 50], [650, 400], [384, 450], [100, 900]]
+/// delete_page = [[40, 115], [384, 430], [384, 560], [470, 600]]
+/// open_search = [[40, 45], [384, 45]]
+/// search_result = [[384, 200]]
+///
+/// [response_format]
+/// separator = "==="
+/// answer = "REPLY:"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    /// Extra instruction/persona appended to every prompt; --instruction,
+    /// READER_BUDDY_INSTRUCTION and --instruction-file take precedence
+    pub instruction: Option<String>,
+    /// Corner name (UR, UL, LR, LL) -> action name
+    #[serde(default)]
+    pub corners: BTreeMap<String, String>,
+    #[serde(default)]
+    pub ui_layout: UiLayoutConfig,
+    /// Response labels/separator; unset fields keep their defaults
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+}
+
+/// xochitl menu positions: a preset, with optional per-step tap overrides
+#[derive(Debug, Default, Deserialize)]
+pub struct UiLayoutConfig {
+    /// Built-in layout to start from (see `UiLayout::preset`)
+    pub preset: Option<String>,
+    /// Taps that add a page after the current one, replacing the preset's
+    pub new_page: Option<Vec<(i32, i32)>>,
+    /// Taps that delete the current page, replacing the preset's
+    pub delete_page: Option<Vec<(i32, i32)>>,
+    /// Taps from a document to the library search field, replacing the preset's
+    pub open_search: Option<Vec<(i32, i32)>>,
+    /// Taps that open the first search result, replacing the preset's
+    pub search_result: Option<Vec<(i32, i32)>>,
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            anyhow::bail!("Config file not found: {}", path.display());
+        }
+        Ok(Figment::new().merge(Toml::file(path)).extract()?)
+    }
+
+    /// Parsed [corners] bindings; fails on unknown corner or action names
+    pub fn corner_bindings(&self) -> Result<Vec<(TriggerCorner, CornerAction)>> {
+        self.corners
+            .iter()
+            .map(|(corner, action)| {
+                Ok((
+                    TriggerCorner::from_string(corner)?,
+                    CornerAction::from_string(action)?,
+                ))
+            })
+            .collect()
+    }
+
+    /// The [ui_layout] preset with its overrides applied; `preset` (--ui-layout)
+    /// takes precedence over the file's preset
+    pub fn ui_layout(&self, preset: Option<&str>) -> Result<UiLayout> {
+        let mut layout = match preset.or(self.ui_layout.preset.as_deref()) {
+            Some(name) => UiLayout::preset(name)?,
+            None => UiLayout::default(),
+        };
+        if let Some(taps) = &self.ui_layout.new_page {
+            layout.new_page = Self::checked_taps("new_page", taps)?;
+        }
+        if let Some(taps) = &self.ui_layout.delete_page {
+            layout.delete_page = Self::checked_taps("delete_page", taps)?;
+        }
+        if let Some(taps) = &self.ui_layout.open_search {
+            layout.open_search = Self::checked_taps("open_search", taps)?;
+        }
+        if let Some(taps) = &self.ui_layout.search_result {
+            layout.search_result = Self::checked_taps("search_result", taps)?;
+        }
+        Ok(layout)
+    }
+
+    /// `taps` if all are on the 768x1024 screen
+    fn checked_taps(step: &str, taps: &[(i32, i32)]) -> Result<Vec<(i32, i32)>> {
+        if let Some(&(x, y)) = taps
+            .iter()
+            .find(|(x, y)| !(0..768).contains(x) || !(0..1024).contains(y))
+        {
+            anyhow::bail!(
+                "[ui_layout] {} tap ({}, {}) is outside the 768x1024 screen",
+                step,
+                x,
+                y
+            );
+        }
+        Ok(taps.to_vec())
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::Result;
+use log::{debug, warn};
+use serde_json::Value as JsonValue;
+use std::fs;
+use std::path::Path;
+
+/// Where xochitl keeps notebook files (<uuid>.metadata, <uuid>.content, <uuid>/*.rm)
+pub const XOCHITL_DATA_DIR: &str = "/home/root/.local/share/remarkable/xochitl";
+
+/// A notebook or PDF/EPUB as listed in xochitl's metadata files
+#[derive(Debug, Clone)]
+pub struct DocumentInfo {
+    pub id: String,
+    pub visible_name: String,
+    /// Milliseconds since the epoch, as recorded by xochitl
+    pub last_modified: u64,
+    /// Milliseconds since the epoch the document was last opened (0 if never recorded)
+    pub last_opened: u64,
+}
+
+/// Find a document by the name shown in the xochitl library
+///
+/// Exact matches win over case-insensitive ones. Deleted and trashed
+/// documents and folders are ignored; if several documents share the name
+/// the most recently modified one is returned.
+pub fn find_document_by_name(name: &str) -> Result<Option<DocumentInfo>> {
+    find_document_in(Path::new(XOCHITL_DATA_DIR), name)
+}
+
+/// The document most recently opened in xochitl, normally the one on screen
+///
+/// xochitl writes lastOpened when a document is opened, so this is a best guess:
+/// it can lag behind until the metadata is flushed to disk.
+pub fn current_document() -> Result<Option<DocumentInfo>> {
+    let mut latest: Option<DocumentInfo> = None;
+    for entry in fs::read_dir(XOCHITL_DATA_DIR)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("metadata") {
+            continue;
+        }
+        let Some(document) = read_metadata(&path) else {
+            continue;
+        };
+        if document.last_opened > latest.as_ref().map_or(0, |d| d.last_opened) {
+            latest = Some(document);
+        }
+    }
+    Ok(latest)
+}
+
+fn find_document_in(data_dir: &Path, name: &str) -> Result<Option<DocumentInfo>> {
+    let mut exact = Vec::new();
+    let mut case_insensitive = Vec::new();
+
+    for entry in fs::read_dir(data_dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("metadata") {
+            continue;
+        }
+        let Some(document) = read_metadata(&path) else {
+            continue;
+        };
+
+        if document.visible_name == name {
+            exact.push(document);
+        } else if document.visible_name.eq_ignore_ascii_case(name) {
+            case_insensitive.push(document);
+        }
+    }
+
+    let mut matches = if exact.is_empty() {
+        case_insensitive
+    } else {
+        exact
+    };
+    if matches.len() > 1 {
+        warn!(
+            "{} documents named '{}', using the most recently modified",
+            matches.len(),
+            name
+        );
+    }
+    matches.sort_by_key(|d| d.last_modified);
+    Ok(matches.pop())
+}
+
+/// Parse a .metadata file, skipping folders and deleted/trashed documents
+fn read_metadata(path: &Path) -> Option<DocumentInfo> {
+    let text = fs::read_to_string(path).ok()?;
+    let metadata: JsonValue = match serde_json::from_str(&text) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            debug!("Skipping unreadable metadata {}: {}", path.display(), e);
+            return None;
+        }
+    };
+
+    if metadata["type"].as_str() != Some("DocumentType")
+        || metadata["deleted"].as_bool() == Some(true)
+        || metadata["parent"].as_str() == Some("trash")
+    {
+        return None;
+    }
+
+    Some(DocumentInfo {
+        id: path.file_stem()?.to_string_lossy().to_string(),
+        visible_name: metadata["visibleName"].as_str()?.to_string(),
+        // xochitl stores this as a string of milliseconds
+        last_modified: metadata["lastModified"]
+            .as_str()
+            .and_then(|m| m.parse().ok())
+            .unwrap_or(0),
+        last_opened: metadata["lastOpened"]
+            .as_str()
+            .and_then(|m| m.parse().ok())
+            .unwrap_or(0),
+    })
+}
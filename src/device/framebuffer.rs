@@ -0,0 +1,224 @@
+use anyhow::Result;
+use log::{debug, info};
+use std::io::{Read, Seek};
+use std::process;
+
+use super::DeviceModel;
+
+/// Where raw framebuffer bytes come from
+/// Screenshot decodes whatever a source returns, so a canned source lets the
+/// decode/encode path run without a device
+pub trait FramebufferSource {
+    /// Read one raw frame of exactly `frame_len` bytes in the device's native format
+    fn read_frame(&mut self, device_model: DeviceModel, frame_len: usize) -> Result<Vec<u8>>;
+}
+
+/// Reads the live framebuffer out of xochitl's memory via /proc/<pid>/mem
+pub struct ProcMemFramebuffer;
+
+impl FramebufferSource for ProcMemFramebuffer {
+    fn read_frame(&mut self, device_model: DeviceModel, frame_len: usize) -> Result<Vec<u8>> {
+        // Find xochitl's process
+        debug!("screenshot: finding pid");
+        let pid = Self::find_xochitl_pid(device_model)?;
+
+        // Find framebuffer location in memory
+        debug!("screenshot: finding address");
+        let skip_bytes = Self::find_framebuffer_address(device_model, &pid, frame_len)?;
+
+        Self::read_framebuffer(&pid, skip_bytes, frame_len)
+    }
+}
+
+impl ProcMemFramebuffer {
+    /// The xochitl process that has the framebuffer mapped
+    /// Some setups run helper processes under the same name; reading the memory of
+    /// one of those gives garbage captures, so the first pid isn't good enough
+    fn find_xochitl_pid(device_model: DeviceModel) -> Result<String> {
+        let output = process::Command::new("pidof").arg("xochitl").output()?;
+        let pids = String::from_utf8(output.stdout)?;
+        let pids: Vec<&str> = pids.split_whitespace().collect();
+        if pids.is_empty() {
+            anyhow::bail!("No xochitl process found");
+        }
+
+        let device = Self::framebuffer_device(device_model);
+        for pid in &pids {
+            match std::fs::read_to_string(format!("/proc/{}/maps", pid)) {
+                Ok(maps) if maps.contains(device) => {
+                    if pids.len() > 1 {
+                        info!(
+                            "Using xochitl pid {} of {:?}: it has {} mapped",
+                            pid, pids, device
+                        );
+                    } else {
+                        debug!("Using xochitl pid {}: it has {} mapped", pid, device);
+                    }
+                    return Ok(pid.to_string());
+                }
+                Ok(_) => debug!("xochitl pid {} has no {} mapping, skipping", pid, device),
+                Err(e) => debug!("Could not read maps of xochitl pid {}: {}", pid, e),
+            }
+        }
+        anyhow::bail!(
+            "No xochitl process has {} mapped (pids: {})",
+            device,
+            pids.join(", ")
+        )
+    }
+
+    /// Device file whose mapping in xochitl's memory holds the framebuffer
+    fn framebuffer_device(device_model: DeviceModel) -> &'static str {
+        match device_model {
+            DeviceModel::RemarkablePaperPro => "/dev/dri/card0",
+            _ => "/dev/fb0",
+        }
+    }
+
+    fn find_framebuffer_address(
+        device_model: DeviceModel,
+        pid: &str,
+        frame_len: usize,
+    ) -> Result<u64> {
+        match device_model {
+            DeviceModel::RemarkablePaperPro => {
+                // For RMPP (arm64), we need to use the approach from pointer_arm64.go
+                let start_address = Self::get_memory_range(pid)?;
+                let frame_pointer = Self::calculate_frame_pointer(pid, start_address, frame_len)?;
+                Ok(frame_pointer)
+            }
+            _ => {
+                // Original RM2 approach
+                let output = process::Command::new("sh")
+                    .arg("-c")
+                    .arg(format!(
+                        "grep -C1 '/dev/fb0' /proc/{}/maps | tail -n1 | sed 's/-.*$//'",
+                        pid
+                    ))
+                    .output()?;
+                let address_hex = String::from_utf8(output.stdout)?.trim().to_string();
+                let address = u64::from_str_radix(&address_hex, 16)?;
+                Ok(address + 7)
+            }
+        }
+    }
+
+    // Get memory range for RMPP based on goMarkableStream/pointer_arm64.go
+    fn get_memory_range(pid: &str) -> Result<u64> {
+        let maps_file_path = format!("/proc/{}/maps", pid);
+        debug!("screenshot: reading memory range from {}", maps_file_path);
+        let maps_content = std::fs::read_to_string(&maps_file_path)?;
+
+        let mut memory_range = String::new();
+        debug!("Scanning for '/dev/dri/card0' in memory");
+        for line in maps_content.lines() {
+            if line.contains("/dev/dri/card0") {
+                memory_range = line.to_string();
+                debug!("Found memory range: {}", memory_range);
+            }
+        }
+
+        if memory_range.is_empty() {
+            anyhow::bail!("No mapping found for /dev/dri/card0");
+        }
+
+        debug!("Final memory range: {}", memory_range);
+        let fields: Vec<&str> = memory_range.split_whitespace().collect();
+        let range_field = fields[0];
+        let start_end: Vec<&str> = range_field.split('-').collect();
+
+        if start_end.len() != 2 {
+            anyhow::bail!("Invalid memory range format");
+        }
+
+        let end = u64::from_str_radix(start_end[1], 16)?;
+        debug!(
+            "range_field: {}\nstart_end: {}\nend: {}",
+            range_field, start_end[1], end
+        );
+        Ok(end)
+    }
+
+    // Calculate frame pointer for RMPP based on goMarkableStream/pointer_arm64.go
+    fn calculate_frame_pointer(pid: &str, start_address: u64, frame_len: usize) -> Result<u64> {
+        let mem_file_path = format!("/proc/{}/mem", pid);
+        let mut file = std::fs::File::open(mem_file_path)?;
+
+        let screen_size_bytes = frame_len as u64;
+
+        let mut offset: u64 = 0;
+        let mut length: u64 = 2;
+
+        while length < screen_size_bytes {
+            offset += length - 2;
+
+            file.seek(std::io::SeekFrom::Start(start_address + offset + 8))?;
+            let mut header = [0u8; 8];
+            file.read_exact(&mut header)?;
+            debug!("  ... header: {:?}", &header);
+
+            length = (header[0] as u64)
+                | ((header[1] as u64) << 8)
+                | ((header[2] as u64) << 16)
+                | ((header[3] as u64) << 24);
+            debug!("  ... length: {}", length);
+            if length < 2 {
+                anyhow::bail!("Invalid header length");
+            }
+        }
+
+        Ok(start_address + offset)
+    }
+
+    fn read_framebuffer(pid: &str, skip_bytes: u64, frame_len: usize) -> Result<Vec<u8>> {
+        let mut buffer = vec![0u8; frame_len];
+        let mut file = std::fs::File::open(format!("/proc/{}/mem", pid))?;
+        file.seek(std::io::SeekFrom::Start(skip_bytes))?;
+        file.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+/// Reads a framebuffer device such as /dev/fb0 (RM1, which has a real one)
+pub struct DevFramebuffer {
+    path: String,
+}
+
+impl DevFramebuffer {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl FramebufferSource for DevFramebuffer {
+    fn read_frame(&mut self, _device_model: DeviceModel, frame_len: usize) -> Result<Vec<u8>> {
+        let mut buffer = vec![0u8; frame_len];
+        let mut file = std::fs::File::open(&self.path)?;
+        file.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+/// Returns the same recorded frame on every read
+pub struct StaticFramebuffer {
+    frame: Vec<u8>,
+}
+
+impl StaticFramebuffer {
+    pub fn new(frame: Vec<u8>) -> Self {
+        Self { frame }
+    }
+}
+
+impl FramebufferSource for StaticFramebuffer {
+    fn read_frame(&mut self, _device_model: DeviceModel, frame_len: usize) -> Result<Vec<u8>> {
+        if self.frame.len() != frame_len {
+            anyhow::bail!(
+                "Static frame is {} bytes, expected {}",
+                self.frame.len(),
+                frame_len
+            );
+        }
+        Ok(self.frame.clone())
+    }
+}
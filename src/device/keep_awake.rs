@@ -0,0 +1,99 @@
+use anyhow::Result;
+use log::{debug, info};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, sleep, JoinHandle};
+use std::time::{Duration, Instant};
+
+#[cfg(target_os = "linux")]
+use evdev::{
+    uinput::VirtualDevice, AttributeSet, EventType as EvdevEventType, InputEvent,
+    KeyCode as EvdevKey,
+};
+
+/// Keeps the tablet from suspending while the main loop is running
+///
+/// xochitl resets its idle-suspend timer on any input event, so we periodically
+/// tap Shift on a dedicated virtual keyboard. A lone Shift tap does not modify
+/// the open document or move the page, so reading is not disturbed.
+pub struct KeepAwake {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl KeepAwake {
+    /// Start the keep-awake thread; it stops when the returned value is dropped
+    pub fn start(no_draw: bool, interval: Duration) -> Result<Self> {
+        info!("Keeping device awake (every {}s)", interval.as_secs());
+
+        let running = Arc::new(AtomicBool::new(true));
+
+        #[cfg(target_os = "linux")]
+        let mut device = if no_draw {
+            None
+        } else {
+            Some(Self::create_virtual_device()?)
+        };
+
+        #[cfg(not(target_os = "linux"))]
+        let _ = no_draw;
+
+        let thread_running = running.clone();
+        let handle = thread::spawn(move || {
+            let mut last_ping = Instant::now();
+            while thread_running.load(Ordering::Relaxed) {
+                // Sleep in short slices so dropping the guard doesn't block for a whole interval
+                sleep(Duration::from_millis(500));
+                if last_ping.elapsed() < interval {
+                    continue;
+                }
+                last_ping = Instant::now();
+
+                #[cfg(target_os = "linux")]
+                if let Some(device) = &mut device {
+                    if let Err(e) = Self::ping(device) {
+                        debug!("Keep-awake ping failed: {}", e);
+                    }
+                }
+
+                debug!("Keep-awake ping");
+            }
+        });
+
+        Ok(Self {
+            running,
+            handle: Some(handle),
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    fn create_virtual_device() -> Result<VirtualDevice> {
+        debug!("Creating keep-awake virtual keyboard");
+        let mut keys = AttributeSet::<EvdevKey>::new();
+        keys.insert(EvdevKey::KEY_LEFTSHIFT);
+
+        let device = VirtualDevice::builder()?
+            .name("Virtual Keep-Awake")
+            .with_keys(&keys)?
+            .build()?;
+        Ok(device)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn ping(device: &mut VirtualDevice) -> Result<()> {
+        let shift = EvdevKey::KEY_LEFTSHIFT.code();
+        device.emit(&[InputEvent::new(EvdevEventType::KEY.0, shift, 1)])?;
+        device.emit(&[InputEvent::new(EvdevEventType::KEY.0, shift, 0)])?;
+        device.emit(&[InputEvent::new(EvdevEventType::SYNCHRONIZATION.0, 0, 0)])?;
+        Ok(())
+    }
+}
+
+impl Drop for KeepAwake {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -15,27 +15,51 @@ use evdev::{
     KeyCode as EvdevKey,
 };
 
+// Navigation keys have no printable character, so they are mapped to
+// private-use code points that can be embedded in key strings
+pub const KEY_CHAR_LEFT: char = '\u{E000}';
+pub const KEY_CHAR_RIGHT: char = '\u{E001}';
+pub const KEY_CHAR_PAGE_UP: char = '\u{E002}';
+pub const KEY_CHAR_PAGE_DOWN: char = '\u{E003}';
+
+/// Where key events go: the virtual keyboard, or a recorder in tests
+#[cfg(target_os = "linux")]
+trait KeySink: Send {
+    fn emit(&mut self, events: &[InputEvent]) -> std::io::Result<()>;
+}
+
+#[cfg(target_os = "linux")]
+impl KeySink for VirtualDevice {
+    fn emit(&mut self, events: &[InputEvent]) -> std::io::Result<()> {
+        VirtualDevice::emit(self, events)
+    }
+}
+
 #[cfg(target_os = "linux")]
 pub struct Keyboard {
-    device: Option<evdev::uinput::VirtualDevice>,
+    device: Option<Box<dyn KeySink>>,
     key_map: HashMap<char, (EvdevKey, bool)>,
     progress_count: u32,
     no_draw_progress: bool,
+    /// Keys typed before pausing to let xochitl catch up (0 = never pause)
+    chunk_size: usize,
+    chunk_pause: time::Duration,
 }
 
 #[cfg(not(target_os = "linux"))]
 pub struct Keyboard {
     progress_count: u32,
     no_draw_progress: bool,
+    chunk_size: usize,
 }
 
 #[cfg(target_os = "linux")]
 impl Keyboard {
     pub fn new(no_draw: bool, no_draw_progress: bool) -> Self {
-        let device = if no_draw {
+        let device: Option<Box<dyn KeySink>> = if no_draw {
             None
         } else {
-            Some(Self::create_virtual_device())
+            Some(Box::new(Self::create_virtual_device()))
         };
 
         Self {
@@ -43,9 +67,23 @@ impl Keyboard {
             key_map: Self::create_key_map(),
             progress_count: 0,
             no_draw_progress,
+            chunk_size: 0,
+            chunk_pause: time::Duration::ZERO,
         }
     }
 
+    /// Type in chunks of `chunk_size` keys with a sync and `pause` after each
+    /// Some xochitl builds drop keys under sustained input; 0 types without pausing
+    pub fn set_chunking(&mut self, chunk_size: usize, pause: time::Duration) {
+        self.chunk_size = chunk_size;
+        self.chunk_pause = pause;
+    }
+
+    /// Keys per typing chunk (0 = no chunking)
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
     fn create_virtual_device() -> evdev::uinput::VirtualDevice {
         debug!("Creating virtual keyboard");
         let mut keys = AttributeSet::<EvdevKey>::new();
@@ -106,8 +144,17 @@ impl Keyboard {
         keys.insert(EvdevKey::KEY_SLASH);
 
         keys.insert(EvdevKey::KEY_BACKSPACE);
+        keys.insert(EvdevKey::KEY_DELETE);
         keys.insert(EvdevKey::KEY_ESC);
 
+        // Navigation keys
+        keys.insert(EvdevKey::KEY_LEFT);
+        keys.insert(EvdevKey::KEY_RIGHT);
+        keys.insert(EvdevKey::KEY_PAGEUP);
+        keys.insert(EvdevKey::KEY_PAGEDOWN);
+        keys.insert(EvdevKey::KEY_HOME);
+        keys.insert(EvdevKey::KEY_END);
+
         keys.insert(EvdevKey::KEY_LEFTCTRL);
         keys.insert(EvdevKey::KEY_LEFTALT);
 
@@ -236,6 +283,12 @@ impl Keyboard {
         key_map.insert('\x08', (EvdevKey::KEY_BACKSPACE, false));
         key_map.insert('\x1b', (EvdevKey::KEY_ESC, false));
 
+        // Navigation keys
+        key_map.insert(KEY_CHAR_LEFT, (EvdevKey::KEY_LEFT, false));
+        key_map.insert(KEY_CHAR_RIGHT, (EvdevKey::KEY_RIGHT, false));
+        key_map.insert(KEY_CHAR_PAGE_UP, (EvdevKey::KEY_PAGEUP, false));
+        key_map.insert(KEY_CHAR_PAGE_DOWN, (EvdevKey::KEY_PAGEDOWN, false));
+
         key_map
     }
 
@@ -245,6 +298,7 @@ impl Keyboard {
             device.emit(&[InputEvent::new(EvdevEventType::SYNCHRONIZATION.0, 0, 0)])?;
             thread::sleep(time::Duration::from_millis(10));
 
+            let mut typed = 0;
             for c in input.chars() {
                 if let Some(&(key, shift)) = self.key_map.get(&c) {
                     if shift {
@@ -274,6 +328,13 @@ impl Keyboard {
                     // Sync event
                     device.emit(&[InputEvent::new(EvdevEventType::SYNCHRONIZATION.0, 0, 0)])?;
                     thread::sleep(time::Duration::from_millis(10));
+
+                    typed += 1;
+                    if self.chunk_size > 0 && typed % self.chunk_size == 0 {
+                        device.emit(&[InputEvent::new(EvdevEventType::SYNCHRONIZATION.0, 0, 0)])?;
+                        debug!("Typed {} keys, pausing between chunks", typed);
+                        thread::sleep(self.chunk_pause);
+                    }
                 }
             }
         }
@@ -316,6 +377,54 @@ impl Keyboard {
         Ok(())
     }
 
+    /// Press and release a single key
+    pub fn press_key(&mut self, key: EvdevKey) -> Result<()> {
+        self.key_down(key)?;
+        self.key_up(key)?;
+        Ok(())
+    }
+
+    /// Move the text cursor to the start of the document (Ctrl+Home)
+    pub fn move_to_document_start(&mut self) -> Result<()> {
+        self.key_down(EvdevKey::KEY_LEFTCTRL)?;
+        self.press_key(EvdevKey::KEY_HOME)?;
+        self.key_up(EvdevKey::KEY_LEFTCTRL)?;
+        Ok(())
+    }
+
+    /// Move the text cursor to the end of the document (Ctrl+End)
+    pub fn move_to_document_end(&mut self) -> Result<()> {
+        self.key_down(EvdevKey::KEY_LEFTCTRL)?;
+        self.press_key(EvdevKey::KEY_END)?;
+        self.key_up(EvdevKey::KEY_LEFTCTRL)?;
+        Ok(())
+    }
+
+    /// Select all typed text on the page (Ctrl+A)
+    pub fn select_all(&mut self) -> Result<()> {
+        self.key_cmd("a", false)
+    }
+
+    /// Delete the selected text (or the character after the cursor if nothing is selected)
+    pub fn delete_selection(&mut self) -> Result<()> {
+        self.press_key(EvdevKey::KEY_DELETE)
+    }
+
+    /// Undo the last change on the page (Ctrl+Z)
+    pub fn undo(&mut self) -> Result<()> {
+        self.key_cmd("z", false)
+    }
+
+    /// Press the left arrow key (previous page in xochitl)
+    pub fn arrow_left(&mut self) -> Result<()> {
+        self.press_key(EvdevKey::KEY_LEFT)
+    }
+
+    /// Press the right arrow key (next page in xochitl)
+    pub fn arrow_right(&mut self) -> Result<()> {
+        self.press_key(EvdevKey::KEY_RIGHT)
+    }
+
     pub fn progress(&mut self, note: &str) -> Result<()> {
         if self.no_draw_progress {
             return Ok(());
@@ -344,9 +453,18 @@ impl Keyboard {
         Self {
             progress_count: 0,
             no_draw_progress,
+            chunk_size: 0,
         }
     }
 
+    pub fn set_chunking(&mut self, chunk_size: usize, _pause: std::time::Duration) {
+        self.chunk_size = chunk_size;
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
     pub fn string_to_keypresses(&mut self, _input: &str) -> Result<()> {
         Ok(())
     }
@@ -355,6 +473,34 @@ impl Keyboard {
         Ok(())
     }
 
+    pub fn move_to_document_start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn move_to_document_end(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn select_all(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn delete_selection(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn undo(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn arrow_left(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn arrow_right(&mut self) -> Result<()> {
+        Ok(())
+    }
+
     pub fn progress(&mut self, note: &str) -> Result<()> {
         if self.no_draw_progress {
             return Ok(());
@@ -371,3 +517,61 @@ impl Keyboard {
         Ok(())
     }
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Records (type, code, value) of every event
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<(u16, u16, i32)>>>);
+
+    impl KeySink for Recorder {
+        fn emit(&mut self, events: &[InputEvent]) -> std::io::Result<()> {
+            let mut recorded = self.0.lock().unwrap();
+            recorded.extend(
+                events
+                    .iter()
+                    .map(|event| (event.event_type().0, event.code(), event.value())),
+            );
+            Ok(())
+        }
+    }
+
+    fn key(key: EvdevKey, value: i32) -> (u16, u16, i32) {
+        (EvdevEventType::KEY.0, key.code(), value)
+    }
+
+    const SYN: (u16, u16, i32) = (0, 0, 0);
+
+    #[test]
+    fn shifted_characters_are_typed_with_shift_held() {
+        let recorder = Recorder::default();
+        let mut keyboard = Keyboard::new(true, false);
+        keyboard.device = Some(Box::new(recorder.clone()));
+
+        keyboard.string_to_keypresses("Hi!").unwrap();
+
+        let shift = EvdevKey::KEY_LEFTSHIFT;
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![
+                SYN,
+                key(shift, 1),
+                key(EvdevKey::KEY_H, 1),
+                key(EvdevKey::KEY_H, 0),
+                key(shift, 0),
+                SYN,
+                key(EvdevKey::KEY_I, 1),
+                key(EvdevKey::KEY_I, 0),
+                SYN,
+                key(shift, 1),
+                key(EvdevKey::KEY_1, 1),
+                key(EvdevKey::KEY_1, 0),
+                key(shift, 0),
+                SYN,
+            ]
+        );
+    }
+}
@@ -1,27 +1,56 @@
+pub mod documents;
+pub mod framebuffer;
+pub mod keep_awake;
 pub mod keyboard;
 pub mod pen;
 pub mod screenshot;
+pub mod speech;
 pub mod touch;
 
 use std::path::Path;
 
+/// Map `value` in 0..=`from_len` onto 0..=`to_max`, optionally mirrored
+///
+/// Rounds to the nearest unit and clamps, so points at the very edge of the screen
+/// (where the trigger corners are) never land outside the target axis range.
+#[cfg(target_os = "linux")]
+pub(crate) fn scale_axis(value: i32, from_len: u32, to_max: i32, mirror: bool) -> i32 {
+    let normalized = value as f32 / from_len as f32;
+    let normalized = if mirror { 1.0 - normalized } else { normalized };
+    ((normalized * to_max as f32).round() as i32).clamp(0, to_max)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DeviceModel {
+    Remarkable1,
     Remarkable2,
     RemarkablePaperPro,
     Unknown,
 }
 
+/// Firmware file naming the hardware revision
+pub const HWREVISION_PATH: &str = "/etc/hwrevision";
+
+/// Contents of /etc/hwrevision, or None if it is missing or unreadable
+pub fn hwrevision() -> Option<String> {
+    std::fs::read_to_string(HWREVISION_PATH)
+        .ok()
+        .map(|hwrev| hwrev.trim().to_string())
+}
+
 impl DeviceModel {
     pub fn detect() -> Self {
-        if Path::new("/etc/hwrevision").exists() {
-            if let Ok(hwrev) = std::fs::read_to_string("/etc/hwrevision") {
+        if Path::new(HWREVISION_PATH).exists() {
+            if let Ok(hwrev) = std::fs::read_to_string(HWREVISION_PATH) {
                 if hwrev.contains("ferrari 1.0") {
                     return DeviceModel::RemarkablePaperPro;
                 }
                 if hwrev.contains("reMarkable2 1.0") {
                     return DeviceModel::Remarkable2;
                 }
+                if hwrev.contains("reMarkable1") || hwrev.contains("zero-gravitas") {
+                    return DeviceModel::Remarkable1;
+                }
             }
         }
 
@@ -31,9 +60,64 @@ impl DeviceModel {
 
     pub fn name(&self) -> &str {
         match self {
+            DeviceModel::Remarkable1 => "Remarkable1",
             DeviceModel::Remarkable2 => "Remarkable2",
             DeviceModel::RemarkablePaperPro => "RemarkablePaperPro",
             DeviceModel::Unknown => "Unknown",
         }
     }
+
+    /// Input device the stylus events are written to
+    pub fn pen_input_path(&self) -> &'static str {
+        match self {
+            DeviceModel::Remarkable1 => "/dev/input/event0",
+            DeviceModel::Remarkable2 => "/dev/input/event1",
+            DeviceModel::RemarkablePaperPro => "/dev/input/event2",
+            DeviceModel::Unknown => "/dev/input/event1", // Default to RM2
+        }
+    }
+
+    /// Input device touches are read from and injected into
+    pub fn touch_input_path(&self) -> &'static str {
+        match self {
+            DeviceModel::Remarkable1 => "/dev/input/event1",
+            DeviceModel::Remarkable2 => "/dev/input/event2",
+            DeviceModel::RemarkablePaperPro => "/dev/input/event3",
+            DeviceModel::Unknown => "/dev/input/event2", // Default to RM2
+        }
+    }
+
+    /// Framebuffer device, for models that have a readable one
+    /// Later models only expose the screen through xochitl's memory
+    pub fn framebuffer_path(&self) -> Option<&'static str> {
+        match self {
+            DeviceModel::Remarkable1 => Some("/dev/fb0"),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_axis_round_trips_the_ends() {
+        for (from_max, to_max) in [(767, 1404), (1023, 1872), (767, 2065), (1023, 2833)] {
+            for mirror in [false, true] {
+                for value in [0, from_max] {
+                    let scaled = scale_axis(value, from_max as u32, to_max, mirror);
+                    assert_eq!(scale_axis(scaled, to_max as u32, from_max, mirror), value);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn scale_axis_mirrors_and_clamps() {
+        assert_eq!(scale_axis(0, 767, 1404, true), 1404);
+        assert_eq!(scale_axis(767, 767, 1404, true), 0);
+        assert_eq!(scale_axis(-10, 767, 1404, false), 0);
+        assert_eq!(scale_axis(800, 767, 1404, false), 1404);
+    }
 }
@@ -11,15 +11,45 @@ use std::time::Duration;
 use evdev::{Device, EventType as EvdevEventType, InputEvent};
 
 use super::DeviceModel;
+use crate::analysis::erase_path::EraseStroke;
+
+#[cfg(target_os = "linux")]
+use super::scale_axis;
 
 // Output dimensions remain the same for both devices
 const VIRTUAL_WIDTH: u32 = 768;
 const VIRTUAL_HEIGHT: u32 = 1024;
 
+/// Speed/fidelity tradeoff for bitmap drawing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DrawSpeed {
+    /// Whole runs of ink as single strokes, no pauses between them
+    Fast,
+    /// Whole runs of ink as single strokes, with short pauses
+    Normal,
+    /// Pixel by pixel with pauses after every stroke and row
+    Careful,
+}
+
+impl DrawSpeed {
+    pub fn from_string(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "fast" => Ok(DrawSpeed::Fast),
+            "normal" => Ok(DrawSpeed::Normal),
+            "careful" => Ok(DrawSpeed::Careful),
+            _ => Err(anyhow::anyhow!(
+                "Invalid draw speed: {}. Use fast, normal, or careful",
+                s
+            )),
+        }
+    }
+}
+
 #[cfg(target_os = "linux")]
 pub struct Pen {
     device: Option<Device>,
     device_model: DeviceModel,
+    draw_speed: DrawSpeed,
 }
 
 #[cfg(not(target_os = "linux"))]
@@ -33,11 +63,7 @@ impl Pen {
         let device_model = DeviceModel::detect();
         info!("Pen using device model: {}", device_model.name());
 
-        let pen_input_device = match device_model {
-            DeviceModel::Remarkable2 => "/dev/input/event1",
-            DeviceModel::RemarkablePaperPro => "/dev/input/event2",
-            DeviceModel::Unknown => "/dev/input/event1", // Default to RM2
-        };
+        let pen_input_device = device_model.pen_input_path();
 
         let device = if no_draw {
             None
@@ -48,9 +74,14 @@ impl Pen {
         Self {
             device,
             device_model,
+            draw_speed: DrawSpeed::Careful,
         }
     }
 
+    pub fn set_draw_speed(&mut self, draw_speed: DrawSpeed) {
+        self.draw_speed = draw_speed;
+    }
+
     pub fn draw_line_screen(&mut self, p1: (i32, i32), p2: (i32, i32)) -> Result<()> {
         self.draw_line(self.virtual_to_input(p1), self.virtual_to_input(p2))
     }
@@ -78,6 +109,46 @@ impl Pen {
     }
 
     pub fn draw_bitmap(&mut self, bitmap: &[Vec<bool>]) -> Result<()> {
+        match self.draw_speed {
+            DrawSpeed::Careful => self.draw_bitmap_careful(bitmap),
+            DrawSpeed::Normal => self.draw_bitmap_runs(bitmap, Duration::from_millis(1)),
+            DrawSpeed::Fast => self.draw_bitmap_runs(bitmap, Duration::ZERO),
+        }
+    }
+
+    /// Draw each horizontal run of ink as one stroke, skipping empty rows
+    /// `pause` is slept after each stroke and (5x) after each inked row
+    fn draw_bitmap_runs(&mut self, bitmap: &[Vec<bool>], pause: Duration) -> Result<()> {
+        for (y, row) in bitmap.iter().enumerate() {
+            let mut x = 0;
+            let mut drew = false;
+            while x < row.len() {
+                if !row[x] {
+                    x += 1;
+                    continue;
+                }
+                let start = x;
+                while x < row.len() && row[x] {
+                    x += 1;
+                }
+
+                self.goto_xy_virtual((start as i32, y as i32))?;
+                self.pen_down()?;
+                self.goto_xy_virtual((x as i32, y as i32))?;
+                self.pen_up()?;
+                drew = true;
+                if !pause.is_zero() {
+                    sleep(pause);
+                }
+            }
+            if drew && !pause.is_zero() {
+                sleep(pause * 5);
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_bitmap_careful(&mut self, bitmap: &[Vec<bool>]) -> Result<()> {
         let mut is_pen_down = false;
         for (y, row) in bitmap.iter().enumerate() {
             for (x, &pixel) in row.iter().enumerate() {
@@ -118,16 +189,71 @@ impl Pen {
                 self.draw_line_screen((x1, y), (x2, y))?;
             }
         } else {
-            // Draw outline only
-            self.draw_line_screen((x1, y1), (x2, y1))?; // Top
-            self.draw_line_screen((x2, y1), (x2, y2))?; // Right
-            self.draw_line_screen((x2, y2), (x1, y2))?; // Bottom
-            self.draw_line_screen((x1, y2), (x1, y1))?; // Left
+            // Draw outline only, as one closed stroke
+            self.draw_path(&[(x1, y1), (x2, y1), (x2, y2), (x1, y2), (x1, y1)])?;
+        }
+
+        Ok(())
+    }
+
+    /// Draw a single stroke through `points` (virtual coordinates)
+    /// Segments are interpolated so consecutive pen events are at most 5 input units apart
+    pub fn draw_path(&mut self, points: &[(i32, i32)]) -> Result<()> {
+        let Some(&first) = points.first() else {
+            return Ok(());
+        };
+
+        let mut previous = self.virtual_to_input(first);
+        self.pen_up()?;
+        self.goto_xy(previous)?;
+        self.pen_down()?;
+
+        for &point in &points[1..] {
+            let (x2, y2) = self.virtual_to_input(point);
+            let (x1, y1) = previous;
+            let length = ((x2 - x1) as f32).hypot((y2 - y1) as f32);
+            // 5.0 is the maximum distance between points, as in draw_line
+            let steps = ((length / 5.0).ceil() as i32).max(1);
+            for i in 1..=steps {
+                let t = i as f32 / steps as f32;
+                let x = x1 + ((x2 - x1) as f32 * t).round() as i32;
+                let y = y1 + ((y2 - y1) as f32 * t).round() as i32;
+                self.goto_xy((x, y))?;
+            }
+            previous = (x2, y2);
         }
 
+        self.pen_up()?;
         Ok(())
     }
 
+    /// Draw an arrow from `from` to `to` (virtual coordinates) with an open head at `to`
+    pub fn draw_arrow(&mut self, from: (i32, i32), to: (i32, i32)) -> Result<()> {
+        // Length of each head wing in virtual pixels (shorter for very short arrows)
+        const ARROW_HEAD_LENGTH: f32 = 15.0;
+
+        self.draw_path(&[from, to])?;
+
+        let (dx, dy) = ((to.0 - from.0) as f32, (to.1 - from.1) as f32);
+        let length = dx.hypot(dy);
+        if length < 1.0 {
+            return Ok(());
+        }
+
+        // Head wings point back along the shaft, ±30 degrees from it
+        let head = ARROW_HEAD_LENGTH.min(length / 3.0);
+        let angle = dy.atan2(dx);
+        let wing = |offset: f32| {
+            let a = angle + std::f32::consts::PI + offset;
+            (
+                to.0 + (head * a.cos()).round() as i32,
+                to.1 + (head * a.sin()).round() as i32,
+            )
+        };
+        let spread = 30f32.to_radians();
+        self.draw_path(&[wing(spread), to, wing(-spread)])
+    }
+
     pub fn pen_down(&mut self) -> Result<()> {
         if let Some(device) = &mut self.device {
             device.send_events(&[
@@ -208,6 +334,19 @@ impl Pen {
         Ok(())
     }
 
+    /// Erase along a list of straight strokes (virtual coordinates)
+    pub fn erase_strokes(&mut self, strokes: &[EraseStroke]) -> Result<()> {
+        info!("Erasing along {} strokes", strokes.len());
+        for &(start, end) in strokes {
+            self.eraser_up()?;
+            self.goto_xy_virtual(start)?;
+            self.eraser_down()?;
+            self.goto_xy_virtual(end)?;
+        }
+        self.eraser_up()?;
+        Ok(())
+    }
+
     pub fn goto_xy_virtual(&mut self, point: (i32, i32)) -> Result<()> {
         self.goto_xy(self.virtual_to_input(point))
     }
@@ -225,7 +364,7 @@ impl Pen {
 
     pub fn max_x_value(&self) -> i32 {
         match self.device_model {
-            DeviceModel::Remarkable2 => 15725,
+            DeviceModel::Remarkable1 | DeviceModel::Remarkable2 => 15725,
             DeviceModel::RemarkablePaperPro => 11180,
             DeviceModel::Unknown => 15725, // Default to RM2
         }
@@ -233,28 +372,23 @@ impl Pen {
 
     pub fn max_y_value(&self) -> i32 {
         match self.device_model {
-            DeviceModel::Remarkable2 => 20966,
+            DeviceModel::Remarkable1 | DeviceModel::Remarkable2 => 20966,
             DeviceModel::RemarkablePaperPro => 15340,
             DeviceModel::Unknown => 20966, // Default to RM2
         }
     }
 
     fn virtual_to_input(&self, (x, y): (i32, i32)) -> (i32, i32) {
-        // Swap and normalize the coordinates
-        let x_normalized = x as f32 / VIRTUAL_WIDTH as f32;
-        let y_normalized = y as f32 / VIRTUAL_HEIGHT as f32;
-
         match self.device_model {
-            DeviceModel::RemarkablePaperPro => {
-                let x_input = (x_normalized * self.max_x_value() as f32) as i32;
-                let y_input = (y_normalized * self.max_y_value() as f32) as i32;
-                (x_input, y_input)
-            }
-            _ => {
-                let x_input = ((1.0 - y_normalized) * self.max_y_value() as f32) as i32;
-                let y_input = (x_normalized * self.max_x_value() as f32) as i32;
-                (x_input, y_input)
-            }
+            DeviceModel::RemarkablePaperPro => (
+                scale_axis(x, VIRTUAL_WIDTH, self.max_x_value(), false),
+                scale_axis(y, VIRTUAL_HEIGHT, self.max_y_value(), false),
+            ),
+            // The digitizer is rotated: its x axis runs up the screen, its y axis across
+            _ => (
+                scale_axis(y, VIRTUAL_HEIGHT, self.max_y_value(), true),
+                scale_axis(x, VIRTUAL_WIDTH, self.max_x_value(), false),
+            ),
         }
     }
 }
@@ -268,6 +402,8 @@ impl Pen {
         Self { device_model }
     }
 
+    pub fn set_draw_speed(&mut self, _draw_speed: DrawSpeed) {}
+
     pub fn draw_line_screen(&mut self, _p1: (i32, i32), _p2: (i32, i32)) -> Result<()> {
         Ok(())
     }
@@ -289,6 +425,14 @@ impl Pen {
         Ok(())
     }
 
+    pub fn draw_path(&mut self, _points: &[(i32, i32)]) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn draw_arrow(&mut self, _from: (i32, i32), _to: (i32, i32)) -> Result<()> {
+        Ok(())
+    }
+
     pub fn erase_rectangle(
         &mut self,
         _top_left: (i32, i32),
@@ -296,4 +440,8 @@ impl Pen {
     ) -> Result<()> {
         Ok(())
     }
+
+    pub fn erase_strokes(&mut self, _strokes: &[EraseStroke]) -> Result<()> {
+        Ok(())
+    }
 }
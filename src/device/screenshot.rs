@@ -1,36 +1,107 @@
 use anyhow::Result;
 use image::GrayImage;
 use log::{debug, info};
+use std::borrow::Cow;
 use std::fs::File;
+use std::io::Cursor;
 use std::io::Write;
-use std::io::{Read, Seek};
-use std::process;
 
 use base64::{engine::general_purpose, Engine as _};
 use image::ImageEncoder;
 
+use super::framebuffer::{DevFramebuffer, FramebufferSource, ProcMemFramebuffer};
 use super::DeviceModel;
+use crate::analysis::preprocess::Preprocess;
 
 const VIRTUAL_WIDTH: u32 = 768;
 const VIRTUAL_HEIGHT: u32 = 1024;
 
+/// Color type of the encoded capture
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScreenshotColor {
+    /// Whatever the device shows: RGBA on the Paper Pro, grayscale elsewhere
+    Auto,
+    /// Always grayscale; smaller uploads, and color adds nothing for reading text
+    Grayscale,
+    /// Always RGBA
+    Color,
+}
+
+impl ScreenshotColor {
+    pub fn from_string(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(ScreenshotColor::Auto),
+            "grayscale" => Ok(ScreenshotColor::Grayscale),
+            "color" => Ok(ScreenshotColor::Color),
+            _ => Err(anyhow::anyhow!(
+                "Invalid screenshot color: {}. Use auto, grayscale, or color",
+                s
+            )),
+        }
+    }
+}
+
 pub struct Screenshot {
     data: Vec<u8>,
     device_model: DeviceModel,
+    color: ScreenshotColor,
+    max_image_dim: Option<u32>,
+    preprocess: Preprocess,
+    source: Box<dyn FramebufferSource>,
 }
 
 impl Screenshot {
     pub fn new() -> Result<Screenshot> {
         let device_model = DeviceModel::detect();
         info!("Screen detected device: {}", device_model.name());
-        Ok(Screenshot {
+        // RM1 has a real framebuffer device; later models only have xochitl's copy
+        let source: Box<dyn FramebufferSource> = match device_model.framebuffer_path() {
+            Some(path) => Box::new(DevFramebuffer::new(path)),
+            None => Box::new(ProcMemFramebuffer),
+        };
+        Ok(Self::with_source(device_model, source))
+    }
+
+    /// Capture from a specific framebuffer source instead of xochitl's memory
+    /// (e.g. a StaticFramebuffer holding a recorded frame)
+    pub fn with_source(
+        device_model: DeviceModel,
+        source: Box<dyn FramebufferSource>,
+    ) -> Screenshot {
+        Screenshot {
             data: vec![],
             device_model,
-        })
+            color: ScreenshotColor::Auto,
+            max_image_dim: None,
+            preprocess: Preprocess::default(),
+            source,
+        }
+    }
+
+    /// Choose grayscale or RGBA encoding instead of the device's own format
+    pub fn set_color(&mut self, color: ScreenshotColor) {
+        self.color = color;
+    }
+
+    /// Limit the longest edge of the image sent to the LLM (None = no limit)
+    /// This does not affect the 768x1024 drawing space used for coordinates
+    pub fn set_max_image_dim(&mut self, max_image_dim: Option<u32>) {
+        self.max_image_dim = max_image_dim;
+    }
+
+    /// Clean-up stages applied to the LLM-bound image only
+    /// The raw capture (used for saving and ink detection) is left untouched
+    pub fn set_preprocess(&mut self, preprocess: Preprocess) {
+        self.preprocess = preprocess;
+    }
+
+    pub fn has_preprocessing(&self) -> bool {
+        self.preprocess.is_enabled()
     }
 
     fn screen_width(&self) -> u32 {
         match self.device_model {
+            DeviceModel::Remarkable1 => 1408, // 1404 visible + 4 pixels of row padding
             DeviceModel::Remarkable2 => 1872,
             DeviceModel::RemarkablePaperPro => 1632,
             DeviceModel::Unknown => 1872, // Default to RM2
@@ -39,6 +110,7 @@ impl Screenshot {
 
     fn screen_height(&self) -> u32 {
         match self.device_model {
+            DeviceModel::Remarkable1 => 1872,
             DeviceModel::Remarkable2 => 1404,
             DeviceModel::RemarkablePaperPro => 2154,
             DeviceModel::Unknown => 1404, // Default to RM2
@@ -47,24 +119,19 @@ impl Screenshot {
 
     pub fn bytes_per_pixel(&self) -> usize {
         match self.device_model {
-            DeviceModel::Remarkable2 => 2,
+            DeviceModel::Remarkable1 | DeviceModel::Remarkable2 => 2,
             DeviceModel::RemarkablePaperPro => 4,
             DeviceModel::Unknown => 2, // Default to RM2
         }
     }
 
     pub fn take_screenshot(&mut self) -> Result<()> {
-        // Find xochitl's process
-        debug!("screenshot: finding pid");
-        let pid = Self::find_xochitl_pid()?;
-
-        // Find framebuffer location in memory
-        debug!("screenshot: finding address");
-        let skip_bytes = self.find_framebuffer_address(&pid)?;
+        let frame_len =
+            self.screen_width() as usize * self.screen_height() as usize * self.bytes_per_pixel();
 
-        // Read the framebuffer data
+        // Read the raw framebuffer data
         debug!("screenshot: reading data");
-        let screenshot_data = self.read_framebuffer(&pid, skip_bytes)?;
+        let screenshot_data = self.source.read_frame(self.device_model, frame_len)?;
         // Process the image data (transpose, color correction, etc.)
         debug!("screenshot: processing image");
         let processed_data = self.process_image(screenshot_data)?;
@@ -74,116 +141,28 @@ impl Screenshot {
         Ok(())
     }
 
-    fn find_xochitl_pid() -> Result<String> {
-        let output = process::Command::new("pidof").arg("xochitl").output()?;
-        let pids = String::from_utf8(output.stdout)?;
-        if let Some(pid) = pids.split_whitespace().next() {
-            return Ok(pid.to_string());
-        }
-        anyhow::bail!("No xochitl process found")
-    }
-
-    fn find_framebuffer_address(&self, pid: &str) -> Result<u64> {
-        match self.device_model {
-            DeviceModel::RemarkablePaperPro => {
-                // For RMPP (arm64), we need to use the approach from pointer_arm64.go
-                let start_address = self.get_memory_range(pid)?;
-                let frame_pointer = self.calculate_frame_pointer(pid, start_address)?;
-                Ok(frame_pointer)
-            }
-            _ => {
-                // Original RM2 approach
-                let output = process::Command::new("sh")
-                    .arg("-c")
-                    .arg(format!(
-                        "grep -C1 '/dev/fb0' /proc/{}/maps | tail -n1 | sed 's/-.*$//'",
-                        pid
-                    ))
-                    .output()?;
-                let address_hex = String::from_utf8(output.stdout)?.trim().to_string();
-                let address = u64::from_str_radix(&address_hex, 16)?;
-                Ok(address + 7)
-            }
-        }
-    }
-
-    // Get memory range for RMPP based on goMarkableStream/pointer_arm64.go
-    fn get_memory_range(&self, pid: &str) -> Result<u64> {
-        let maps_file_path = format!("/proc/{}/maps", pid);
-        debug!("screenshot: reading memory range from {}", maps_file_path);
-        let maps_content = std::fs::read_to_string(&maps_file_path)?;
-
-        let mut memory_range = String::new();
-        debug!("Scanning for '/dev/dri/card0' in memory");
-        for line in maps_content.lines() {
-            if line.contains("/dev/dri/card0") {
-                memory_range = line.to_string();
-                debug!("Found memory range: {}", memory_range);
-            }
-        }
-
-        if memory_range.is_empty() {
-            anyhow::bail!("No mapping found for /dev/dri/card0");
-        }
-
-        debug!("Final memory range: {}", memory_range);
-        let fields: Vec<&str> = memory_range.split_whitespace().collect();
-        let range_field = fields[0];
-        let start_end: Vec<&str> = range_field.split('-').collect();
-
-        if start_end.len() != 2 {
-            anyhow::bail!("Invalid memory range format");
-        }
-
-        let end = u64::from_str_radix(start_end[1], 16)?;
-        debug!(
-            "range_field: {}\nstart_end: {}\nend: {}",
-            range_field, start_end[1], end
-        );
-        Ok(end)
-    }
-
-    // Calculate frame pointer for RMPP based on goMarkableStream/pointer_arm64.go
-    fn calculate_frame_pointer(&self, pid: &str, start_address: u64) -> Result<u64> {
-        let mem_file_path = format!("/proc/{}/mem", pid);
-        let mut file = std::fs::File::open(mem_file_path)?;
-
-        let screen_size_bytes = self.screen_width() as u64
-            * self.screen_height() as u64
-            * self.bytes_per_pixel() as u64;
-
-        let mut offset: u64 = 0;
-        let mut length: u64 = 2;
-
-        while length < screen_size_bytes {
-            offset += length - 2;
-
-            file.seek(std::io::SeekFrom::Start(start_address + offset + 8))?;
-            let mut header = [0u8; 8];
-            file.read_exact(&mut header)?;
-            debug!("  ... header: {:?}", &header);
-
-            length = (header[0] as u64)
-                | ((header[1] as u64) << 8)
-                | ((header[2] as u64) << 16)
-                | ((header[3] as u64) << 24);
-            debug!("  ... length: {}", length);
-            if length < 2 {
-                anyhow::bail!("Invalid header length");
-            }
-        }
-
-        Ok(start_address + offset)
-    }
-
-    fn read_framebuffer(&self, pid: &str, skip_bytes: u64) -> Result<Vec<u8>> {
-        let window_bytes =
-            self.screen_width() as usize * self.screen_height() as usize * self.bytes_per_pixel();
-        let mut buffer = vec![0u8; window_bytes];
-        let mut file = std::fs::File::open(format!("/proc/{}/mem", pid))?;
-        file.seek(std::io::SeekFrom::Start(skip_bytes))?;
-        file.read_exact(&mut buffer)?;
-        Ok(buffer)
+    /// Use a saved PNG (e.g. a debug dump) as the current capture instead of the screen
+    /// It is scaled to the 768x1024 virtual screen like a live capture
+    pub fn load_png(&mut self, png_data: &[u8]) -> Result<()> {
+        let img = Self::correct_orientation(image::load_from_memory(png_data)?);
+        debug!("Loaded {}x{} image", img.width(), img.height());
+        let resized = img
+            .resize_exact(
+                VIRTUAL_WIDTH,
+                VIRTUAL_HEIGHT,
+                image::imageops::FilterType::Triangle,
+            )
+            .to_luma8();
+
+        let mut data = Vec::new();
+        image::codecs::png::PngEncoder::new(&mut data).write_image(
+            resized.as_raw(),
+            VIRTUAL_WIDTH,
+            VIRTUAL_HEIGHT,
+            image::ExtendedColorType::L8,
+        )?;
+        self.data = data;
+        Ok(())
     }
 
     fn process_image(&self, data: Vec<u8>) -> Result<Vec<u8>> {
@@ -193,7 +172,7 @@ impl Screenshot {
 
         // Resize the PNG to VIRTUAL_WIDTH x VIRTUAL_HEIGHT
         debug!("Resizing image to {}x{}", VIRTUAL_WIDTH, VIRTUAL_HEIGHT);
-        let img = image::load_from_memory(&png_data)?;
+        let img = Self::correct_orientation(image::load_from_memory(&png_data)?);
         let resized_img = img.resize_exact(
             VIRTUAL_WIDTH,
             VIRTUAL_HEIGHT,
@@ -205,35 +184,56 @@ impl Screenshot {
         let mut resized_png_data = Vec::new();
         let encoder = image::codecs::png::PngEncoder::new(&mut resized_png_data);
 
-        // Handle different color types based on device
-        match self.device_model {
-            DeviceModel::RemarkablePaperPro => {
-                encoder.write_image(
-                    resized_img.as_rgba8().unwrap().as_raw(),
-                    VIRTUAL_WIDTH,
-                    VIRTUAL_HEIGHT,
-                    image::ExtendedColorType::Rgba8,
-                )?;
-            }
-            _ => {
-                encoder.write_image(
-                    resized_img.as_luma8().unwrap().as_raw(),
-                    VIRTUAL_WIDTH,
-                    VIRTUAL_HEIGHT,
-                    image::ExtendedColorType::L8,
-                )?;
-            }
+        // Only the Paper Pro has a color screen; the others capture grayscale
+        let rgba = match self.color {
+            ScreenshotColor::Auto => self.device_model == DeviceModel::RemarkablePaperPro,
+            ScreenshotColor::Grayscale => false,
+            ScreenshotColor::Color => true,
+        };
+        if rgba {
+            encoder.write_image(
+                resized_img.to_rgba8().as_raw(),
+                VIRTUAL_WIDTH,
+                VIRTUAL_HEIGHT,
+                image::ExtendedColorType::Rgba8,
+            )?;
+        } else {
+            encoder.write_image(
+                resized_img.to_luma8().as_raw(),
+                VIRTUAL_WIDTH,
+                VIRTUAL_HEIGHT,
+                image::ExtendedColorType::L8,
+            )?;
         }
 
         Ok(resized_png_data)
     }
 
+    /// Make sure the capture is portrait, matching the 768x1024 virtual screen
+    /// Some xochitl states hand us a landscape framebuffer; resizing that as-is would
+    /// squash the page and throw off every coordinate the LLM returns
+    fn correct_orientation(img: image::DynamicImage) -> image::DynamicImage {
+        if img.width() > img.height() {
+            info!(
+                "Screenshot is landscape ({}x{}), rotating to portrait",
+                img.width(),
+                img.height()
+            );
+            return img.rotate90();
+        }
+        img
+    }
+
     fn encode_png(&self, raw_data: &[u8]) -> Result<Vec<u8>> {
         match self.device_model {
             DeviceModel::RemarkablePaperPro => {
                 // RMPP uses 32-bit RGBA format
                 self.encode_png_rmpp(raw_data)
             }
+            DeviceModel::Remarkable1 => {
+                // RM1 uses 16-bit RGB565, already in portrait orientation
+                self.encode_png_rm1(raw_data)
+            }
             _ => {
                 // RM2 uses 16-bit grayscale
                 self.encode_png_rm2(raw_data)
@@ -241,6 +241,36 @@ impl Screenshot {
         }
     }
 
+    fn encode_png_rm1(&self, raw_data: &[u8]) -> Result<Vec<u8>> {
+        const VISIBLE_WIDTH: u32 = 1404;
+        let width = self.screen_width();
+        let height = self.screen_height();
+
+        // RGB565 little-endian; e-ink is gray so the 6-bit green channel carries the level
+        let gray: Vec<u8> = raw_data
+            .chunks_exact(2)
+            .map(|chunk| {
+                let value = u16::from_le_bytes([chunk[0], chunk[1]]);
+                let green = ((value >> 5) & 0x3f) as u32;
+                (green * 255 / 63) as u8
+            })
+            .collect();
+
+        let img = GrayImage::from_raw(width, height, gray)
+            .ok_or_else(|| anyhow::anyhow!("Failed to create image from raw data"))?;
+        let visible = image::imageops::crop_imm(&img, 0, 0, VISIBLE_WIDTH, height).to_image();
+
+        let mut png_data = Vec::new();
+        let encoder = image::codecs::png::PngEncoder::new(&mut png_data);
+        encoder.write_image(
+            visible.as_raw(),
+            visible.width(),
+            visible.height(),
+            image::ExtendedColorType::L8,
+        )?;
+        Ok(png_data)
+    }
+
     fn encode_png_rm2(&self, raw_data: &[u8]) -> Result<Vec<u8>> {
         let raw_u8: Vec<u8> = raw_data
             .chunks_exact(2)
@@ -299,10 +329,64 @@ impl Screenshot {
     }
 
     pub fn base64(&self) -> Result<String> {
-        let base64_image = general_purpose::STANDARD.encode(&self.data);
+        let llm_image = self.llm_image_data()?;
+        let base64_image = general_purpose::STANDARD.encode(&llm_image);
         Ok(base64_image)
     }
 
+    /// PNG data as it should be sent to the LLM (preprocessed, then downscaled to max_image_dim)
+    pub fn llm_image_data(&self) -> Result<Cow<'_, [u8]>> {
+        if !self.preprocess.is_enabled() {
+            return self.limit_image_dim(&self.data);
+        }
+
+        let processed = self.preprocess.apply(&self.data)?;
+        Ok(Cow::Owned(self.limit_image_dim(&processed)?.into_owned()))
+    }
+
+    /// Encode the screenshot as a complete `data:image/png;base64,...` URI
+    /// Encodes straight into a pre-sized string, avoiding an intermediate base64 copy
+    pub fn data_uri(&self) -> Result<String> {
+        const PREFIX: &str = "data:image/png;base64,";
+        let llm_image = self.llm_image_data()?;
+        let mut uri = String::with_capacity(PREFIX.len() + llm_image.len().div_ceil(3) * 4);
+        uri.push_str(PREFIX);
+        general_purpose::STANDARD.encode_string(&llm_image, &mut uri);
+        Ok(uri)
+    }
+
+    /// Downscale the PNG so its longest edge fits within max_image_dim
+    fn limit_image_dim<'a>(&self, png_data: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        let Some(max_dim) = self.max_image_dim else {
+            return Ok(Cow::Borrowed(png_data));
+        };
+
+        let img = image::load_from_memory(png_data)?;
+        let (width, height) = (img.width(), img.height());
+        if width.max(height) <= max_dim {
+            debug!("LLM image dimensions: {}x{}", width, height);
+            return Ok(Cow::Borrowed(png_data));
+        }
+
+        // resize() preserves the aspect ratio within the given bounds
+        let resized_img = img.resize(max_dim, max_dim, image::imageops::FilterType::Triangle);
+        debug!(
+            "LLM image dimensions: {}x{} (downscaled from {}x{}, max {})",
+            resized_img.width(),
+            resized_img.height(),
+            width,
+            height,
+            max_dim
+        );
+
+        let mut resized_png_data = Vec::new();
+        resized_img.write_to(
+            &mut Cursor::new(&mut resized_png_data),
+            image::ImageFormat::Png,
+        )?;
+        Ok(Cow::Owned(resized_png_data))
+    }
+
     pub fn get_image_data(&self) -> &[u8] {
         &self.data
     }
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use log::{debug, info};
+use std::env;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Text-to-speech engines we know how to drive
+#[derive(Debug, Clone, PartialEq)]
+enum TtsEngine {
+    EspeakNg,
+    Espeak,
+    /// Piper needs a voice model; its raw audio is played with aplay
+    Piper {
+        model: PathBuf,
+    },
+}
+
+/// Speaks answers through a locally installed text-to-speech engine
+pub struct Speaker {
+    engine: TtsEngine,
+}
+
+impl Speaker {
+    /// Find an installed engine: piper when a voice model is given, otherwise espeak-ng or espeak
+    /// Returns None if none is available
+    pub fn detect(piper_model: Option<&Path>) -> Option<Self> {
+        let engine = match piper_model {
+            Some(model) if find_executable("piper") && find_executable("aplay") => {
+                TtsEngine::Piper {
+                    model: model.to_path_buf(),
+                }
+            }
+            _ if find_executable("espeak-ng") => TtsEngine::EspeakNg,
+            _ if find_executable("espeak") => TtsEngine::Espeak,
+            _ => return None,
+        };
+        info!("Text-to-speech engine: {:?}", engine);
+        Some(Self { engine })
+    }
+
+    /// Speak `text`, blocking until it has been played
+    pub fn speak(&self, text: &str) -> Result<()> {
+        debug!("Speaking {} characters", text.len());
+        match &self.engine {
+            TtsEngine::EspeakNg => run("espeak-ng", &[text]),
+            TtsEngine::Espeak => run("espeak", &[text]),
+            TtsEngine::Piper { model } => {
+                let mut piper = Command::new("piper")
+                    .arg("--model")
+                    .arg(model)
+                    .arg("--output_raw")
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::null())
+                    .spawn()?;
+                let audio = piper
+                    .stdout
+                    .take()
+                    .ok_or_else(|| anyhow::anyhow!("piper has no output"))?;
+                let mut aplay = Command::new("aplay")
+                    .args(["-q", "-r", "22050", "-f", "S16_LE", "-t", "raw", "-"])
+                    .stdin(audio)
+                    .spawn()?;
+
+                if let Some(mut stdin) = piper.stdin.take() {
+                    stdin.write_all(text.as_bytes())?;
+                }
+                piper.wait()?;
+                aplay.wait()?;
+                Ok(())
+            }
+        }
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    let status = Command::new(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    if !status.success() {
+        anyhow::bail!("{} exited with {}", program, status);
+    }
+    Ok(())
+}
+
+/// Whether `name` is an executable file somewhere on PATH
+fn find_executable(name: &str) -> bool {
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| dir.join(name).is_file()))
+        .unwrap_or(false)
+}
@@ -1,21 +1,28 @@
 use anyhow::Result;
 use log::info;
+use std::time::Duration;
 
 #[cfg(target_os = "linux")]
-use log::debug;
+use log::{debug, error};
 
 #[cfg(target_os = "linux")]
-use std::thread::sleep;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
 
 #[cfg(target_os = "linux")]
-use std::time::Duration;
+use std::thread::{self, sleep};
+
+#[cfg(target_os = "linux")]
+use std::time::Instant;
 
 #[cfg(target_os = "linux")]
 use evdev::{Device, EventType as EvdevEventType, InputEvent};
 
 use super::DeviceModel;
 
-#[derive(Debug, Clone)]
+#[cfg(target_os = "linux")]
+use super::scale_axis;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TriggerCorner {
     UpperRight,
     UpperLeft,
@@ -36,6 +43,32 @@ impl TriggerCorner {
             )),
         }
     }
+
+    /// Short name used in logs and config ("UR", "UL", "LR", "LL")
+    pub fn name(&self) -> &'static str {
+        match self {
+            TriggerCorner::UpperRight => "UR",
+            TriggerCorner::UpperLeft => "UL",
+            TriggerCorner::LowerRight => "LR",
+            TriggerCorner::LowerLeft => "LL",
+        }
+    }
+
+    /// Whether a virtual-space point falls in this corner's trigger zone
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        const CORNER_SIZE: i32 = 68; // Size of the trigger zone (68x68 pixels)
+        let right = x > VIRTUAL_WIDTH as i32 - CORNER_SIZE;
+        let left = x < CORNER_SIZE;
+        let top = y < CORNER_SIZE;
+        let bottom = y > VIRTUAL_HEIGHT as i32 - CORNER_SIZE;
+
+        match self {
+            TriggerCorner::UpperRight => right && top,
+            TriggerCorner::UpperLeft => left && top,
+            TriggerCorner::LowerRight => right && bottom,
+            TriggerCorner::LowerLeft => left && bottom,
+        }
+    }
 }
 
 // Output dimensions remain the same for both devices
@@ -55,14 +88,17 @@ const ABS_MT_PRESSURE: u16 = 58;
 #[cfg(target_os = "linux")]
 pub struct Touch {
     device: Option<Device>,
+    device_path: &'static str,
     device_model: DeviceModel,
-    trigger_corner: TriggerCorner,
+    trigger_corners: Vec<TriggerCorner>,
+    /// Touch release positions (raw input coordinates) from the listener thread
+    releases: Option<Receiver<(i32, i32)>>,
 }
 
 #[cfg(not(target_os = "linux"))]
 pub struct Touch {
     device_model: DeviceModel,
-    trigger_corner: TriggerCorner,
+    trigger_corners: Vec<TriggerCorner>,
 }
 
 #[cfg(target_os = "linux")]
@@ -71,11 +107,7 @@ impl Touch {
         let device_model = DeviceModel::detect();
         info!("Touch using device model: {}", device_model.name());
 
-        let device_path = match device_model {
-            DeviceModel::Remarkable2 => "/dev/input/event2",
-            DeviceModel::RemarkablePaperPro => "/dev/input/event3",
-            DeviceModel::Unknown => "/dev/input/event2", // Default to RM2
-        };
+        let device_path = device_model.touch_input_path();
 
         let device = if no_touch {
             None
@@ -85,44 +117,124 @@ impl Touch {
 
         Self {
             device,
+            device_path,
             device_model,
-            trigger_corner,
+            trigger_corners: vec![trigger_corner],
+            releases: None,
         }
     }
 
-    pub fn wait_for_trigger(&mut self) -> Result<()> {
-        let mut position_x = 0;
-        let mut position_y = 0;
+    /// Listen on several corners at once; wait_for_trigger reports which one fired
+    pub fn set_trigger_corners(&mut self, corners: Vec<TriggerCorner>) {
+        self.trigger_corners = corners;
+    }
+
+    pub fn trigger_corners(&self) -> &[TriggerCorner] {
+        &self.trigger_corners
+    }
+
+    /// Block until a touch is released in one of the trigger corners and return it
+    pub fn wait_for_trigger(&mut self) -> Result<TriggerCorner> {
         loop {
-            // Store events in a temporary vector to avoid borrowing issues
-            let mut events_to_process = Vec::new();
-            if let Some(device) = &mut self.device {
-                for event in device.fetch_events()? {
-                    events_to_process.push(event);
-                }
+            if let Some(corner) = self.poll_trigger(Duration::from_secs(1))? {
+                return Ok(corner);
             }
+        }
+    }
 
-            // Process the events after releasing the mutable borrow
-            for event in events_to_process {
-                if event.code() == ABS_MT_POSITION_X {
-                    position_x = event.value();
-                }
-                if event.code() == ABS_MT_POSITION_Y {
-                    position_y = event.value();
-                }
-                if event.code() == ABS_MT_TRACKING_ID && event.value() == -1 {
-                    let (x, y) = self.input_to_virtual((position_x, position_y));
-                    debug!(
-                        "Touch release detected at ({}, {}) normalized ({}, {})",
-                        position_x, position_y, x, y
-                    );
-                    if self.is_in_trigger_zone(x, y) {
-                        debug!("Touch release in target zone!");
-                        return Ok(());
+    /// Wait up to `timeout` for a touch release in one of the trigger corners
+    /// Returns None if nothing triggered in time, so callers can do other work between polls
+    pub fn poll_trigger(&mut self, timeout: Duration) -> Result<Option<TriggerCorner>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Some((x, y)) = self.poll_tap(remaining)? else {
+                return Ok(None);
+            };
+            if let Some(corner) = self.trigger_corner_at(x, y) {
+                debug!("Touch release in target zone {}!", corner.name());
+                return Ok(Some(corner));
+            }
+        }
+    }
+
+    /// Block until the user lifts a finger anywhere and return where (virtual coordinates)
+    pub fn wait_for_tap(&mut self) -> Result<(i32, i32)> {
+        if self.device.is_none() {
+            anyhow::bail!("Touch input is disabled (--no-draw); can't wait for a tap");
+        }
+        loop {
+            if let Some(position) = self.poll_tap(Duration::from_secs(1))? {
+                return Ok(position);
+            }
+        }
+    }
+
+    /// Wait up to `timeout` for a touch release anywhere on the screen
+    /// Returns its position in virtual coordinates, or None if nothing was released in time
+    pub fn poll_tap(&mut self, timeout: Duration) -> Result<Option<(i32, i32)>> {
+        if self.device.is_none() {
+            sleep(timeout);
+            return Ok(None);
+        }
+        if self.releases.is_none() {
+            self.releases = Some(Self::start_release_listener(self.device_path)?);
+        }
+
+        let received = match &self.releases {
+            Some(releases) => releases.recv_timeout(timeout),
+            None => return Ok(None),
+        };
+        let (position_x, position_y) = match received {
+            Ok(position) => position,
+            Err(RecvTimeoutError::Timeout) => return Ok(None),
+            Err(RecvTimeoutError::Disconnected) => {
+                self.releases = None;
+                anyhow::bail!("Touch listener stopped");
+            }
+        };
+
+        let (x, y) = self.input_to_virtual((position_x, position_y));
+        debug!(
+            "Touch release detected at ({}, {}) normalized ({}, {})",
+            position_x, position_y, x, y
+        );
+        Ok(Some((x, y)))
+    }
+
+    /// Read the touch device on a background thread and report every touch release
+    /// Uses its own handle, since fetch_events blocks and the main handle also sends events
+    fn start_release_listener(device_path: &str) -> Result<Receiver<(i32, i32)>> {
+        let mut device = Device::open(device_path)?;
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut position_x = 0;
+            let mut position_y = 0;
+            loop {
+                let events = match device.fetch_events() {
+                    Ok(events) => events,
+                    Err(e) => {
+                        error!("Touch listener failed: {}", e);
+                        return;
+                    }
+                };
+                for event in events {
+                    if event.code() == ABS_MT_POSITION_X {
+                        position_x = event.value();
+                    }
+                    if event.code() == ABS_MT_POSITION_Y {
+                        position_y = event.value();
+                    }
+                    if event.code() == ABS_MT_TRACKING_ID
+                        && event.value() == -1
+                        && sender.send((position_x, position_y)).is_err()
+                    {
+                        return;
                     }
                 }
             }
-        }
+        });
+        Ok(receiver)
     }
 
     pub fn touch_start(&mut self, xy: (i32, i32)) -> Result<()> {
@@ -177,21 +289,16 @@ impl Touch {
         Ok(())
     }
 
-    fn is_in_trigger_zone(&self, x: i32, y: i32) -> bool {
-        const CORNER_SIZE: i32 = 68; // Size of the trigger zone (68x68 pixels)
-
-        match self.trigger_corner {
-            TriggerCorner::UpperRight => x > VIRTUAL_WIDTH as i32 - CORNER_SIZE && y < CORNER_SIZE,
-            TriggerCorner::UpperLeft => x < CORNER_SIZE && y < CORNER_SIZE,
-            TriggerCorner::LowerRight => {
-                x > VIRTUAL_WIDTH as i32 - CORNER_SIZE && y > VIRTUAL_HEIGHT as i32 - CORNER_SIZE
-            }
-            TriggerCorner::LowerLeft => x < CORNER_SIZE && y > VIRTUAL_HEIGHT as i32 - CORNER_SIZE,
-        }
+    fn trigger_corner_at(&self, x: i32, y: i32) -> Option<TriggerCorner> {
+        self.trigger_corners
+            .iter()
+            .copied()
+            .find(|corner| corner.contains(x, y))
     }
 
     fn screen_width(&self) -> u32 {
         match self.device_model {
+            DeviceModel::Remarkable1 => 767,
             DeviceModel::Remarkable2 => 1404,
             DeviceModel::RemarkablePaperPro => 2065,
             DeviceModel::Unknown => 1404, // Default to RM2
@@ -200,50 +307,50 @@ impl Touch {
 
     fn screen_height(&self) -> u32 {
         match self.device_model {
+            DeviceModel::Remarkable1 => 1023,
             DeviceModel::Remarkable2 => 1872,
             DeviceModel::RemarkablePaperPro => 2833,
             DeviceModel::Unknown => 1872, // Default to RM2
         }
     }
 
-    fn virtual_to_input(&self, (x, y): (i32, i32)) -> (i32, i32) {
-        // Swap and normalize the coordinates
-        let x_normalized = x as f32 / VIRTUAL_WIDTH as f32;
-        let y_normalized = y as f32 / VIRTUAL_HEIGHT as f32;
-
+    /// Whether the touch panel's x and y axes run opposite to the virtual screen's
+    fn axis_mirroring(&self) -> (bool, bool) {
         match self.device_model {
-            DeviceModel::RemarkablePaperPro => {
-                let x_input = (x_normalized * self.screen_width() as f32) as i32;
-                let y_input = (y_normalized * self.screen_height() as f32) as i32;
-                (x_input, y_input)
-            }
-            _ => {
-                // RM2 coordinate transformation
-                let x_input = (x_normalized * self.screen_width() as f32) as i32;
-                let y_input = ((1.0 - y_normalized) * self.screen_height() as f32) as i32;
-                (x_input, y_input)
-            }
+            DeviceModel::RemarkablePaperPro => (false, false),
+            // RM1 touch panel is mirrored on both axes
+            DeviceModel::Remarkable1 => (true, true),
+            // RM2 coordinate transformation
+            _ => (false, true),
         }
     }
 
-    fn input_to_virtual(&self, (x, y): (i32, i32)) -> (i32, i32) {
-        // Swap and normalize the coordinates
-        let x_normalized = x as f32 / self.screen_width() as f32;
-        let y_normalized = y as f32 / self.screen_height() as f32;
+    /// Virtual screen position to panel coordinates; the inverse of input_to_virtual,
+    /// with the last virtual pixel on the panel's edge
+    fn virtual_to_input(&self, (x, y): (i32, i32)) -> (i32, i32) {
+        let (mirror_x, mirror_y) = self.axis_mirroring();
+        (
+            scale_axis(
+                x,
+                VIRTUAL_WIDTH as u32 - 1,
+                self.screen_width() as i32,
+                mirror_x,
+            ),
+            scale_axis(
+                y,
+                VIRTUAL_HEIGHT as u32 - 1,
+                self.screen_height() as i32,
+                mirror_y,
+            ),
+        )
+    }
 
-        match self.device_model {
-            DeviceModel::RemarkablePaperPro => {
-                let x_input = (x_normalized * VIRTUAL_WIDTH as f32) as i32;
-                let y_input = (y_normalized * VIRTUAL_HEIGHT as f32) as i32;
-                (x_input, y_input)
-            }
-            _ => {
-                // RM2 coordinate transformation
-                let x_input = (x_normalized * VIRTUAL_WIDTH as f32) as i32;
-                let y_input = ((1.0 - y_normalized) * VIRTUAL_HEIGHT as f32) as i32;
-                (x_input, y_input)
-            }
-        }
+    fn input_to_virtual(&self, (x, y): (i32, i32)) -> (i32, i32) {
+        let (mirror_x, mirror_y) = self.axis_mirroring();
+        (
+            scale_axis(x, self.screen_width(), VIRTUAL_WIDTH as i32 - 1, mirror_x),
+            scale_axis(y, self.screen_height(), VIRTUAL_HEIGHT as i32 - 1, mirror_y),
+        )
     }
 }
 
@@ -255,12 +362,36 @@ impl Touch {
 
         Self {
             device_model,
-            trigger_corner,
+            trigger_corners: vec![trigger_corner],
         }
     }
 
-    pub fn wait_for_trigger(&mut self) -> Result<()> {
-        Ok(())
+    pub fn set_trigger_corners(&mut self, corners: Vec<TriggerCorner>) {
+        self.trigger_corners = corners;
+    }
+
+    pub fn trigger_corners(&self) -> &[TriggerCorner] {
+        &self.trigger_corners
+    }
+
+    pub fn wait_for_trigger(&mut self) -> Result<TriggerCorner> {
+        self.trigger_corners
+            .first()
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("No trigger corners configured"))
+    }
+
+    pub fn poll_trigger(&mut self, _timeout: Duration) -> Result<Option<TriggerCorner>> {
+        Ok(self.trigger_corners.first().copied())
+    }
+
+    pub fn wait_for_tap(&mut self) -> Result<(i32, i32)> {
+        anyhow::bail!("Touch input is only available on the reMarkable")
+    }
+
+    pub fn poll_tap(&mut self, timeout: Duration) -> Result<Option<(i32, i32)>> {
+        std::thread::sleep(timeout);
+        Ok(None)
     }
 
     pub fn touch_start(&mut self, _xy: (i32, i32)) -> Result<()> {
@@ -279,3 +410,50 @@ impl Touch {
         Ok(())
     }
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    fn touch(device_model: DeviceModel) -> Touch {
+        Touch {
+            device: None,
+            device_path: device_model.touch_input_path(),
+            device_model,
+            trigger_corners: Vec::new(),
+            releases: None,
+        }
+    }
+
+    #[test]
+    fn corners_round_trip_for_every_model() {
+        let corners = [(0, 0), (767, 0), (0, 1023), (767, 1023)];
+        for model in [
+            DeviceModel::Remarkable1,
+            DeviceModel::Remarkable2,
+            DeviceModel::RemarkablePaperPro,
+            DeviceModel::Unknown,
+        ] {
+            let touch = touch(model);
+            for corner in corners {
+                let input = touch.virtual_to_input(corner);
+                assert_eq!(touch.input_to_virtual(input), corner, "{:?}", model);
+            }
+        }
+    }
+
+    #[test]
+    fn remarkable1_panel_is_mirrored() {
+        let touch = touch(DeviceModel::Remarkable1);
+        assert_eq!(touch.virtual_to_input((0, 0)), (767, 1023));
+        assert_eq!(touch.virtual_to_input((767, 1023)), (0, 0));
+        assert_eq!(touch.input_to_virtual((767, 0)), (0, 1023));
+    }
+
+    #[test]
+    fn remarkable2_panel_is_mirrored_vertically() {
+        let touch = touch(DeviceModel::Remarkable2);
+        assert_eq!(touch.virtual_to_input((0, 0)), (0, 1872));
+        assert_eq!(touch.virtual_to_input((767, 1023)), (1404, 0));
+    }
+}
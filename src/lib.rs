@@ -1,16 +1,34 @@
 pub mod analysis;
+pub mod config;
 pub mod device;
 pub mod llm;
 pub mod workflow;
 
 // Re-export commonly used types
-pub use analysis::BoundingBox;
+pub use analysis::{
+    answer_page::AnswerPageCheck, preprocess::Preprocess, quality::QualityCheck, AnalysisMetadata,
+    AnalysisResult, BoundingBox, QaItem, ThresholdMode,
+};
+pub use config::Config;
 pub use device::{
+    keep_awake::KeepAwake,
     keyboard::Keyboard,
-    pen::Pen,
-    screenshot::Screenshot,
+    pen::{DrawSpeed, Pen},
+    screenshot::{Screenshot, ScreenshotColor},
+    speech::Speaker,
     touch::{Touch, TriggerCorner},
     DeviceModel,
 };
-pub use llm::{openai::OpenAI, LLMEngine};
-pub use workflow::{orchestrator::Orchestrator, Workflow};
+pub use llm::{image_server::ImageServer, openai::OpenAI, LLMEngine, TokenUsage};
+pub use workflow::{
+    answer_stream::{AnswerStream, StreamPart},
+    orchestrator::{AnsweredItem, BenchmarkReport, Evaluation, IterationOutcome, Orchestrator},
+    page_manager::{NavMethod, SwipeConfig, UiLayout},
+    response_format::ResponseFormat,
+    script::{Action, Script},
+    symbol_pool::{SymbolPool, SymbolStyle},
+    watchdog::Watchdog,
+    webhook::Webhook,
+    AnswerFormat, AnswerOrder, AnswerTemplate, CornerAction, EraseMode, ErrorDisplay, InkLayout,
+    OnAmbiguous, PageCreateFallback, PostAnswerAction, Workflow,
+};
@@ -0,0 +1,115 @@
+use anyhow::Result;
+use log::{debug, info};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Publishes screenshots as files that the LLM provider can fetch by URL
+///
+/// Images are written to `dir` and referenced as `{base_url}/{file}`. Unless an
+/// external server is used, a minimal built-in HTTP server serves `dir`.
+pub struct ImageServer {
+    dir: PathBuf,
+    base_url: String,
+    last_file: Option<PathBuf>,
+}
+
+impl ImageServer {
+    /// Start the built-in HTTP server on `port`, serving files from `dir`
+    pub fn start(dir: impl Into<PathBuf>, base_url: &str, port: u16) -> Result<Self> {
+        let server = Self::external(dir, base_url)?;
+
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        info!(
+            "Serving screenshots from {} on port {}",
+            server.dir.display(),
+            port
+        );
+
+        let serve_dir = server.dir.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let result = stream
+                    .map_err(anyhow::Error::from)
+                    .and_then(|stream| Self::handle(stream, &serve_dir));
+                if let Err(e) = result {
+                    debug!("Image server request failed: {}", e);
+                }
+            }
+        });
+
+        Ok(server)
+    }
+
+    /// Publish into `dir` without starting a server (served by something else)
+    pub fn external(dir: impl Into<PathBuf>, base_url: &str) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            last_file: None,
+        })
+    }
+
+    /// Write a PNG and return the URL it can be fetched from
+    /// Only the most recent image is kept on disk
+    pub fn publish(&mut self, png_data: &[u8]) -> Result<String> {
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let filename = format!("screenshot-{}.png", millis);
+        let path = self.dir.join(&filename);
+        fs::write(&path, png_data)?;
+
+        if let Some(last_file) = self.last_file.replace(path) {
+            let _ = fs::remove_file(last_file);
+        }
+
+        let url = format!("{}/{}", self.base_url, filename);
+        debug!("Published screenshot at {}", url);
+        Ok(url)
+    }
+
+    fn handle(mut stream: TcpStream, dir: &Path) -> Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let mut request_line = String::new();
+        BufReader::new(stream.try_clone()?).read_line(&mut request_line)?;
+
+        // "GET /screenshot-123.png HTTP/1.1"
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or("");
+        let name = parts.next().unwrap_or("").trim_start_matches('/');
+        debug!("Image server: {} /{}", method, name);
+
+        // Only plain file names inside the image directory are served
+        let is_plain_name = !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+        let data = if method == "GET" && is_plain_name {
+            fs::read(dir.join(name)).ok()
+        } else {
+            None
+        };
+
+        match data {
+            Some(data) => {
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    data.len()
+                )?;
+                stream.write_all(&data)?;
+            }
+            None => {
+                stream.write_all(
+                    b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
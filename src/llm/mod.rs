@@ -1,10 +1,34 @@
+pub mod image_server;
 pub mod openai;
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Token usage reported by the provider for a single request
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
 
 pub trait LLMEngine {
     fn add_text_content(&mut self, text: &str);
     fn add_image_content(&mut self, base64_image: &str);
+    /// Add an image by URL (e.g. a complete data URI), taking ownership to avoid a copy
+    fn add_image_url(&mut self, url: String);
     fn clear_content(&mut self);
     fn execute(&mut self) -> Result<String>;
+
+    /// Like execute, but pass the answer to `on_chunk` piece by piece as it arrives
+    /// Returns the whole answer. The default hands over the finished answer in one
+    /// piece, for engines that can't stream.
+    fn execute_streaming(
+        &mut self,
+        on_chunk: &mut dyn FnMut(&str) -> Result<()>,
+    ) -> Result<String> {
+        let response = self.execute()?;
+        on_chunk(&response)?;
+        Ok(response)
+    }
 }
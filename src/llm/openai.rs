@@ -1,14 +1,109 @@
-use super::LLMEngine;
+use super::{LLMEngine, TokenUsage};
 use anyhow::Result;
-use log::{debug, info};
+use log::{debug, info, warn};
 use serde_json::json;
 use serde_json::Value as JsonValue;
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Model name fragments known to accept image input. OpenAI names first, then common
+// vision models served through OpenAI-compatible endpoints
+const VISION_MODEL_PATTERNS: &[&str] = &[
+    "gpt-4o",
+    "gpt-4.1",
+    "gpt-4.5",
+    "gpt-4-turbo",
+    "gpt-4-vision",
+    "gpt-5",
+    "o1",
+    "o3",
+    "o4",
+    "claude-3",
+    "claude-sonnet-4",
+    "claude-opus-4",
+    "gemini",
+    "llava",
+    "pixtral",
+    "vision",
+    "-vl",
+];
+
+// Text-only variants that would otherwise match a pattern above
+const TEXT_ONLY_MODEL_PATTERNS: &[&str] =
+    &["o1-mini", "o1-preview", "o3-mini", "audio", "realtime"];
+
+// Shown by list-models when the endpoint has no models list
+pub const KNOWN_MODELS: &[&str] = &[
+    "gpt-4o",
+    "gpt-4o-mini",
+    "gpt-4.1",
+    "gpt-4.1-mini",
+    "gpt-5",
+    "gpt-5-mini",
+    "o3",
+    "o4-mini",
+];
+
+const MAX_TOKENS: u64 = 4000;
+
+// Wait before the first retry of a failed request; doubled for each further retry
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Follow-up sent when a completion was cut off by max_tokens
+const CONTINUE_PROMPT: &str =
+    "Continue exactly where you left off. Do not repeat anything you already wrote.";
+
+/// Sends the HTTP requests of an OpenAI client, so tests can stand in for the server
+trait Transport: Send + Sync {
+    /// POST `body` as JSON to `url` with extra `headers`; returns the response body
+    fn post_json(
+        &self,
+        url: &str,
+        headers: &[(&str, String)],
+        body: &JsonValue,
+    ) -> std::result::Result<Box<dyn Read + Send>, ureq::Error>;
+}
+
+/// The real transport: requests go out through ureq
+struct UreqTransport;
+
+impl Transport for UreqTransport {
+    fn post_json(
+        &self,
+        url: &str,
+        headers: &[(&str, String)],
+        body: &JsonValue,
+    ) -> std::result::Result<Box<dyn Read + Send>, ureq::Error> {
+        let mut request = ureq::post(url);
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+        let response = request.send_json(body)?;
+        Ok(Box::new(response.into_body().into_reader()))
+    }
+}
 
 pub struct OpenAI {
     model: String,
     base_url: String,
     api_key: String,
+    organization: Option<String>,
+    project: Option<String>,
     content: Vec<JsonValue>,
+    last_usage: Option<TokenUsage>,
+    last_finish_reason: Option<String>,
+    /// Follow-up requests allowed when a completion hits max_tokens
+    max_continuations: u32,
+    /// Retries of a request that failed with a transient error
+    max_retries: u32,
+    /// Wait before the first retry (RETRY_BASE_DELAY; tests don't wait)
+    retry_base_delay: Duration,
+    payload_log_dir: Option<PathBuf>,
+    transport: Arc<dyn Transport>,
 }
 
 impl OpenAI {
@@ -19,22 +114,397 @@ impl OpenAI {
             model,
             base_url,
             api_key,
+            organization: None,
+            project: None,
             content: Vec::new(),
+            last_usage: None,
+            last_finish_reason: None,
+            max_continuations: 0,
+            max_retries: 3,
+            retry_base_delay: RETRY_BASE_DELAY,
+            payload_log_dir: None,
+            transport: Arc::new(UreqTransport),
         }
     }
 
+    /// A client with the same endpoint, auth and settings but its own request
+    /// content, so several requests can run at once (execute mutates the content)
+    pub fn worker(&self) -> Self {
+        Self {
+            model: self.model.clone(),
+            base_url: self.base_url.clone(),
+            api_key: self.api_key.clone(),
+            organization: self.organization.clone(),
+            project: self.project.clone(),
+            content: Vec::new(),
+            last_usage: None,
+            last_finish_reason: None,
+            max_continuations: self.max_continuations,
+            max_retries: self.max_retries,
+            retry_base_delay: self.retry_base_delay,
+            payload_log_dir: self.payload_log_dir.clone(),
+            transport: Arc::clone(&self.transport),
+        }
+    }
+
+    /// Send requests on behalf of an OpenAI organization (OpenAI-Organization header)
+    pub fn with_organization(mut self, organization: Option<String>) -> Self {
+        self.organization = organization;
+        self
+    }
+
+    /// Bill requests to an OpenAI project (OpenAI-Project header)
+    pub fn with_project(mut self, project: Option<String>) -> Self {
+        self.project = project;
+        self
+    }
+
+    /// Write each request (image elided) and raw response to a file in `dir`
+    pub fn with_payload_log(mut self, dir: Option<PathBuf>) -> Self {
+        self.payload_log_dir = dir;
+        self
+    }
+
+    /// Ask the model to continue up to `max_continuations` times when an answer is cut off
+    /// by max_tokens, joining the parts into one response (0 = return the truncated text)
+    pub fn with_max_continuations(mut self, max_continuations: u32) -> Self {
+        self.max_continuations = max_continuations;
+        self
+    }
+
+    /// Retry a request up to `max` times after a network error or HTTP 429/500/502/503,
+    /// waiting 500ms, 1s, 2s, ... in between (0 = fail on the first error)
+    pub fn with_retries(mut self, max: u32) -> Self {
+        self.max_retries = max;
+        self
+    }
+
     pub fn from_env(model: Option<String>) -> Result<Self> {
         let api_key = std::env::var("OPENAI_API_KEY")
             .map_err(|_| anyhow::anyhow!("OPENAI_API_KEY environment variable not set"))?;
         let base_url = std::env::var("OPENAI_BASE_URL").ok();
         let model = model.unwrap_or_else(|| "gpt-4o".to_string());
+        let organization = std::env::var("OPENAI_ORG_ID").ok();
+        let project = std::env::var("OPENAI_PROJECT_ID").ok();
+
+        Ok(Self::new(model, api_key, base_url)
+            .with_organization(organization)
+            .with_project(project))
+    }
+
+    /// Model used for requests
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Send later requests to a different model on the same endpoint and credentials
+    pub fn set_model(&mut self, model: &str) {
+        if self.model != model {
+            debug!("Switching model from {} to {}", self.model, model);
+            self.model = model.to_string();
+        }
+    }
+
+    /// Whether the model name looks like a vision-capable model
+    /// This is a name heuristic only; unknown models are reported as not vision-capable
+    pub fn supports_vision(&self) -> bool {
+        is_vision_model(&self.model)
+    }
+
+    /// Token usage reported for the most recent request (if the provider sent it)
+    pub fn last_usage(&self) -> Option<TokenUsage> {
+        self.last_usage
+    }
 
-        Ok(Self::new(model, api_key, base_url))
+    /// finish_reason of the most recent completion ("length" means it was cut off)
+    pub fn last_finish_reason(&self) -> Option<&str> {
+        self.last_finish_reason.as_deref()
     }
 
     pub fn add_content(&mut self, content: JsonValue) {
         self.content.push(content);
     }
+
+    /// Request content with inline image data replaced by a size placeholder
+    fn elided_content(&self) -> Vec<JsonValue> {
+        self.content
+            .iter()
+            .map(|item| match item["image_url"]["url"].as_str() {
+                Some(url) if url.starts_with("data:") => json!({
+                    "type": "image_url",
+                    "image_url": { "url": format!("<inline image, {} bytes>", url.len()) },
+                }),
+                _ => item.clone(),
+            })
+            .collect()
+    }
+
+    /// Write the request/response pair to a timestamped file in the payload log directory
+    fn write_payload_log(&self, response: &str) {
+        let Some(dir) = &self.payload_log_dir else {
+            return;
+        };
+
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let path = dir.join(format!("llm-{}.json", millis));
+
+        // The API key lives only in the Authorization header, which is never written
+        let entry = json!({
+            "timestamp_ms": millis as u64,
+            "url": format!("{}/v1/chat/completions", self.base_url),
+            "headers": {
+                "Authorization": "Bearer [REDACTED]",
+                "OpenAI-Organization": self.organization,
+                "OpenAI-Project": self.project,
+            },
+            "request": {
+                "model": self.model,
+                "messages": [{ "role": "user", "content": self.elided_content() }],
+                "max_tokens": MAX_TOKENS,
+            },
+            "response": response,
+        });
+
+        let result = fs::create_dir_all(dir).and_then(|_| {
+            fs::write(
+                &path,
+                serde_json::to_string_pretty(&entry).unwrap_or_default(),
+            )
+        });
+        match result {
+            Ok(()) => debug!("Wrote LLM payload log to {}", path.display()),
+            Err(e) => warn!("Failed to write LLM payload log {}: {}", path.display(), e),
+        }
+    }
+
+    /// Model ids offered by the endpoint (GET /v1/models), sorted
+    /// Fails if the endpoint rejects the key or has no models list
+    pub fn list_models(&self) -> Result<Vec<String>> {
+        let mut request = ureq::get(format!("{}/v1/models", self.base_url).as_str())
+            .header("Authorization", &format!("Bearer {}", self.api_key));
+        if let Some(organization) = &self.organization {
+            request = request.header("OpenAI-Organization", organization);
+        }
+        if let Some(project) = &self.project {
+            request = request.header("OpenAI-Project", project);
+        }
+
+        let mut response = request.call().map_err(|err| {
+            anyhow::anyhow!(
+                "API ERROR: {}",
+                redact_secrets(&err.to_string(), &self.api_key)
+            )
+        })?;
+        let json = parse_json_body(&response.body_mut().read_to_string()?, &self.api_key)?;
+        debug!("Models response: {}", json);
+
+        let mut models: Vec<String> = json["data"]
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("No model list in the response"))?
+            .iter()
+            .filter_map(|model| model["id"].as_str().map(str::to_string))
+            .collect();
+        models.sort();
+        Ok(models)
+    }
+
+    /// POST a chat completion request body; errors have the key redacted
+    fn post(&self, body: &JsonValue) -> Result<Box<dyn Read + Send>> {
+        // Log the request without the inline image; headers (and so the key) are never logged
+        debug!(
+            "Request: {}",
+            json!({
+                "model": self.model,
+                "messages": [{ "role": "user", "content": self.elided_content() }],
+            })
+        );
+        let url = format!("{}/v1/chat/completions", self.base_url);
+        let mut headers = vec![
+            ("Authorization", format!("Bearer {}", self.api_key)),
+            ("Content-Type", "application/json".to_string()),
+        ];
+        if let Some(organization) = &self.organization {
+            headers.push(("OpenAI-Organization", organization.clone()));
+        }
+        if let Some(project) = &self.project {
+            headers.push(("OpenAI-Project", project.clone()));
+        }
+
+        let mut retries = 0;
+        loop {
+            let err = match self.transport.post_json(&url, &headers, body) {
+                Ok(response) => return Ok(response),
+                Err(err) => err,
+            };
+            let message = redact_secrets(&err.to_string(), &self.api_key);
+            if retries < self.max_retries && is_transient(&err) {
+                // Capped so a large --api-retries can't overflow the delay
+                let delay = self.retry_base_delay * 2u32.pow(retries.min(6));
+                retries += 1;
+                warn!(
+                    "API request failed ({}); retrying in {}ms ({}/{})",
+                    message,
+                    delay.as_millis(),
+                    retries,
+                    self.max_retries
+                );
+                sleep(delay);
+                continue;
+            }
+
+            info!("API Error: {}", message);
+            self.write_payload_log(&format!("error: {}", message));
+            return Err(anyhow::anyhow!("API ERROR: {}", message));
+        }
+    }
+
+    /// POST one chat completion request and return the parsed response body
+    fn send(&mut self, messages: &[JsonValue]) -> Result<JsonValue> {
+        let body = json!({
+            "model": self.model,
+            "messages": messages,
+            "max_tokens": MAX_TOKENS
+        });
+        let mut response = self.post(&body)?;
+
+        // Read response body as string; a dropped connection or a proxy error page
+        // must surface as an error, not take the whole program down
+        let mut body_text = String::new();
+        response.read_to_string(&mut body_text).map_err(|e| {
+            self.write_payload_log(&format!("error: {}", e));
+            anyhow::anyhow!("Could not read the response body: {}", e)
+        })?;
+        self.write_payload_log(&body_text);
+        let json = parse_json_body(&body_text, &self.api_key)?;
+        debug!("Response: {}", json);
+        Ok(json)
+    }
+
+    /// Send the request with streaming ("stream": true) and return the response to
+    /// read the answer from
+    ///
+    /// The stream doesn't borrow the client, so the caller can go on using whatever
+    /// owns it while reading. Hand it to finish_streaming once done (or broken off)
+    /// to record the usage, finish reason and payload log.
+    pub fn start_streaming(&mut self) -> Result<ChatStream> {
+        let messages = vec![json!({ "role": "user", "content": self.content })];
+        let body = json!({
+            "model": self.model,
+            "messages": messages,
+            "max_tokens": MAX_TOKENS,
+            "stream": true,
+            "stream_options": { "include_usage": true },
+        });
+        let response = self.post(&body)?;
+
+        self.last_usage = None;
+        self.last_finish_reason = None;
+        // A blank line is added at the end, for servers that omit the one ending
+        // the last event
+        let lines = BufReader::new(response)
+            .lines()
+            .chain(std::iter::once(Ok(String::new())));
+        Ok(ChatStream {
+            lines: Box::new(lines),
+            api_key: self.api_key.clone(),
+            text: String::new(),
+            usage: None,
+            finish_reason: None,
+            error: None,
+            ended: false,
+        })
+    }
+
+    /// Record what a stream from start_streaming reported and log its payload
+    pub fn finish_streaming(&mut self, stream: ChatStream) {
+        match &stream.error {
+            Some(e) => self.write_payload_log(&format!("{}\nerror: {}", stream.text, e)),
+            None => self.write_payload_log(&stream.text),
+        }
+        self.last_usage = stream.usage;
+        self.last_finish_reason = stream.finish_reason;
+        if self.last_finish_reason.as_deref() == Some("length") {
+            warn!("Streamed response was cut off by max_tokens");
+        }
+    }
+}
+
+/// A streamed chat completion being read, from OpenAI::start_streaming
+pub struct ChatStream {
+    lines: Box<dyn Iterator<Item = std::io::Result<String>> + Send>,
+    /// To keep the key out of error messages
+    api_key: String,
+    text: String,
+    usage: Option<TokenUsage>,
+    finish_reason: Option<String>,
+    /// Why the stream broke off, for the payload log
+    error: Option<String>,
+    ended: bool,
+}
+
+impl ChatStream {
+    /// The next piece of the answer; None once the stream has ended
+    ///
+    /// Lines are read whole, so JSON split across network reads arrives in one
+    /// piece; an event may still span several data: lines, joined until the blank
+    /// line that ends it.
+    pub fn next_chunk(&mut self) -> Result<Option<String>> {
+        let mut data = String::new();
+        while !self.ended {
+            let Some(line) = self.lines.next() else {
+                break;
+            };
+            let line = line.map_err(|e| self.fail(format!("Response stream broke off: {}", e)))?;
+            if let Some(part) = line.strip_prefix("data:") {
+                if !data.is_empty() {
+                    data.push('\n');
+                }
+                data.push_str(part.strip_prefix(' ').unwrap_or(part));
+                continue;
+            }
+            // Other fields (event:, id:) and comments (keep-alives) carry no text
+            if !line.is_empty() || data.is_empty() {
+                continue;
+            }
+            let event = std::mem::take(&mut data);
+            if event.trim() == "[DONE]" {
+                break;
+            }
+            let json =
+                parse_json_body(&event, &self.api_key).map_err(|e| self.fail(e.to_string()))?;
+            if let Some(usage) = json.get("usage").filter(|usage| !usage.is_null()) {
+                self.usage = Some(TokenUsage {
+                    prompt_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0),
+                    completion_tokens: usage["completion_tokens"].as_u64().unwrap_or(0),
+                    total_tokens: usage["total_tokens"].as_u64().unwrap_or(0),
+                });
+            }
+            if let Some(reason) = json["choices"][0]["finish_reason"].as_str() {
+                self.finish_reason = Some(reason.to_string());
+            }
+            if let Some(part) = content_text(&json["choices"][0]["delta"]["content"]) {
+                self.text.push_str(&part);
+                return Ok(Some(part));
+            }
+        }
+        self.ended = true;
+        Ok(None)
+    }
+
+    /// The answer received so far
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// End the stream on an error, keeping it for the payload log
+    fn fail(&mut self, message: String) -> anyhow::Error {
+        self.ended = true;
+        self.error = Some(message.clone());
+        anyhow::anyhow!(message)
+    }
 }
 
 impl LLMEngine for OpenAI {
@@ -46,12 +516,18 @@ impl LLMEngine for OpenAI {
     }
 
     fn add_image_content(&mut self, base64_image: &str) {
-        self.add_content(json!({
-            "type": "image_url",
-            "image_url": {
-                "url": format!("data:image/png;base64,{}", base64_image)
-            }
-        }));
+        self.add_image_url(format!("data:image/png;base64,{}", base64_image));
+    }
+
+    fn add_image_url(&mut self, url: String) {
+        // Build the value by hand; json!() would serialize (and so copy) the url
+        let mut image_url = serde_json::Map::new();
+        image_url.insert("url".to_string(), JsonValue::String(url));
+
+        let mut content = serde_json::Map::new();
+        content.insert("type".to_string(), json!("image_url"));
+        content.insert("image_url".to_string(), JsonValue::Object(image_url));
+        self.add_content(JsonValue::Object(content));
     }
 
     fn clear_content(&mut self) {
@@ -59,41 +535,467 @@ impl LLMEngine for OpenAI {
     }
 
     fn execute(&mut self) -> Result<String> {
-        let body = json!({
-            "model": self.model,
-            "messages": [{
-                "role": "user",
-                "content": self.content
-            }],
-            "max_tokens": 4000
-        });
+        let mut messages = vec![json!({ "role": "user", "content": self.content })];
+        let mut response_text = String::new();
+        let mut usage: Option<TokenUsage> = None;
+        let mut continuations = 0;
+
+        loop {
+            let json = self.send(&messages)?;
+
+            if let Some(part) = json.get("usage").map(|usage| TokenUsage {
+                prompt_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0),
+                completion_tokens: usage["completion_tokens"].as_u64().unwrap_or(0),
+                total_tokens: usage["total_tokens"].as_u64().unwrap_or(0),
+            }) {
+                let total = usage.get_or_insert_with(TokenUsage::default);
+                total.prompt_tokens += part.prompt_tokens;
+                total.completion_tokens += part.completion_tokens;
+                total.total_tokens += part.total_tokens;
+            }
+            self.last_usage = usage;
+            self.last_finish_reason = json["choices"][0]["finish_reason"]
+                .as_str()
+                .map(str::to_string);
+
+            // Extract the response text
+            let part = response_content(&json).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No response content found in {}",
+                    redact_secrets(&json.to_string(), &self.api_key)
+                )
+            })?;
+            response_text.push_str(&part);
+
+            if self.last_finish_reason.as_deref() != Some("length") {
+                break;
+            }
+            if continuations >= self.max_continuations {
+                warn!(
+                    "Response was cut off by max_tokens after {} continuation(s)",
+                    continuations
+                );
+                break;
+            }
+
+            continuations += 1;
+            info!(
+                "Response was cut off by max_tokens, asking to continue ({}/{})",
+                continuations, self.max_continuations
+            );
+            messages.push(json!({ "role": "assistant", "content": part }));
+            messages.push(json!({ "role": "user", "content": CONTINUE_PROMPT }));
+        }
+
+        Ok(response_text)
+    }
 
-        // print body for debugging
-        debug!("Request: {}", body);
-        let raw_response = ureq::post(format!("{}/v1/chat/completions", self.base_url).as_str())
-            .header("Authorization", &format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .send_json(&body);
-
-        let mut response = match raw_response {
-            Ok(response) => response,
-            Err(err) => {
-                info!("API Error: {}", err);
-                return Err(anyhow::anyhow!("API ERROR: {}", err));
+    /// Streams with server-sent events ("stream": true), see start_streaming
+    ///
+    /// Unlike execute, an answer cut off by max_tokens is not continued, since the
+    /// chunks already handed out can't be taken back. An error from `on_chunk` stops
+    /// the stream and is returned.
+    fn execute_streaming(
+        &mut self,
+        on_chunk: &mut dyn FnMut(&str) -> Result<()>,
+    ) -> Result<String> {
+        let mut stream = self.start_streaming()?;
+        let mut read = || -> Result<()> {
+            while let Some(chunk) = stream.next_chunk()? {
+                on_chunk(&chunk)?;
             }
+            Ok(())
         };
+        let result = read();
+        let response_text = stream.text().to_string();
+        self.finish_streaming(stream);
+        result.map(|_| response_text)
+    }
+}
 
-        // Read response body as string
-        let body_text = response.body_mut().read_to_string().unwrap();
-        let json: JsonValue = serde_json::from_str(&body_text).unwrap();
-        debug!("Response: {}", json);
+/// Whether a failed request is worth retrying: network trouble, rate limiting, or a
+/// server error that is usually temporary. Client errors (400, 401, ...) fail fast.
+fn is_transient(err: &ureq::Error) -> bool {
+    match err {
+        ureq::Error::StatusCode(status) => matches!(status, 429 | 500 | 502 | 503),
+        ureq::Error::Io(_)
+        | ureq::Error::Timeout(_)
+        | ureq::Error::HostNotFound
+        | ureq::Error::ConnectionFailed => true,
+        _ => false,
+    }
+}
+
+/// Answer text of a chat completion response
+///
+/// OpenAI-compatible servers don't all agree on the shape: besides the standard
+/// `choices[0].message.content` string, some gateways send content as an array of
+/// `{"type": "text", "text": ...}` parts, and some still use the legacy
+/// `choices[0].text`, or a top-level `content` array (Anthropic-style) or
+/// `output_text` (Responses API). The first of these that holds text wins.
+fn response_content(json: &JsonValue) -> Option<String> {
+    let choice = &json["choices"][0];
+    [
+        &choice["message"]["content"],
+        &choice["text"],
+        &json["content"],
+        &json["output_text"],
+    ]
+    .into_iter()
+    .find_map(content_text)
+}
+
+/// Text of a content value: a plain string, or the text parts of an array of parts
+fn content_text(content: &JsonValue) -> Option<String> {
+    if let Some(text) = content.as_str() {
+        return Some(text.to_string());
+    }
+    let parts = content.as_array()?;
+    let text: Vec<&str> = parts
+        .iter()
+        .filter(|part| part["type"].as_str().is_none_or(|kind| kind == "text"))
+        .filter_map(|part| part["text"].as_str().or_else(|| part.as_str()))
+        .collect();
+    (!text.is_empty()).then(|| text.concat())
+}
+
+/// Parse a response body as JSON; the error quotes the start of the body, since
+/// a malformed or truncated reply (or an HTML error page) is otherwise a mystery
+fn parse_json_body(body: &str, api_key: &str) -> Result<JsonValue> {
+    // Enough to recognize an error page without flooding the log
+    const SNIPPET_CHARS: usize = 200;
 
-        // Extract the response text
-        let response_text = json["choices"][0]["message"]["content"]
-            .as_str()
-            .ok_or_else(|| anyhow::anyhow!("No response content found"))?
+    serde_json::from_str(body).map_err(|e| {
+        let mut snippet: String = body.chars().take(SNIPPET_CHARS).collect();
+        if body.chars().count() > SNIPPET_CHARS {
+            snippet.push_str("...");
+        }
+        anyhow::anyhow!(
+            "Response is not valid JSON ({}): {}",
+            e,
+            redact_secrets(&snippet, api_key)
+        )
+    })
+}
+
+/// Remove the API key and any `Bearer <token>` credentials from text before it is logged
+fn redact_secrets(text: &str, api_key: &str) -> String {
+    let mut redacted = if api_key.is_empty() {
+        text.to_string()
+    } else {
+        text.replace(api_key, "[REDACTED]")
+    };
+
+    let mut search_from = 0;
+    while let Some(pos) = redacted[search_from..].find("Bearer ") {
+        let token_start = search_from + pos + "Bearer ".len();
+        let token_end = redacted[token_start..]
+            .find(|c: char| c.is_whitespace() || c == '"' || c == '\'' || c == ',')
+            .map_or(redacted.len(), |end| token_start + end);
+        redacted.replace_range(token_start..token_end, "[REDACTED]");
+        search_from = token_start + "[REDACTED]".len();
+    }
+
+    redacted
+}
+
+/// Check a model name against the known vision-capable patterns
+/// Provider prefixes such as "openai/" (used by routers) are ignored
+pub fn is_vision_model(model: &str) -> bool {
+    let model = model.to_lowercase();
+    let name = model.rsplit('/').next().unwrap_or(&model);
+    if TEXT_ONLY_MODEL_PATTERNS.iter().any(|p| name.contains(p)) {
+        return false;
+    }
+    VISION_MODEL_PATTERNS.iter().any(|p| name.contains(p))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::io::Cursor;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    /// Answers every request with a fixed body, optionally breaking off after it
+    struct FixedTransport {
+        body: &'static str,
+        broken: bool,
+    }
+
+    impl Transport for FixedTransport {
+        fn post_json(
+            &self,
+            _url: &str,
+            _headers: &[(&str, String)],
+            _body: &JsonValue,
+        ) -> std::result::Result<Box<dyn Read + Send>, ureq::Error> {
+            let body = Cursor::new(self.body);
+            if self.broken {
+                Ok(Box::new(body.chain(BrokenReader)))
+            } else {
+                Ok(Box::new(body))
+            }
+        }
+    }
+
+    /// Answers each request with the next scripted status (200 with `body`, or
+    /// an error status) and counts the requests
+    struct ScriptedTransport {
+        body: &'static str,
+        statuses: Mutex<VecDeque<u16>>,
+        calls: AtomicUsize,
+    }
+
+    impl Transport for ScriptedTransport {
+        fn post_json(
+            &self,
+            _url: &str,
+            _headers: &[(&str, String)],
+            _body: &JsonValue,
+        ) -> std::result::Result<Box<dyn Read + Send>, ureq::Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match self.statuses.lock().unwrap().pop_front() {
+                Some(200) | None => Ok(Box::new(Cursor::new(self.body))),
+                Some(status) => Err(ureq::Error::StatusCode(status)),
+            }
+        }
+    }
+
+    /// A client answering with `statuses` in turn, without waiting between retries
+    fn scripted_client(statuses: &[u16]) -> (OpenAI, Arc<ScriptedTransport>) {
+        let transport = Arc::new(ScriptedTransport {
+            body: r#"{"choices": [{"message": {"content": "An answer"}}]}"#,
+            statuses: Mutex::new(statuses.iter().copied().collect()),
+            calls: AtomicUsize::new(0),
+        });
+        let mut llm = OpenAI::new("gpt-4o".to_string(), "sk-test".to_string(), None);
+        llm.transport = transport.clone();
+        llm.retry_base_delay = Duration::ZERO;
+        llm.add_text_content("What does this mean?");
+        (llm, transport)
+    }
+
+    /// A connection that drops
+    struct BrokenReader;
+
+    impl Read for BrokenReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::ConnectionReset.into())
+        }
+    }
+
+    fn client(body: &'static str, broken: bool) -> OpenAI {
+        let mut llm = OpenAI::new("gpt-4o".to_string(), "sk-test".to_string(), None);
+        llm.transport = Arc::new(FixedTransport { body, broken });
+        llm.add_text_content("What does this mean?");
+        llm
+    }
+
+    #[test]
+    fn content_from_a_string() {
+        let json = json!({ "choices": [{ "message": { "content": "An answer" } }] });
+        assert_eq!(response_content(&json).as_deref(), Some("An answer"));
+    }
+
+    #[test]
+    fn content_from_an_array_of_parts() {
+        let json = json!({ "choices": [{ "message": { "content": [
+            { "type": "text", "text": "An " },
+            { "type": "image_url", "image_url": { "url": "data:," } },
+            { "type": "text", "text": "answer" },
+        ] } }] });
+        assert_eq!(response_content(&json).as_deref(), Some("An answer"));
+    }
+
+    #[test]
+    fn content_null_is_no_content() {
+        let json = json!({ "choices": [{ "message": { "content": null } }] });
+        assert_eq!(response_content(&json), None);
+        assert_eq!(content_text(&JsonValue::Null), None);
+    }
+
+    #[test]
+    fn content_refusal_is_no_content() {
+        let json = json!({ "choices": [{ "message": {
+            "content": null,
+            "refusal": "I can't help with that",
+        } }] });
+        assert_eq!(response_content(&json), None);
+        let parts = json!([{ "type": "refusal", "refusal": "I can't help with that" }]);
+        assert_eq!(content_text(&parts), None);
+    }
+
+    #[test]
+    fn execute_returns_the_answer() {
+        let mut llm = client(
+            r#"{"choices": [{"message": {"content": "An answer"}, "finish_reason": "stop"}]}"#,
+            false,
+        );
+        assert_eq!(llm.execute().unwrap(), "An answer");
+        assert_eq!(llm.last_finish_reason(), Some("stop"));
+    }
+
+    #[test]
+    fn execute_fails_on_a_truncated_body() {
+        let mut llm = client(r#"{"choices": [{"message": {"content": "An ans"#, false);
+        let err = llm.execute().unwrap_err().to_string();
+        assert!(err.contains("not valid JSON"), "{}", err);
+    }
+
+    #[test]
+    fn execute_fails_when_the_connection_drops() {
+        let mut llm = client(r#"{"choices": [{"message": "#, true);
+        let err = llm.execute().unwrap_err().to_string();
+        assert!(err.contains("Could not read the response body"), "{}", err);
+    }
+
+    #[test]
+    fn execute_fails_without_choices() {
+        let mut llm = client(r#"{"error": {"message": "overloaded"}}"#, false);
+        let err = llm.execute().unwrap_err().to_string();
+        assert!(err.contains("No response content"), "{}", err);
+    }
+
+    #[test]
+    fn execute_fails_on_non_json() {
+        let mut llm = client("<html><body>502 Bad Gateway</body></html>", false);
+        let err = llm.execute().unwrap_err().to_string();
+        assert!(err.contains("502 Bad Gateway"), "{}", err);
+    }
+
+    #[test]
+    fn execute_retries_a_transient_error() {
+        let (mut llm, transport) = scripted_client(&[503, 200]);
+        assert_eq!(llm.execute().unwrap(), "An answer");
+        assert_eq!(transport.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn execute_fails_fast_on_an_auth_error() {
+        let (mut llm, transport) = scripted_client(&[401, 200]);
+        let err = llm.execute().unwrap_err().to_string();
+        assert!(err.contains("401"), "{}", err);
+        assert_eq!(transport.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn execute_without_retries_makes_one_call() {
+        let (llm, transport) = scripted_client(&[503, 200]);
+        let mut llm = llm.with_retries(0);
+        assert!(llm.execute().is_err());
+        assert_eq!(transport.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn execute_gives_up_after_the_retries() {
+        let (llm, transport) = scripted_client(&[429, 500, 502, 200]);
+        let mut llm = llm.with_retries(2);
+        assert!(llm.execute().is_err());
+        assert_eq!(transport.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn workers_share_the_transport_across_threads() {
+        let llm = client(
+            r#"{"choices": [{"message": {"content": "An answer"}}]}"#,
+            false,
+        );
+        let answers: Vec<String> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..3)
+                .map(|_| {
+                    let mut worker = llm.worker();
+                    scope.spawn(move || {
+                        worker.add_text_content("What does this mean?");
+                        worker.execute().unwrap()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(answers, ["An answer"; 3]);
+    }
+
+    #[test]
+    fn execute_streaming_fails_on_a_truncated_event() {
+        let mut llm = client(
+            "data: {\"choices\": [{\"delta\": {\"content\": \"An\"}}]}\n\ndata: {\"choices\": [{\"del\n\n",
+            false,
+        );
+        let mut chunks = Vec::new();
+        let result = llm.execute_streaming(&mut |chunk| {
+            chunks.push(chunk.to_string());
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(chunks, ["An"]);
+    }
+
+    #[test]
+    fn streaming_hands_out_chunks_and_records_the_usage() {
+        let mut llm = client(
+            "data: {\"choices\": [{\"delta\": {\"content\": \"An \"}}]}\n\n\
+             data: {\"choices\": [{\"delta\": {\"content\": \"answer\"}, \"finish_reason\": \"stop\"}]}\n\n\
+             data: {\"choices\": [], \"usage\": {\"prompt_tokens\": 5, \"completion_tokens\": 2, \"total_tokens\": 7}}\n\n\
+             data: [DONE]\n\n",
+            false,
+        );
+        let mut stream = llm.start_streaming().unwrap();
+        assert_eq!(stream.next_chunk().unwrap().as_deref(), Some("An "));
+        assert_eq!(stream.next_chunk().unwrap().as_deref(), Some("answer"));
+        assert_eq!(stream.next_chunk().unwrap(), None);
+        assert_eq!(stream.text(), "An answer");
+        llm.finish_streaming(stream);
+        assert_eq!(llm.last_finish_reason(), Some("stop"));
+        assert_eq!(llm.last_usage().map(|usage| usage.total_tokens), Some(7));
+    }
+
+    #[test]
+    fn redact_secrets_removes_the_api_key() {
+        let text = "request to https://api.example.com failed with key sk-secret123";
+        assert_eq!(
+            redact_secrets(text, "sk-secret123"),
+            "request to https://api.example.com failed with key [REDACTED]"
+        );
+    }
+
+    #[test]
+    fn redact_secrets_removes_bearer_tokens() {
+        let text = r#"headers: {"Authorization": "Bearer abc.def", "x": 'Bearer ghi'}, Bearer jkl"#;
+        assert_eq!(
+            redact_secrets(text, ""),
+            r#"headers: {"Authorization": "Bearer [REDACTED]", "x": 'Bearer [REDACTED]'}, Bearer [REDACTED]"#
+        );
+    }
+
+    #[test]
+    fn redact_secrets_leaves_other_text_alone() {
+        let text = "Connection refused (os error 111)";
+        assert_eq!(redact_secrets(text, "sk-secret123"), text);
+    }
+
+    #[test]
+    fn parse_json_body_accepts_json() {
+        let json = parse_json_body(r#"{"choices": []}"#, "sk-secret123").unwrap();
+        assert!(json["choices"].is_array());
+    }
+
+    #[test]
+    fn parse_json_body_quotes_a_redacted_snippet() {
+        let body = "<html>Bad gateway for sk-secret123</html>";
+        let err = parse_json_body(body, "sk-secret123")
+            .unwrap_err()
             .to_string();
+        assert!(err.contains("not valid JSON"), "{}", err);
+        assert!(err.contains("<html>Bad gateway for [REDACTED]"), "{}", err);
+        assert!(!err.contains("sk-secret123"), "{}", err);
+    }
 
-        Ok(response_text)
+    #[test]
+    fn parse_json_body_shortens_long_bodies() {
+        let body = "x".repeat(1000);
+        let err = parse_json_body(&body, "").unwrap_err().to_string();
+        assert!(err.ends_with(&format!("{}...", "x".repeat(200))), "{}", err);
     }
 }
@@ -1,9 +1,19 @@
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use dotenv::dotenv;
-use log::info;
-use remarkable_reader_buddy::{OpenAI, Orchestrator, TriggerCorner, Workflow};
-use std::thread::sleep;
+use log::{info, warn};
+use remarkable_reader_buddy::device::{hwrevision, HWREVISION_PATH};
+use remarkable_reader_buddy::llm::openai::{is_vision_model, KNOWN_MODELS};
+use remarkable_reader_buddy::workflow::export::write_answer_export;
+use remarkable_reader_buddy::workflow::CONTOUR_ERASE_MARGIN;
+use remarkable_reader_buddy::{
+    AnswerFormat, AnswerOrder, AnswerTemplate, BenchmarkReport, Config, CornerAction, DeviceModel,
+    DrawSpeed, EraseMode, ErrorDisplay, ImageServer, InkLayout, KeepAwake, NavMethod, OnAmbiguous,
+    OpenAI, Orchestrator, PageCreateFallback, PostAnswerAction, Preprocess, QualityCheck,
+    ScreenshotColor, Script, Speaker, SwipeConfig, SymbolPool, SymbolStyle, ThresholdMode, Touch,
+    TriggerCorner, UiLayout, Webhook, Workflow,
+};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 #[derive(Parser)]
@@ -14,22 +24,73 @@ use std::time::Duration;
                         then uses ChatGPT to provide answers directly on your reMarkable tablet."
 )]
 pub struct Args {
-    /// OpenAI API key (can also be set via OPENAI_API_KEY env var)
-    #[arg(long, env = "OPENAI_API_KEY")]
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// OpenAI API key; visible in process listings, so prefer --api-key-file or
+    /// the OPENAI_API_KEY env var (used when neither option is given)
+    #[arg(long, global = true)]
     api_key: Option<String>,
 
+    /// Read the API key from this file (surrounding whitespace trimmed); also works
+    /// with a named pipe or /dev/fd/N for injected secrets
+    #[arg(long, global = true, env = "READER_BUDDY_API_KEY_FILE")]
+    api_key_file: Option<String>,
+
     /// OpenAI model to use
     #[arg(long, short, default_value = "gpt-4o")]
     model: String,
 
+    /// Model for the define action (default: --model)
+    #[arg(long)]
+    define_model: Option<String>,
+
+    /// Model for the summarize action (default: --model)
+    #[arg(long)]
+    summarize_model: Option<String>,
+
+    /// Model for the translate action (default: --model)
+    #[arg(long)]
+    translate_model: Option<String>,
+
+    /// Model for the describe action (default: --model)
+    #[arg(long)]
+    describe_model: Option<String>,
+
     /// OpenAI base URL (for custom endpoints)
-    #[arg(long, env = "OPENAI_BASE_URL")]
+    #[arg(long, global = true, env = "OPENAI_BASE_URL")]
     base_url: Option<String>,
 
+    /// OpenAI organization ID (sent as the OpenAI-Organization header)
+    #[arg(long, global = true, env = "OPENAI_ORG_ID")]
+    openai_org: Option<String>,
+
+    /// OpenAI project ID (sent as the OpenAI-Project header)
+    #[arg(long, global = true, env = "OPENAI_PROJECT_ID")]
+    openai_project: Option<String>,
+
+    /// Skip the check that --model looks like a vision-capable model
+    #[arg(long)]
+    assume_vision: bool,
+
+    /// Treat preflight warnings (e.g. a model not known to support images) as errors
+    #[arg(long)]
+    strict: bool,
+
+    /// Run on hardware that isn't recognized, using reMarkable 2 device paths
+    /// (required with --strict)
+    #[arg(long)]
+    allow_unknown_device: bool,
+
     /// Disable drawing/output (testing mode)
     #[arg(long)]
     no_draw: bool,
 
+    /// Milliseconds to wait for the virtual input devices after creating them
+    /// (skipped with --no-draw, which creates none)
+    #[arg(long, default_value = "1000")]
+    device_init_ms: u64,
+
     /// Disable trigger waiting (run immediately)
     #[arg(long)]
     no_trigger: bool,
@@ -38,65 +99,1012 @@ pub struct Args {
     #[arg(long)]
     once: bool,
 
+    /// Send a built-in test page to the model, check the response format, and exit
+    #[arg(long)]
+    validate_prompt: bool,
+
+    /// Replay a device action script (tap, swipe, type, line, wait), then exit
+    #[arg(long, value_name = "FILE")]
+    run_script: Option<String>,
+
+    /// Record the menu taps you make while adding a page by hand and save them
+    /// as a [ui_layout] config file (use it with --config), then exit
+    #[arg(long, value_name = "FILE", conflicts_with = "no_draw")]
+    calibrate_ui: Option<String>,
+
+    /// Save the answer pages from the current page on to PATH (.pdf for one PDF,
+    /// otherwise PNG), then exit
+    #[arg(long, value_name = "PATH", conflicts_with = "no_draw")]
+    export_answers: Option<String>,
+
+    /// Skip the LLM call when the capture has less ink than this fraction (blank read)
+    #[arg(long, default_value = "0.0005")]
+    min_ink_fraction: f32,
+
+    /// Skip the LLM call when the capture has more ink than this fraction (all black)
+    #[arg(long, default_value = "0.95")]
+    max_ink_fraction: f32,
+
+    /// Skip the LLM call when the capture's gray levels vary less than this (std dev, 0-255)
+    #[arg(long, default_value = "4.0")]
+    min_capture_contrast: f32,
+
+    /// Send every capture to the LLM without the blank/saturated check
+    #[arg(long)]
+    no_quality_check: bool,
+
+    /// Time N analysis runs on --input-png (load, LLM call, parse) and print the
+    /// stats, then exit. Nothing is drawn
+    #[arg(long, value_name = "N", requires = "input_png")]
+    benchmark: Option<usize>,
+
+    /// Run the analysis on every PNG in DIR (in name order), print one JSON line per
+    /// file, then exit. Nothing is drawn
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["benchmark", "image_via_url"])]
+    input_dir: Option<String>,
+
+    /// LLM calls to run at once with --input-dir; rate limits are retried with
+    /// backoff (--api-retries)
+    #[arg(long, value_name = "N", default_value = "1", requires = "input_dir")]
+    concurrency: usize,
+
     /// Input PNG file for testing (instead of taking screenshot)
     #[arg(long)]
     input_png: Option<String>,
 
+    /// Run one iteration on a saved screenshot (e.g. a --debug-dump file) instead of a
+    /// fresh capture, without waiting for a trigger. Combine with --no-draw to leave the page alone
+    #[arg(long)]
+    replay: Option<String>,
+
     /// Save screenshot to file
     #[arg(long)]
     save_screenshot: Option<String>,
 
+    /// Save every captured screenshot to --debug-dir for debugging
+    #[arg(long)]
+    debug_dump: bool,
+
+    /// Directory for all debug artifacts (screenshots, --verbose-llm payloads)
+    #[arg(long, default_value = "/tmp/reader-buddy")]
+    debug_dir: String,
+
+    /// Number of debug screenshots to keep (0 keeps all)
+    #[arg(long, default_value = "10")]
+    debug_keep: usize,
+
+    /// Write every LLM request (image elided) and raw response to a file in --verbose-llm-dir
+    #[arg(long)]
+    verbose_llm: bool,
+
+    /// Ask the model to continue this many times when an answer is cut off by max_tokens (0 = off)
+    #[arg(long, default_value_t = 0)]
+    max_continuations: u32,
+
+    /// Retries of an LLM request after a network error or HTTP 429/500/502/503,
+    /// with exponential backoff from 500ms (0 = no retries)
+    #[arg(long, default_value_t = 3)]
+    api_retries: u32,
+
+    /// Directory for --verbose-llm payload files [default: <debug-dir>/llm]
+    #[arg(long)]
+    verbose_llm_dir: Option<String>,
+
+    /// Maximum width/height of the image sent to the LLM (longest edge, in pixels)
+    #[arg(long)]
+    max_image_dim: Option<u32>,
+
+    /// Capture encoding: auto (color on Paper Pro only), grayscale, or color
+    #[arg(long, default_value = "auto")]
+    screenshot_color: String,
+
+    /// Stretch contrast of the image sent to the LLM (helps faint pencil)
+    #[arg(long)]
+    preprocess_contrast: bool,
+
+    /// Sharpen the image sent to the LLM
+    #[arg(long)]
+    preprocess_sharpen: bool,
+
+    /// Convert the image sent to the LLM to pure black and white
+    #[arg(long)]
+    preprocess_binarize: bool,
+
+    /// Send the screenshot as a URL under this base (e.g. http://10.11.99.1:8765) instead of inline
+    #[arg(long)]
+    image_via_url: Option<String>,
+
+    /// Directory the URL-referenced screenshots are written to
+    #[arg(long, default_value = "/tmp/reader-buddy-images")]
+    image_serve_dir: String,
+
+    /// Port for the built-in image server
+    #[arg(long, default_value = "8765")]
+    image_serve_port: u16,
+
+    /// Don't start the built-in image server (image_serve_dir is served externally)
+    #[arg(long)]
+    external_image_server: bool,
+
+    /// Wait for the screen to stop changing after page turns (instead of fixed delays)
+    #[arg(long)]
+    settle_check: bool,
+
+    /// Maximum time to wait for the screen to settle, in milliseconds
+    #[arg(long, default_value = "2000")]
+    settle_timeout_ms: u64,
+
+    /// Fraction of pixels allowed to change between captures for the screen to count as settled
+    #[arg(long, default_value = "0.001")]
+    settle_threshold: f32,
+
+    /// Maximum consecutive page turns when seeking the end of a document
+    #[arg(long, default_value = "100")]
+    max_nav_steps: usize,
+
+    /// Action for --trigger-corner (answer, define, summarize, translate, describe, clear)
+    #[arg(long, default_value = "answer")]
+    action: String,
+
+    /// TOML config file (e.g. [corners] bindings)
+    #[arg(long, env = "READER_BUDDY_CONFIG")]
+    config: Option<String>,
+
     /// Trigger corner (UR, UL, LR, LL)
     #[arg(long, default_value = "LR")]
     trigger_corner: String,
 
+    /// Keep the tablet from sleeping while the main loop is running
+    #[arg(long)]
+    keep_awake: bool,
+
+    /// Seconds between keep-awake pings
+    #[arg(long, default_value = "60")]
+    keep_awake_interval: u64,
+
+    /// Keep a separate circled-number sequence for each document (detected from xochitl)
+    #[arg(long)]
+    per_document_symbols: bool,
+
+    /// Document id for --per-document-symbols instead of detecting the open document
+    #[arg(long)]
+    document_id: Option<String>,
+
+    /// Directory for persistent state (default: $XDG_STATE_HOME/reader-buddy or /home/root)
+    #[arg(long)]
+    state_dir: Option<String>,
+
+    /// Page navigation method (swipe, edge-tap, keyboard)
+    #[arg(long, default_value = "swipe")]
+    nav_method: String,
+
+    /// Undo swipes that draw a line instead of turning the page and fall back to the keyboard
+    #[arg(long)]
+    swipe_guard: bool,
+
+    /// Swipe length for --nav-method swipe, centered on the screen (overrides start/end x)
+    #[arg(long)]
+    swipe_distance: Option<i32>,
+
+    /// x where a next-page swipe starts (default depends on the device model)
+    #[arg(long)]
+    swipe_start_x: Option<i32>,
+
+    /// x where a next-page swipe ends (default depends on the device model)
+    #[arg(long)]
+    swipe_end_x: Option<i32>,
+
+    /// Height the swipe runs at (default depends on the device model)
+    #[arg(long)]
+    swipe_y: Option<i32>,
+
+    /// Intermediate touch points in a swipe (default depends on the device model)
+    #[arg(long)]
+    swipe_steps: Option<u32>,
+
+    /// Pause after each swipe step in milliseconds (default depends on the device model)
+    #[arg(long)]
+    swipe_step_delay_ms: Option<u64>,
+
+    /// When turning past the last page gives no blank page for an answer, scratch
+    /// or log page: retry (turn past it again), menu (add one via the xochitl menu),
+    /// or abort; file (file-based creation) is not available yet
+    #[arg(long, default_value = "retry")]
+    page_create_fallback: String,
+
+    /// Page creation attempts for --page-create-fallback retry or menu
+    #[arg(long, default_value = "2")]
+    page_create_retries: u32,
+
+    /// Where new answers go on the answer page (append, prepend)
+    #[arg(long, default_value = "append")]
+    answer_order: String,
+
+    /// Draw answers with the pen as ink (not editable) instead of typing them
+    #[arg(long)]
+    answer_as_ink: bool,
+
+    /// Left edge of ink answers [default: 40]
+    #[arg(long)]
+    ink_x: Option<i32>,
+
+    /// Highest y an ink answer starts at; later ones go below existing ink [default: 72]
+    #[arg(long)]
+    ink_y: Option<i32>,
+
+    /// Width ink answers are wrapped to [default: 688]
+    #[arg(long)]
+    ink_width: Option<i32>,
+
+    /// Font size of ink answers [default: 20]
+    #[arg(long)]
+    ink_font_size: Option<f32>,
+
+    /// Layout of each answer entry (qa, answer-only, compact)
+    #[arg(long, default_value = "qa")]
+    answer_format: String,
+
+    /// When unsure whether the next page is an answer page: create, reuse, or abort
+    #[arg(long, default_value = "create")]
+    on_ambiguous: String,
+
+    /// Where to end up after writing answers: return, stay, or answer-page
+    /// (stay and answer-page both leave you on the answer page)
+    #[arg(long, default_value = "return")]
+    post_answer_action: String,
+
+    /// Always put answers on a new page at the end, skipping the answer page check
+    #[arg(long)]
+    always_new_answer_page: bool,
+
+    /// Write every answer in this notebook (its name in the library) and switch back
+    /// afterwards; uses the open_search and search_result taps of --ui-layout
+    #[arg(long, value_name = "NAME")]
+    answer_document: Option<String>,
+
+    /// How errors are shown on the device (corner, log-page, inline)
+    #[arg(long, default_value = "corner")]
+    error_display: String,
+
+    /// Send the existing answer page to the model too, so answers stay consistent and aren't repeated
+    #[arg(long)]
+    answer_page_context: bool,
+
+    /// Don't erase the question or draw a symbol on the reading page; only write the answer page
+    #[arg(long)]
+    non_destructive: bool,
+
+    /// Don't render answers shorter than this many characters (no answer page is created)
+    #[arg(long, default_value_t = 1)]
+    min_answer_chars: usize,
+
+    /// Cut answers longer than this many characters before typing them (0 = no limit)
+    #[arg(long, default_value_t = 1500)]
+    max_answer_chars: usize,
+
+    /// Briefly show a note on screen when an answer was skipped (too short, already answered)
+    #[arg(long)]
+    show_skipped: bool,
+
+    /// Don't answer a question again if its reference symbol is already on the page
+    #[arg(long)]
+    skip_answered: bool,
+
+    /// Type the answer onto the answer page as the model writes it
+    #[arg(long, conflicts_with = "answer_as_ink")]
+    stream_to_page: bool,
+
+    /// Draw an arrow from each reference symbol to the content it refers to
+    #[arg(long)]
+    draw_arrow: bool,
+
+    /// Let the clear action wipe the answer page (it still needs two taps in a row)
+    #[arg(long)]
+    allow_clear: bool,
+
+    /// Also read each answer aloud (needs espeak-ng, espeak, or piper with --piper-model)
+    #[arg(long)]
+    speak: bool,
+
+    /// Piper voice model (.onnx) to use for --speak instead of espeak
+    #[arg(long, env = "READER_BUDDY_PIPER_MODEL")]
+    piper_model: Option<String>,
+
+    /// Show answers on a throwaway page for this many seconds, then wipe it (0 = off)
+    #[arg(long, default_value = "0")]
+    scratch: u64,
+
+    /// Allow reader-buddy to delete pages (used by --scratch); destructive, off by default
+    #[arg(long)]
+    allow_page_delete: bool,
+
+    /// Taps that add a page after the current one in xochitl's menu, as "x,y;x,y;..."
+    /// (virtual coordinates), replacing the --ui-layout sequence
+    #[arg(long)]
+    new_page_taps: Option<String>,
+
+    /// Taps that delete the current page in xochitl's menu, as "x,y;x,y;..." (virtual
+    /// coordinates), replacing the --ui-layout sequence. A wrong sequence can delete the wrong page
+    #[arg(long)]
+    delete_page_taps: Option<String>,
+
+    /// xochitl menu positions for your firmware (3.x); [ui_layout] in --config overrides
+    /// single steps [default: 3.x]
+    #[arg(long, value_name = "PRESET")]
+    ui_layout: Option<String>,
+
+    /// POST each result as JSON to this URL (e.g. a companion app on your laptop)
+    #[arg(long)]
+    webhook_url: Option<String>,
+
+    /// Include a PNG crop of each outlined region in webhook posts
+    #[arg(long)]
+    webhook_images: bool,
+
+    /// Webhook request timeout in seconds
+    #[arg(long, default_value = "5")]
+    webhook_timeout: u64,
+
+    /// Ask the model to quote the outlined text each answer is based on
+    #[arg(long)]
+    cite: bool,
+
+    /// Text typed before each rendered answer (e.g. "[RB]") for downstream tools
+    #[arg(long, default_value = "")]
+    answer_prefix: String,
+
+    /// Text typed after each rendered answer (e.g. "[/RB]")
+    #[arg(long, default_value = "")]
+    answer_suffix: String,
+
+    /// Consecutive failed iterations before backing off (0 = never back off)
+    #[arg(long, default_value = "5")]
+    max_consecutive_failures: u32,
+
+    /// Seconds to sleep between iterations once the failure budget is exhausted
+    #[arg(long, default_value = "300")]
+    failure_backoff: u64,
+
+    /// Exit instead of backing off once the failure budget is exhausted
+    #[arg(long)]
+    exit_on_repeated_failure: bool,
+
+    /// Exit (status 3) when an iteration makes no progress for this many seconds,
+    /// e.g. a hung LLM call, so a supervisor can restart it (0 = no watchdog)
+    #[arg(long, default_value = "0")]
+    watchdog_timeout: u64,
+
+    /// Extra instruction/persona appended to every prompt
+    #[arg(long, env = "READER_BUDDY_INSTRUCTION")]
+    instruction: Option<String>,
+
+    /// Read the extra instruction/persona from a file
+    #[arg(long, conflicts_with = "instruction")]
+    instruction_file: Option<String>,
+
+    /// Pause after this many typed keys so xochitl can catch up (0 = type without pausing)
+    #[arg(long, default_value = "0")]
+    type_chunk_size: usize,
+
+    /// Pause between typing chunks in milliseconds
+    #[arg(long, default_value = "500")]
+    type_chunk_pause_ms: u64,
+
+    /// Type and delete a probe before each answer to check that typing reaches the page,
+    /// then check each answer appeared and retype it if keystrokes were dropped
+    #[arg(long)]
+    verify_typing: bool,
+
+    /// Background for new answer pages: ruled, grid, or blank
+    #[arg(long, default_value = "blank")]
+    answer_template: String,
+
+    /// Bitmap drawing (symbols, markers): fast, normal, or careful (slowest, pixel by pixel)
+    #[arg(long, default_value = "careful")]
+    draw_speed: String,
+
+    /// How the question is erased: rect (whole box) or contour (only the ink, slightly grown)
+    #[arg(long, default_value = "rect")]
+    erase_mode: String,
+
+    /// Pixels of margin erased around the question's ink with --erase-mode contour
+    #[arg(long, default_value_t = CONTOUR_ERASE_MARGIN)]
+    erase_margin: u8,
+
+    /// Grayscale level (0-255) below which a pixel is erased as ink with --erase-mode
+    /// contour (default: from --threshold-mode)
+    #[arg(long)]
+    erase_threshold: Option<u8>,
+
+    /// Ink detection threshold for erasing and page checks: fixed (128) or otsu (per image)
+    #[arg(long, default_value = "otsu")]
+    threshold_mode: String,
+
+    /// How reference symbols are drawn: pixel (built-in digits) or font (SVG text)
+    #[arg(long, default_value = "pixel")]
+    symbol_style: String,
+
+    /// Font file for rendered SVG text (default: the embedded DejaVu Sans)
+    #[arg(long)]
+    font: Option<String>,
+
     /// Log level (error, warn, info, debug, trace)
     #[arg(long, default_value = "info")]
     log_level: String,
 }
 
+/// One-off commands that print something and exit instead of running the assistant
+#[derive(Subcommand)]
+enum Command {
+    /// Print version, build, and detected device details for bug reports
+    Info,
+    /// List the models offered by the endpoint, marking likely vision models
+    ListModels,
+}
+
+/// Parse "x,y;x,y;..." into virtual screen coordinates
+fn parse_taps(text: &str) -> Result<Vec<(i32, i32)>> {
+    text.split(';')
+        .filter(|tap| !tap.trim().is_empty())
+        .map(|tap| {
+            let (x, y) = tap
+                .split_once(',')
+                .ok_or_else(|| anyhow::anyhow!("Invalid tap '{}': expected x,y", tap))?;
+            let x: i32 = x.trim().parse()?;
+            let y: i32 = y.trim().parse()?;
+            if !(0..768).contains(&x) || !(0..1024).contains(&y) {
+                anyhow::bail!("Tap ({}, {}) is outside the 768x1024 screen", x, y);
+            }
+            Ok((x, y))
+        })
+        .collect()
+}
+
+/// Steps of adding a page by hand, in the order of UiLayout::new_page
+const NEW_PAGE_STEPS: &[&str] = &[
+    "Open the document menu",
+    "Open the page options",
+    "Tap the button that adds a page after the current one",
+    "Tap the new page to close the menu",
+];
+
+/// Walk the user through adding a page and save the taps as a UI layout
+fn calibrate_ui(touch: &mut Touch, path: &Path) -> Result<()> {
+    if path.exists() {
+        anyhow::bail!(
+            "{} already exists; choose a new file so it isn't overwritten",
+            path.display()
+        );
+    }
+
+    println!("Open a notebook you can add a blank page to.");
+    println!("Do each step on the tablet; the position of each tap is recorded.");
+    let mut layout = UiLayout {
+        new_page: Vec::new(),
+        ..UiLayout::default()
+    };
+    for (number, step) in NEW_PAGE_STEPS.iter().enumerate() {
+        println!("{}/{}: {}", number + 1, NEW_PAGE_STEPS.len(), step);
+        let (x, y) = touch.wait_for_tap()?;
+        println!("  recorded tap at ({}, {})", x, y);
+        layout.new_page.push((x, y));
+    }
+
+    std::fs::write(
+        path,
+        format!("# Recorded by --calibrate-ui\n{}", layout.to_toml()),
+    )?;
+    println!(
+        "Saved to {}. Use it with --config {} (or copy the [ui_layout] section into your config)",
+        path.display(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Read an API key from a file, FIFO or /dev/fd/N, trimming surrounding whitespace
+fn read_api_key_file(path: &str) -> Result<String> {
+    let api_key = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Could not read API key file {}: {}", path, e))?
+        .trim()
+        .to_string();
+    if api_key.is_empty() {
+        anyhow::bail!("API key file {} is empty", path);
+    }
+    Ok(api_key)
+}
+
+/// The PNG files in `dir`, sorted by name
+fn png_files(dir: &str) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+        {
+            files.push(path);
+        }
+    }
+    if files.is_empty() {
+        anyhow::bail!("No PNG files in {}", dir);
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Print min/max/mean/p95 for each benchmark stage
+fn print_benchmark(report: &BenchmarkReport, runs: usize) {
+    println!("{} run(s), {} failed", runs, report.failures);
+    println!(
+        "{:<8} {:>10} {:>10} {:>10} {:>10}",
+        "stage", "min ms", "max ms", "mean ms", "p95 ms"
+    );
+    for (stage, timings) in [
+        ("capture", &report.capture),
+        ("llm", &report.llm),
+        ("parse", &report.parse),
+    ] {
+        if timings.is_empty() {
+            println!("{:<8} no successful runs", stage);
+            continue;
+        }
+        let mut ms: Vec<f64> = timings.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+        ms.sort_by(f64::total_cmp);
+        let mean = ms.iter().sum::<f64>() / ms.len() as f64;
+        let p95 = ms[((ms.len() as f64 * 0.95).ceil() as usize).clamp(1, ms.len()) - 1];
+        println!(
+            "{:<8} {:>10.1} {:>10.1} {:>10.1} {:>10.1}",
+            stage,
+            ms[0],
+            ms[ms.len() - 1],
+            mean,
+            p95
+        );
+    }
+}
+
+/// Diagnostics for support requests: build details, detected model, and device paths
+/// Only reads files, so it is safe to run while xochitl (or another instance) is active
+fn print_info() {
+    let present = |path: &str| {
+        if Path::new(path).exists() {
+            "present"
+        } else {
+            "missing"
+        }
+    };
+
+    println!("reader-buddy {}", env!("CARGO_PKG_VERSION"));
+    println!(
+        "build:        {}-{} ({})",
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+        if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        }
+    );
+
+    let model = DeviceModel::detect();
+    println!("device model: {}", model.name());
+    match hwrevision() {
+        Some(hwrev) => println!("hwrevision:   {}", hwrev.replace('\n', "; ")),
+        None => println!(
+            "hwrevision:   unavailable ({} missing or unreadable)",
+            HWREVISION_PATH
+        ),
+    }
+
+    let pen = model.pen_input_path();
+    let touch = model.touch_input_path();
+    println!("pen input:    {} ({})", pen, present(pen));
+    println!("touch input:  {} ({})", touch, present(touch));
+    match model.framebuffer_path() {
+        Some(fb) => println!("framebuffer:  {} ({})", fb, present(fb)),
+        None => println!("framebuffer:  xochitl process memory (/proc/<pid>/mem)"),
+    }
+    println!("uinput:       /dev/uinput ({})", present("/dev/uinput"));
+}
+
+/// Warn loudly when the hardware isn't recognized, since everything then runs on
+/// reMarkable 2 assumptions; with --strict, refuse unless --allow-unknown-device
+fn check_device_model(allow_unknown: bool, strict: bool) -> Result<()> {
+    let model = DeviceModel::detect();
+    if model != DeviceModel::Unknown {
+        info!("Device model: {}", model.name());
+        return Ok(());
+    }
+
+    let found = match hwrevision() {
+        Some(hwrev) => format!("\"{}\"", hwrev.replace('\n', "; ")),
+        None => "nothing (missing or unreadable)".to_string(),
+    };
+    warn!("=== Unrecognized device model ===");
+    warn!("{} contains {}", HWREVISION_PATH, found);
+    warn!(
+        "Assuming a reMarkable 2: pen input {}, touch input {}, screen read from \
+         xochitl's memory at reMarkable 2 resolution",
+        model.pen_input_path(),
+        model.touch_input_path()
+    );
+    warn!("On other hardware captures may be garbage and taps may land in the wrong place");
+    if !allow_unknown {
+        if strict {
+            anyhow::bail!(
+                "Unrecognized device model; use --allow-unknown-device to run with \
+                 reMarkable 2 assumptions anyway"
+            );
+        }
+        warn!("Use --allow-unknown-device to confirm these assumptions are fine");
+    }
+    Ok(())
+}
+
+/// Print the endpoint's model ids, or the built-in list if it has no models endpoint
+/// Doubles as an API key check, since the models list needs the same auth
+fn list_models(llm: &OpenAI) {
+    let models = match llm.list_models() {
+        Ok(models) => models,
+        Err(e) => {
+            println!("Could not list models from the endpoint: {}", e);
+            println!("Commonly available models:");
+            KNOWN_MODELS.iter().map(|m| m.to_string()).collect()
+        }
+    };
+    for model in &models {
+        if is_vision_model(model) {
+            println!("{} (vision)", model);
+        } else {
+            println!("{}", model);
+        }
+    }
+}
+
 fn main() -> Result<()> {
     // Load .env file if it exists
     dotenv().ok();
 
     let args = Args::parse();
 
+    if let Some(Command::Info) = args.command {
+        print_info();
+        return Ok(());
+    }
+
     // Initialize logger
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(&args.log_level))
         .format_timestamp_millis()
         .init();
 
+    // Initialize LLM; key precedence is --api-key, then --api-key-file, then OPENAI_API_KEY
+    let api_key = match (args.api_key, &args.api_key_file) {
+        (Some(api_key), _) => Some(api_key),
+        (None, Some(path)) => Some(read_api_key_file(path)?),
+        (None, None) => None,
+    };
+    let llm = if let Some(api_key) = api_key {
+        OpenAI::new(args.model, api_key, args.base_url)
+    } else {
+        OpenAI::from_env(Some(args.model))?
+    }
+    .with_organization(args.openai_org)
+    .with_project(args.openai_project)
+    .with_max_continuations(args.max_continuations)
+    .with_retries(args.api_retries)
+    .with_payload_log(args.verbose_llm.then(|| match &args.verbose_llm_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(&args.debug_dir).join("llm"),
+    }));
+
+    if let Some(Command::ListModels) = args.command {
+        list_models(&llm);
+        return Ok(());
+    }
+
     info!("=== ReMarkable Reader Buddy Starting ===");
-    info!("Model: {}", args.model);
+    info!("Model: {}", llm.model());
     info!("Trigger Corner: {} (lower-right)", args.trigger_corner);
+    check_device_model(args.allow_unknown_device, args.strict)?;
 
     // Parse trigger corner
     let trigger_corner = TriggerCorner::from_string(&args.trigger_corner)?;
 
+    // Corner bindings from the config file replace the single trigger corner
+    let config = match &args.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+    let default_action = CornerAction::from_string(&args.action)?;
+    let corner_actions = config.corner_bindings()?;
+    for (corner, action) in &corner_actions {
+        info!("Corner {} bound to {:?}", corner.name(), action);
+    }
+
+    // Parse page navigation and output options
+    let nav_method = NavMethod::from_string(&args.nav_method)?;
+    let answer_order = AnswerOrder::from_string(&args.answer_order)?;
+    let answer_format = AnswerFormat::from_string(&args.answer_format)?;
+    let error_display = ErrorDisplay::from_string(&args.error_display)?;
+    let on_ambiguous = OnAmbiguous::from_string(&args.on_ambiguous)?;
+    let post_answer_action = PostAnswerAction::from_string(&args.post_answer_action)?;
+    let page_create_fallback = PageCreateFallback::from_string(&args.page_create_fallback)?;
+    let symbol_style = SymbolStyle::from_string(&args.symbol_style)?;
+    let erase_mode = EraseMode::from_string(&args.erase_mode)?;
+    let threshold_mode = ThresholdMode::from_string(&args.threshold_mode)?;
+    let screenshot_color = ScreenshotColor::from_string(&args.screenshot_color)?;
+    let draw_speed = DrawSpeed::from_string(&args.draw_speed)?;
+    let answer_template = AnswerTemplate::from_string(&args.answer_template)?;
+    let mut swipe = SwipeConfig::for_model(DeviceModel::detect());
+    if let Some(distance) = args.swipe_distance {
+        swipe = swipe.with_distance(distance);
+    }
+    let swipe = SwipeConfig {
+        start_x: args.swipe_start_x.unwrap_or(swipe.start_x),
+        end_x: args.swipe_end_x.unwrap_or(swipe.end_x),
+        y: args.swipe_y.unwrap_or(swipe.y),
+        steps: args.swipe_steps.unwrap_or(swipe.steps),
+        step_delay_ms: args.swipe_step_delay_ms.unwrap_or(swipe.step_delay_ms),
+    };
+    let answer_ink = args.answer_as_ink.then(|| {
+        let layout = InkLayout::default();
+        InkLayout {
+            x: args.ink_x.unwrap_or(layout.x),
+            y: args.ink_y.unwrap_or(layout.y),
+            width: args.ink_width.unwrap_or(layout.width),
+            font_size: args.ink_font_size.unwrap_or(layout.font_size),
+        }
+    });
+    let mut ui_layout = config.ui_layout(args.ui_layout.as_deref())?;
+    if let Some(taps) = &args.new_page_taps {
+        ui_layout.new_page = parse_taps(taps)?;
+    }
+    if let Some(taps) = &args.delete_page_taps {
+        ui_layout.delete_page = parse_taps(taps)?;
+    }
+
+    // Keep every debug artifact in one place so it is easy to find and clean up
+    if args.debug_dump || args.verbose_llm {
+        std::fs::create_dir_all(&args.debug_dir)?;
+        info!("Debug artifacts directory: {}", args.debug_dir);
+    }
+
+    // Start the image server (falls back to inline images if it can't start)
+    let image_server = match &args.image_via_url {
+        Some(base_url) => {
+            let server = if args.external_image_server {
+                ImageServer::external(&args.image_serve_dir, base_url)
+            } else {
+                ImageServer::start(&args.image_serve_dir, base_url, args.image_serve_port)
+            };
+            match server {
+                Ok(server) => Some(server),
+                Err(e) => {
+                    warn!("Image server unavailable, sending images inline: {}", e);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
     // Initialize workflow
-    let workflow = Workflow::new(args.no_draw, trigger_corner)?;
+    // A benchmark or batch run only does analysis, so it never needs the virtual devices
+    let script = match &args.run_script {
+        Some(path) => Some(Script::load(Path::new(path))?),
+        None => None,
+    };
+
+    let analysis_only = args.benchmark.is_some() || args.input_dir.is_some();
+    let mut workflow = Workflow::new(args.no_draw || analysis_only, trigger_corner)?
+        .with_trigger_corners(corner_actions.iter().map(|(corner, _)| *corner).collect())
+        .with_nav_method(nav_method)
+        .with_swipe(swipe)
+        .with_swipe_guard(args.swipe_guard)
+        .with_answer_order(answer_order)
+        .with_error_display(error_display)
+        .with_screenshot_color(screenshot_color)
+        .with_max_image_dim(args.max_image_dim)
+        .with_quality_check((!args.no_quality_check).then_some(QualityCheck {
+            min_ink: args.min_ink_fraction,
+            max_ink: args.max_ink_fraction,
+            min_contrast: args.min_capture_contrast,
+        }))
+        .with_preprocess(Preprocess {
+            contrast_stretch: args.preprocess_contrast,
+            sharpen: args.preprocess_sharpen,
+            binarize: args.preprocess_binarize,
+        })
+        .with_image_server(image_server)
+        .with_max_nav_steps(args.max_nav_steps)
+        .with_erase_mode(erase_mode)
+        .with_erase_tuning(args.erase_margin, args.erase_threshold)
+        .with_threshold_mode(threshold_mode)
+        .with_draw_speed(draw_speed)
+        .with_answer_template(answer_template)
+        .with_answer_ink(answer_ink)
+        .with_verify_typing(args.verify_typing)
+        .with_typing_chunks(
+            args.type_chunk_size,
+            Duration::from_millis(args.type_chunk_pause_ms),
+        )
+        .with_symbol_style(symbol_style, args.font.as_ref().map(PathBuf::from))
+        .with_page_create_fallback(page_create_fallback, args.page_create_retries)
+        .with_settle_check(
+            args.settle_check
+                .then(|| Duration::from_millis(args.settle_timeout_ms)),
+            args.settle_threshold,
+        )
+        .with_page_delete(args.allow_page_delete)
+        .with_ui_layout(ui_layout)
+        .with_replay(match &args.replay {
+            Some(path) => Some(std::fs::read(path)?),
+            None => None,
+        })
+        .with_debug_dump(args.debug_dump, args.debug_keep)
+        .with_debug_dir(PathBuf::from(&args.debug_dir));
 
     // Give time for the virtual devices to be initialized
-    sleep(Duration::from_millis(1000));
+    workflow.wait_for_virtual_devices(Duration::from_millis(args.device_init_ms));
 
-    // Initialize LLM
-    let llm = if let Some(api_key) = args.api_key {
-        OpenAI::new(args.model, api_key, args.base_url)
+    if let Some(script) = &script {
+        return workflow.run_script(script);
+    }
+    if let Some(path) = &args.calibrate_ui {
+        return calibrate_ui(workflow.get_touch_mut(), Path::new(path));
+    }
+    if let Some(path) = &args.export_answers {
+        let pages = workflow.capture_answer_pages()?;
+        for file in write_answer_export(Path::new(path), &pages)? {
+            println!("{}", file.display());
+        }
+        return Ok(());
+    }
+
+    // Images are silently dropped by text-only models, which produces nonsense answers
+    // Per-action models share the endpoint and credentials of --model
+    let action_models: Vec<(CornerAction, String)> = [
+        (CornerAction::Define, &args.define_model),
+        (CornerAction::Summarize, &args.summarize_model),
+        (CornerAction::Translate, &args.translate_model),
+        (CornerAction::Describe, &args.describe_model),
+    ]
+    .into_iter()
+    .filter_map(|(action, model)| model.clone().map(|model| (action, model)))
+    .collect();
+    for (action, model) in &action_models {
+        info!("{:?} uses model {}", action, model);
+    }
+
+    if !args.assume_vision {
+        let models =
+            std::iter::once(llm.model()).chain(action_models.iter().map(|(_, m)| m.as_str()));
+        for model in models.filter(|model| !is_vision_model(model)) {
+            let message = format!(
+                "Model '{}' is not known to accept images; answers will likely be wrong. \
+                 Use --assume-vision if it does",
+                model
+            );
+            if args.strict {
+                anyhow::bail!(message);
+            }
+            warn!("{}", message);
+        }
+    }
+
+    let speaker = if args.speak {
+        let speaker = Speaker::detect(args.piper_model.as_deref().map(Path::new));
+        if speaker.is_none() {
+            warn!("--speak given but no espeak-ng, espeak, or piper/aplay found on PATH; answers won't be spoken");
+        }
+        speaker
     } else {
-        OpenAI::from_env(Some(args.model))?
+        None
+    };
+
+    // Load symbol state
+    let mut symbol_pool = match &args.state_dir {
+        Some(state_dir) => SymbolPool::new().with_state_dir(state_dir),
+        None => SymbolPool::new(),
+    };
+    info!("Symbol state file: {}", symbol_pool.state_file());
+    let _ = symbol_pool.load();
+
+    // Resolve the custom instruction (if any); the command line overrides the config file
+    let instruction = match &args.instruction_file {
+        Some(path) => Some(std::fs::read_to_string(path)?.trim().to_string()),
+        None => args.instruction.clone().or(config.instruction.clone()),
     };
 
     // Create orchestrator
-    let mut orchestrator = Orchestrator::new(workflow, llm);
+    let mut orchestrator = Orchestrator::with_symbol_pool(workflow, llm, symbol_pool)
+        .with_instruction(instruction)
+        .with_per_document_symbols(
+            args.per_document_symbols || args.document_id.is_some(),
+            args.document_id.clone(),
+        )
+        .with_answer_markers(args.answer_prefix, args.answer_suffix)
+        .with_answer_format(answer_format)
+        .with_citations(args.cite)
+        .with_answer_page_context(args.answer_page_context)
+        .with_non_destructive(args.non_destructive)
+        .with_draw_arrow(args.draw_arrow)
+        .with_min_answer_length(args.min_answer_chars, args.show_skipped)
+        .with_skip_answered(args.skip_answered)
+        .with_stream_to_page(args.stream_to_page)
+        .with_response_format(config.response_format.clone())
+        .with_max_answer_length(args.max_answer_chars)
+        .with_allow_clear(args.allow_clear)
+        .with_font(args.font.as_ref().map(PathBuf::from))
+        .with_speaker(speaker)
+        .with_scratch((args.scratch > 0).then(|| Duration::from_secs(args.scratch)))
+        .with_webhook(args.webhook_url.map(|url| {
+            Webhook::new(url, Duration::from_secs(args.webhook_timeout))
+                .with_images(args.webhook_images)
+        }))
+        .with_default_action(default_action)
+        .with_corner_actions(corner_actions)
+        .with_action_models(action_models)
+        .with_on_ambiguous(on_ambiguous)
+        .with_post_answer_action(post_answer_action)
+        .with_always_new_answer_page(args.always_new_answer_page)
+        .with_answer_document(args.answer_document.clone())
+        .with_failure_budget(
+            args.max_consecutive_failures,
+            Duration::from_secs(args.failure_backoff),
+            args.exit_on_repeated_failure,
+        )
+        .with_watchdog(
+            (args.watchdog_timeout > 0).then(|| Duration::from_secs(args.watchdog_timeout)),
+        );
 
     info!("Initialization complete");
 
     // Run the workflow
-    if args.once {
+    if args.validate_prompt {
+        return orchestrator.validate_prompt();
+    }
+
+    if let (Some(runs), Some(input_png)) = (args.benchmark, &args.input_png) {
+        let png = std::fs::read(input_png)?;
+        let report = orchestrator.benchmark(&png, runs)?;
+        print_benchmark(&report, runs);
+        return Ok(());
+    }
+
+    if let Some(input_dir) = &args.input_dir {
+        let files = png_files(input_dir)?;
+        let evaluations = orchestrator.evaluate(&files, args.concurrency)?;
+        for evaluation in &evaluations {
+            println!("{}", serde_json::to_string(evaluation)?);
+        }
+        let failed = evaluations.iter().filter(|e| e.error.is_some()).count();
+        let answered = evaluations.iter().filter(|e| !e.items.is_empty()).count();
+        info!(
+            "{} file(s): {} answered, {} with nothing found, {} failed",
+            evaluations.len(),
+            answered,
+            evaluations.len() - answered - failed,
+            failed
+        );
+        return Ok(());
+    }
+
+    if args.once || args.replay.is_some() {
         info!("Running single iteration");
-        orchestrator.run_iteration()?;
+        let outcome = orchestrator.run_iteration()?;
+        info!("Iteration outcome: {:?}", outcome);
     } else {
         info!("Starting main loop");
+        let _keep_awake = if args.keep_awake {
+            Some(KeepAwake::start(
+                args.no_draw,
+                Duration::from_secs(args.keep_awake_interval),
+            )?)
+        } else {
+            None
+        };
         orchestrator.run_loop()?;
     }
 
@@ -0,0 +1,103 @@
+use super::response_format::ResponseFormat;
+
+/// A piece of a streamed response that is ready to be rendered
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamPart {
+    /// Everything before the separator, complete (sent once)
+    Header(String),
+    /// More answer text, with the answer label removed
+    Answer(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+    /// Waiting for the separator
+    Header,
+    /// Separator seen; waiting to see whether the answer starts with its label
+    Label,
+    Answer,
+}
+
+/// Splits a streamed response into header and answer text as the chunks arrive
+///
+/// The header is only handed out once the separator has arrived, so it can be
+/// parsed like a complete response. Answer text is handed out as soon as it is
+/// known not to be part of the answer label.
+#[derive(Debug, Clone)]
+pub struct AnswerStream {
+    separator: String,
+    answer_label: String,
+    stage: Stage,
+    /// Text of the current stage not handed out yet
+    pending: String,
+    /// Whether any answer text was handed out (leading whitespace is dropped until then)
+    answer_begun: bool,
+    /// Everything received so far
+    received: String,
+}
+
+impl AnswerStream {
+    pub fn new(format: &ResponseFormat) -> Self {
+        Self {
+            separator: format.separator.clone(),
+            answer_label: format.answer.clone(),
+            stage: Stage::Header,
+            pending: String::new(),
+            answer_begun: false,
+            received: String::new(),
+        }
+    }
+
+    /// Feed the next chunk; returns the parts it completed
+    pub fn push(&mut self, chunk: &str) -> Vec<StreamPart> {
+        self.received.push_str(chunk);
+        self.pending.push_str(chunk);
+        let mut parts = Vec::new();
+
+        if self.stage == Stage::Header {
+            let Some(index) = self.pending.find(&self.separator) else {
+                return parts;
+            };
+            let rest = self.pending.split_off(index + self.separator.len());
+            self.pending.truncate(index);
+            parts.push(StreamPart::Header(std::mem::replace(
+                &mut self.pending,
+                rest,
+            )));
+            self.stage = Stage::Label;
+        }
+
+        if self.stage == Stage::Label {
+            let text = self.pending.trim_start();
+            let answer = if let Some(answer) = text.strip_prefix(self.answer_label.as_str()) {
+                answer
+            } else if text.is_empty() || self.answer_label.starts_with(text) {
+                // Could still turn into the label
+                return parts;
+            } else {
+                text
+            };
+            self.pending = answer.to_string();
+            self.stage = Stage::Answer;
+        }
+
+        if !self.answer_begun {
+            self.pending = self.pending.trim_start().to_string();
+        }
+        if !self.pending.is_empty() {
+            self.answer_begun = true;
+            parts.push(StreamPart::Answer(std::mem::take(&mut self.pending)));
+        }
+        parts
+    }
+
+    /// Whether the separator has arrived, i.e. the answer has begun
+    pub fn answer_started(&self) -> bool {
+        self.stage != Stage::Header
+    }
+
+    /// Everything received so far
+    pub fn received(&self) -> &str {
+        &self.received
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::Result;
+use image::codecs::jpeg::JpegEncoder;
+use image::ImageEncoder;
+use log::info;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Page images are 768x1024 virtual pixels, laid out at 96 dpi (0.75 pt per pixel)
+const POINTS_PER_PIXEL: f32 = 0.75;
+const JPEG_QUALITY: u8 = 85;
+
+/// Save captured answer pages (PNG data) to `path`
+///
+/// A `.pdf` path gets one PDF with a page per capture. Anything else is written as
+/// PNG: the path itself for a single page, otherwise `<stem>-1.png`, `<stem>-2.png`, ...
+/// next to it. Returns the files written.
+pub fn write_answer_export(path: &Path, pages: &[Vec<u8>]) -> Result<Vec<PathBuf>> {
+    if pages.is_empty() {
+        anyhow::bail!("No answer pages to export");
+    }
+
+    let is_pdf = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"));
+    if is_pdf {
+        fs::write(path, pdf_from_pages(pages)?)?;
+        info!(
+            "Exported {} answer page(s) to {}",
+            pages.len(),
+            path.display()
+        );
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    if pages.len() == 1 {
+        fs::write(path, &pages[0])?;
+        info!("Exported the answer page to {}", path.display());
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let stem = path
+        .file_stem()
+        .map_or("answers".into(), |stem| stem.to_string_lossy());
+    let mut written = Vec::with_capacity(pages.len());
+    for (index, page) in pages.iter().enumerate() {
+        let page_path = path.with_file_name(format!("{}-{}.png", stem, index + 1));
+        fs::write(&page_path, page)?;
+        info!(
+            "Exported answer page {} to {}",
+            index + 1,
+            page_path.display()
+        );
+        written.push(page_path);
+    }
+    Ok(written)
+}
+
+/// A minimal PDF with each page image embedded as a grayscale JPEG
+fn pdf_from_pages(pages: &[Vec<u8>]) -> Result<Vec<u8>> {
+    let mut pdf = Vec::new();
+    // Byte offset of each object, for the cross-reference table
+    let mut offsets = Vec::new();
+    pdf.extend_from_slice(b"%PDF-1.4\n");
+
+    // Objects: 1 catalog, 2 page tree, then page, contents and image for each page
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 3 + i * 3).collect();
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+
+    offsets.push(pdf.len());
+    pdf.extend_from_slice(b"1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n");
+    offsets.push(pdf.len());
+    pdf.extend_from_slice(
+        format!(
+            "2 0 obj\n<< /Type /Pages /Kids [{}] /Count {} >>\nendobj\n",
+            kids.join(" "),
+            pages.len()
+        )
+        .as_bytes(),
+    );
+
+    for (page, id) in pages.iter().zip(&page_ids) {
+        let gray = image::load_from_memory(page)?.to_luma8();
+        let (width, height) = gray.dimensions();
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY).write_image(
+            gray.as_raw(),
+            width,
+            height,
+            image::ExtendedColorType::L8,
+        )?;
+
+        let page_width = width as f32 * POINTS_PER_PIXEL;
+        let page_height = height as f32 * POINTS_PER_PIXEL;
+        let contents = format!(
+            "q {:.2} 0 0 {:.2} 0 0 cm /Im0 Do Q",
+            page_width, page_height
+        );
+
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(
+            format!(
+                "{} 0 obj\n<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+                 /Resources << /XObject << /Im0 {} 0 R >> >> /Contents {} 0 R >>\nendobj\n",
+                id,
+                page_width,
+                page_height,
+                id + 2,
+                id + 1
+            )
+            .as_bytes(),
+        );
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(
+            format!(
+                "{} 0 obj\n<< /Length {} >>\nstream\n{}\nendstream\nendobj\n",
+                id + 1,
+                contents.len(),
+                contents
+            )
+            .as_bytes(),
+        );
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(
+            format!(
+                "{} 0 obj\n<< /Type /XObject /Subtype /Image /Width {} /Height {} \
+                 /ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /DCTDecode \
+                 /Length {} >>\nstream\n",
+                id + 2,
+                width,
+                height,
+                jpeg.len()
+            )
+            .as_bytes(),
+        );
+        pdf.extend_from_slice(&jpeg);
+        pdf.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    let xref = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n", offsets.len() + 1).as_bytes());
+    pdf.extend_from_slice(b"0000000000 65535 f \n");
+    for offset in &offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            offsets.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+    Ok(pdf)
+}
@@ -1,11 +1,374 @@
+pub mod answer_stream;
+pub mod export;
 pub mod orchestrator;
 pub mod page_manager;
+pub mod response_format;
+pub mod script;
 pub mod symbol_pool;
+pub mod validation;
+pub mod watchdog;
+pub mod webhook;
 
 use anyhow::Result;
-use log::info;
+use log::{debug, info, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
 
-use crate::device::{keyboard::Keyboard, pen::Pen, screenshot::Screenshot, touch::Touch};
+use crate::analysis::{
+    answer_page::{
+        answer_page_marker_bitmap, classify_answer_page, has_answer_page_marker, is_blank_page,
+        lowest_ink_row, AnswerPageCheck, MARKER_X, MARKER_Y,
+    },
+    erase_path::contour_erase_strokes,
+    image_difference, is_swipe_stroke,
+    preprocess::Preprocess,
+    quality::QualityCheck,
+    symbol_match::find_symbol,
+    ThresholdMode,
+};
+use crate::device::{
+    documents::find_document_by_name,
+    keyboard::Keyboard,
+    pen::{DrawSpeed, Pen},
+    screenshot::{Screenshot, ScreenshotColor},
+    touch::{Touch, TriggerCorner},
+    DeviceModel,
+};
+use crate::llm::image_server::ImageServer;
+use page_manager::{NavMethod, PageManager, SwipeConfig, UiLayout};
+use script::Script;
+use symbol_pool::SymbolStyle;
+use validation::svg_to_bitmap;
+
+// Ruled/grid answer template layout (virtual coordinates), spaced like typed body text
+const TEMPLATE_LINE_SPACING: i32 = 32;
+const TEMPLATE_LEFT: i32 = 40;
+const TEMPLATE_RIGHT: i32 = 728;
+const TEMPLATE_TOP: i32 = 72;
+const TEMPLATE_BOTTOM: i32 = 984;
+
+// Typed (and then deleted) to check that keystrokes reach a text area
+const TYPING_PROBE: &str = "####";
+// Typed text must change at least this share of the screen the probe predicts;
+// the probe's '#' is denser than most letters, so the margin is generous
+const MIN_TYPED_INK_RATIO: f32 = 0.25;
+// Characters beyond this may scroll off the visible page, so they aren't counted
+const MAX_VERIFIED_CHARS: usize = 1500;
+// Times an entry is typed before giving up on it (--verify-typing)
+const TYPING_ATTEMPTS: u32 = 3;
+
+// Reference symbol size in pixels (room for the digits inside the circle)
+const SYMBOL_SIZE: u32 = 28;
+
+/// Pixels of margin grown around the ink for --erase-mode contour (--erase-margin default)
+pub const CONTOUR_ERASE_MARGIN: u8 = 3;
+
+// Debug screenshot dumps are written to <debug dir>/DEBUG_DUMP_PREFIX-NNN.png
+pub const DEFAULT_DEBUG_DIR: &str = "/tmp/reader-buddy";
+const DEBUG_DUMP_PREFIX: &str = "screenshot-";
+
+/// Where and how large answers are drawn with --answer-as-ink (virtual coordinates)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InkLayout {
+    /// Left edge of the text
+    pub x: i32,
+    /// Highest the first answer may start; later answers go below existing ink
+    pub y: i32,
+    /// Width text is wrapped to
+    pub width: i32,
+    pub font_size: f32,
+}
+
+impl Default for InkLayout {
+    fn default() -> Self {
+        Self {
+            x: TEMPLATE_LEFT,
+            y: TEMPLATE_TOP,
+            width: TEMPLATE_RIGHT - TEMPLATE_LEFT,
+            font_size: 20.0,
+        }
+    }
+}
+
+impl InkLayout {
+    /// Wrap `text` to the layout width and lay it out as SVG text lines
+    /// Character widths are estimated, so lines may end a little short of the width
+    fn svg(&self, text: &str) -> String {
+        let max_chars = ((self.width as f32 / (self.font_size * 0.55)) as usize).max(1);
+        let mut lines = Vec::new();
+        for paragraph in text.lines() {
+            let mut line = String::new();
+            for word in paragraph.split_whitespace() {
+                if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > max_chars {
+                    lines.push(std::mem::take(&mut line));
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(word);
+            }
+            lines.push(line);
+        }
+
+        let line_height = self.font_size * 1.3;
+        let height = (lines.len() as f32 * line_height + self.font_size * 0.5).ceil() as i32;
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}"><g font-family="sans-serif" font-size="{}" fill="black">"#,
+            self.width, height, self.font_size
+        );
+        for (i, line) in lines.iter().enumerate() {
+            let escaped = line
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;");
+            svg.push_str(&format!(
+                r#"<text x="0" y="{}">{}</text>"#,
+                (i + 1) as f32 * line_height,
+                escaped
+            ));
+        }
+        svg.push_str("</g></svg>");
+        svg
+    }
+}
+
+/// Where new answers are placed on the answer page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnswerOrder {
+    /// Newest answer at the bottom
+    Append,
+    /// Newest answer at the top
+    Prepend,
+}
+
+impl AnswerOrder {
+    pub fn from_string(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "append" => Ok(AnswerOrder::Append),
+            "prepend" => Ok(AnswerOrder::Prepend),
+            _ => Err(anyhow::anyhow!(
+                "Invalid answer order: {}. Use append or prepend",
+                s
+            )),
+        }
+    }
+}
+
+/// Layout of each entry typed on the answer page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnswerFormat {
+    /// "① Q: question" then "A: answer", entries separated by "---"
+    Qa,
+    /// "① answer" with no question echo
+    AnswerOnly,
+    /// "① question" with the answer on the next line, no labels or separators
+    Compact,
+}
+
+impl AnswerFormat {
+    pub fn from_string(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "qa" => Ok(AnswerFormat::Qa),
+            "answer-only" => Ok(AnswerFormat::AnswerOnly),
+            "compact" => Ok(AnswerFormat::Compact),
+            _ => Err(anyhow::anyhow!(
+                "Invalid answer format: {}. Use qa, answer-only, or compact",
+                s
+            )),
+        }
+    }
+}
+
+/// How errors are surfaced to the user on the device
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorDisplay {
+    /// Draw a small error glyph in the upper-left corner of the current page
+    Corner,
+    /// Write the error onto a new "Reader Buddy Log" page at the end of the document
+    LogPage,
+    /// Type the error onto the current page
+    Inline,
+}
+
+impl ErrorDisplay {
+    pub fn from_string(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "corner" => Ok(ErrorDisplay::Corner),
+            "log-page" => Ok(ErrorDisplay::LogPage),
+            "inline" => Ok(ErrorDisplay::Inline),
+            _ => Err(anyhow::anyhow!(
+                "Invalid error display: {}. Use corner, log-page, or inline",
+                s
+            )),
+        }
+    }
+}
+
+/// What to do when the page after the reading page might or might not be an answer page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OnAmbiguous {
+    /// Put the answers on a fresh page at the end of the document
+    Create,
+    /// Write on the next page anyway
+    Reuse,
+    /// Go back and fail the iteration without writing anything
+    Abort,
+}
+
+impl OnAmbiguous {
+    pub fn from_string(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "create" => Ok(OnAmbiguous::Create),
+            "reuse" => Ok(OnAmbiguous::Reuse),
+            "abort" => Ok(OnAmbiguous::Abort),
+            _ => Err(anyhow::anyhow!(
+                "Invalid ambiguous answer page policy: {}. Use create, reuse, or abort",
+                s
+            )),
+        }
+    }
+}
+
+/// Where to leave the user once the answers are written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PostAnswerAction {
+    /// Go back to the reading page
+    Return,
+    /// Stay on the answer page to read the new answers ("stay" is accepted too)
+    AnswerPage,
+}
+
+impl PostAnswerAction {
+    pub fn from_string(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "return" => Ok(PostAnswerAction::Return),
+            "stay" | "answer-page" => Ok(PostAnswerAction::AnswerPage),
+            _ => Err(anyhow::anyhow!(
+                "Invalid post-answer action: {}. Use return, stay, or answer-page",
+                s
+            )),
+        }
+    }
+}
+
+/// Background drawn on a blank answer page before the first answer is typed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnswerTemplate {
+    /// Leave the page blank
+    Blank,
+    /// Horizontal lines, one per text line
+    Ruled,
+    /// Horizontal and vertical lines on the same spacing
+    Grid,
+}
+
+impl AnswerTemplate {
+    pub fn from_string(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "blank" => Ok(AnswerTemplate::Blank),
+            "ruled" => Ok(AnswerTemplate::Ruled),
+            "grid" => Ok(AnswerTemplate::Grid),
+            _ => Err(anyhow::anyhow!(
+                "Invalid answer template: {}. Use ruled, grid, or blank",
+                s
+            )),
+        }
+    }
+}
+
+/// How the question region is erased
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EraseMode {
+    /// Sweep the whole bounding box
+    Rect,
+    /// Sweep only the (slightly grown) ink inside the bounding box
+    Contour,
+}
+
+impl EraseMode {
+    pub fn from_string(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "rect" => Ok(EraseMode::Rect),
+            "contour" => Ok(EraseMode::Contour),
+            _ => Err(anyhow::anyhow!(
+                "Invalid erase mode: {}. Use rect or contour",
+                s
+            )),
+        }
+    }
+}
+
+/// What to do when turning past the last page doesn't give a blank page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PageCreateFallback {
+    /// Repeat the creation gesture (turning past the last page), up to the
+    /// configured number of retries
+    Retry,
+    /// Add the page through xochitl's menu (the `new_page` taps), up to the
+    /// configured number of retries
+    Menu,
+    /// Fail the iteration straight away
+    Abort,
+}
+
+impl PageCreateFallback {
+    pub fn from_string(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "retry" => Ok(PageCreateFallback::Retry),
+            "menu" => Ok(PageCreateFallback::Menu),
+            "abort" => Ok(PageCreateFallback::Abort),
+            "file" => Err(anyhow::anyhow!(
+                "File-based page creation is not available yet. Use retry, menu or abort"
+            )),
+            _ => Err(anyhow::anyhow!(
+                "Invalid page creation fallback: {}. Use retry, menu or abort",
+                s
+            )),
+        }
+    }
+}
+
+/// What a trigger corner does when tapped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CornerAction {
+    /// Answer the handwritten question about the outlined content
+    Answer,
+    /// Define the outlined word or phrase
+    Define,
+    /// Summarize the outlined passage
+    Summarize,
+    /// Translate the outlined passage into English
+    Translate,
+    /// Describe an outlined figure or diagram (no question, nothing erased)
+    Describe,
+    /// Repeat the previous action on the current page
+    Redo,
+    /// Undo the previous answer (not supported yet)
+    Undo,
+    /// Delete all typed text on the answer page (needs --allow-clear and a second tap)
+    ClearAnswers,
+}
+
+impl CornerAction {
+    pub fn from_string(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "answer" => Ok(CornerAction::Answer),
+            "define" => Ok(CornerAction::Define),
+            "summarize" => Ok(CornerAction::Summarize),
+            "translate" => Ok(CornerAction::Translate),
+            "describe" | "figure" => Ok(CornerAction::Describe),
+            "redo" => Ok(CornerAction::Redo),
+            "undo" => Ok(CornerAction::Undo),
+            "clear" | "clear-answers" => Ok(CornerAction::ClearAnswers),
+            _ => Err(anyhow::anyhow!(
+                "Invalid corner action: {}. Use answer, define, summarize, translate, describe, redo, undo, or clear",
+                s
+            )),
+        }
+    }
+}
 
 /// Main workflow coordinator
 pub struct Workflow {
@@ -13,33 +376,531 @@ pub struct Workflow {
     pen: Pen,
     keyboard: Keyboard,
     touch: Touch,
+    nav_method: NavMethod,
+    swipe: SwipeConfig,
+    /// Undo swipes that drew a line instead of turning the page, then use the keyboard
+    swipe_guard: bool,
+    answer_order: AnswerOrder,
+    error_display: ErrorDisplay,
+    image_server: Option<ImageServer>,
+    settle_timeout: Option<Duration>,
+    settle_threshold: f32,
+    max_nav_steps: usize,
+    erase_mode: EraseMode,
+    /// Contour erasing: pixels grown around the ink, and the ink level (None = threshold_mode)
+    erase_margin: u8,
+    erase_threshold: Option<u8>,
+    verify_typing: bool,
+    answer_template: AnswerTemplate,
+    symbol_style: SymbolStyle,
+    symbol_font: Option<PathBuf>,
+    page_create_fallback: PageCreateFallback,
+    page_create_retries: u32,
+    /// Page deletion is destructive and must be enabled explicitly
+    allow_page_delete: bool,
+    /// Menu tap positions for the installed firmware
+    ui_layout: UiLayout,
+    debug_dump: bool,
+    /// Directory all debug artifacts are written to
+    debug_dir: PathBuf,
+    debug_keep: usize,
+    debug_dump_count: u32,
+    /// How ink is told apart from paper in captures
+    threshold_mode: ThresholdMode,
+    /// Saved screenshot used for the next capture instead of the screen (--replay)
+    replay: Option<Vec<u8>>,
+    /// Draw answers as pen ink instead of typing them (--answer-as-ink)
+    answer_ink: Option<InkLayout>,
+    /// Reject blank or saturated captures before they reach the LLM
+    quality_check: Option<QualityCheck>,
+    /// Whether virtual pen/touch/keyboard devices were created (false with --no-draw)
+    virtual_devices: bool,
+}
+
+/// The page turns navigate_to_last_page makes, kept apart from Workflow so the
+/// search can run against a fake document in tests
+trait PageTurner {
+    /// Turn one page forward, let it settle, and capture the screen (PNG)
+    fn forward_and_capture(&mut self) -> Result<Vec<u8>>;
+    /// Turn `pages` pages back
+    fn back(&mut self, pages: usize) -> Result<()>;
+}
+
+impl PageTurner for Workflow {
+    fn forward_and_capture(&mut self) -> Result<Vec<u8>> {
+        self.turn_page(true)?;
+        self.wait_for_page_settle(Duration::from_millis(300))?;
+        self.screenshot.take_screenshot()?;
+        Ok(self.screenshot.get_image_data().to_vec())
+    }
+
+    fn back(&mut self, pages: usize) -> Result<()> {
+        self.navigate_back(pages)
+    }
+}
+
+/// Turn forward from the page captured in `start` until a turn leaves the screen
+/// unchanged (difference at most `threshold`); returns the turns that changed it
+///
+/// After `max_steps` changing turns the end is taken as undetectable: the pages
+/// are turned back so the user isn't left deep in the document, and it fails.
+fn find_last_page(
+    turner: &mut impl PageTurner,
+    start: Vec<u8>,
+    max_steps: usize,
+    threshold: f32,
+) -> Result<usize> {
+    let mut previous = start;
+    for step in 1..=max_steps {
+        let current = turner.forward_and_capture()?;
+        if image_difference(&previous, &current)? <= threshold {
+            info!("Reached the last page after {} page turn(s)", step - 1);
+            return Ok(step - 1);
+        }
+        previous = current;
+    }
+
+    warn!(
+        "Still turning pages after {} steps; going back to the starting page",
+        max_steps
+    );
+    turner.back(max_steps)?;
+    Err(anyhow::anyhow!(
+        "Gave up looking for the last page after {} page turns (--max-nav-steps); \
+         end-of-document detection may have failed",
+        max_steps
+    ))
 }
 
 impl Workflow {
-    pub fn new(no_draw: bool, trigger_corner: crate::device::touch::TriggerCorner) -> Result<Self> {
+    pub fn new(no_draw: bool, trigger_corner: TriggerCorner) -> Result<Self> {
         Ok(Self {
             screenshot: Screenshot::new()?,
             pen: Pen::new(no_draw),
             keyboard: Keyboard::new(no_draw, false),
             touch: Touch::new(no_draw, trigger_corner),
+            nav_method: NavMethod::Swipe,
+            swipe: SwipeConfig::for_model(DeviceModel::detect()),
+            swipe_guard: false,
+            answer_order: AnswerOrder::Append,
+            error_display: ErrorDisplay::Corner,
+            image_server: None,
+            settle_timeout: None,
+            settle_threshold: 0.001,
+            max_nav_steps: 100,
+            erase_mode: EraseMode::Rect,
+            erase_margin: CONTOUR_ERASE_MARGIN,
+            erase_threshold: None,
+            verify_typing: false,
+            answer_template: AnswerTemplate::Blank,
+            symbol_style: SymbolStyle::Pixel,
+            symbol_font: None,
+            page_create_fallback: PageCreateFallback::Retry,
+            page_create_retries: 2,
+            allow_page_delete: false,
+            ui_layout: UiLayout::default(),
+            debug_dump: false,
+            debug_dir: PathBuf::from(DEFAULT_DEBUG_DIR),
+            debug_keep: 0,
+            debug_dump_count: 0,
+            threshold_mode: ThresholdMode::default(),
+            replay: None,
+            answer_ink: None,
+            quality_check: None,
+            virtual_devices: !no_draw,
         })
     }
 
+    /// Give newly created virtual devices time to be picked up by xochitl
+    /// Returns at once when no devices were created (--no-draw)
+    pub fn wait_for_virtual_devices(&self, delay: Duration) {
+        if self.virtual_devices && !delay.is_zero() {
+            debug!("Waiting {}ms for virtual devices", delay.as_millis());
+            sleep(delay);
+        }
+    }
+
+    /// Replay a device action script (see `Script`)
+    pub fn run_script(&mut self, script: &Script) -> Result<()> {
+        script.run(&mut self.touch, &mut self.keyboard, &mut self.pen)
+    }
+
+    /// Check captures meant for the LLM and re-capture once if they look broken
+    pub fn with_quality_check(mut self, quality_check: Option<QualityCheck>) -> Self {
+        self.quality_check = quality_check;
+        self
+    }
+
+    /// Draw answer entries as ink with this layout instead of typing them
+    pub fn with_answer_ink(mut self, answer_ink: Option<InkLayout>) -> Self {
+        self.answer_ink = answer_ink;
+        self
+    }
+
+    /// Encode captures in grayscale or color regardless of the device default
+    pub fn with_screenshot_color(mut self, color: ScreenshotColor) -> Self {
+        self.screenshot.set_color(color);
+        self
+    }
+
+    /// Limit the longest edge of the image sent to the LLM
+    pub fn with_max_image_dim(mut self, max_image_dim: Option<u32>) -> Self {
+        self.screenshot.set_max_image_dim(max_image_dim);
+        self
+    }
+
+    /// Clean up the image sent to the LLM (contrast, sharpen, binarize)
+    pub fn with_preprocess(mut self, preprocess: Preprocess) -> Self {
+        self.screenshot.set_preprocess(preprocess);
+        self
+    }
+
+    /// Choose how page turns are sent to xochitl
+    pub fn with_nav_method(mut self, nav_method: NavMethod) -> Self {
+        self.nav_method = nav_method;
+        self
+    }
+
+    /// Shape of the swipe used by the swipe navigation method
+    pub fn with_swipe(mut self, swipe: SwipeConfig) -> Self {
+        self.swipe = swipe;
+        self
+    }
+
+    /// Check each swipe for a line drawn by the pen tool; undo it and switch to
+    /// keyboard page turns if one is found
+    pub fn with_swipe_guard(mut self, swipe_guard: bool) -> Self {
+        self.swipe_guard = swipe_guard;
+        self
+    }
+
+    /// Choose whether new answers go at the top or bottom of the answer page
+    pub fn with_answer_order(mut self, answer_order: AnswerOrder) -> Self {
+        self.answer_order = answer_order;
+        self
+    }
+
+    /// Choose how errors are shown on the device
+    pub fn with_error_display(mut self, error_display: ErrorDisplay) -> Self {
+        self.error_display = error_display;
+        self
+    }
+
+    /// Send screenshots to the LLM as URLs published by this server instead of inline data
+    pub fn with_image_server(mut self, image_server: Option<ImageServer>) -> Self {
+        self.image_server = image_server;
+        self
+    }
+
+    /// Wait for the screen to stop changing after navigation instead of sleeping a fixed time
+    /// `threshold` is the fraction of pixels allowed to differ between consecutive captures
+    pub fn with_settle_check(mut self, timeout: Option<Duration>, threshold: f32) -> Self {
+        self.settle_timeout = timeout;
+        self.settle_threshold = threshold;
+        self
+    }
+
+    /// Cap on consecutive page turns in any end-seeking navigation loop
+    pub fn with_max_nav_steps(mut self, max_nav_steps: usize) -> Self {
+        self.max_nav_steps = max_nav_steps;
+        self
+    }
+
+    /// Trade bitmap drawing fidelity (symbols, markers) for speed
+    pub fn with_draw_speed(mut self, draw_speed: DrawSpeed) -> Self {
+        self.pen.set_draw_speed(draw_speed);
+        self
+    }
+
+    /// Type in chunks of `chunk_size` keys, pausing `pause` after each (0 = no chunks)
+    pub fn with_typing_chunks(mut self, chunk_size: usize, pause: Duration) -> Self {
+        self.keyboard.set_chunking(chunk_size, pause);
+        self
+    }
+
+    /// Check that typed text shows up on the page before typing anything real, and
+    /// that each entry roughly all appeared afterwards (retyping it if not)
+    pub fn with_verify_typing(mut self, verify_typing: bool) -> Self {
+        self.verify_typing = verify_typing;
+        self
+    }
+
+    /// Choose the background drawn on new (blank) answer pages
+    pub fn with_answer_template(mut self, answer_template: AnswerTemplate) -> Self {
+        self.answer_template = answer_template;
+        self
+    }
+
+    /// Choose how the question region is erased
+    pub fn with_erase_mode(mut self, erase_mode: EraseMode) -> Self {
+        self.erase_mode = erase_mode;
+        self
+    }
+
+    /// Tune contour erasing for the pen in use: heavier strokes need a larger
+    /// `margin`; `threshold` fixes the ink level (None = from the threshold mode)
+    pub fn with_erase_tuning(mut self, margin: u8, threshold: Option<u8>) -> Self {
+        self.erase_margin = margin;
+        self.erase_threshold = threshold;
+        self
+    }
+
+    /// Choose how reference symbols are drawn; `font` is used by the Font style
+    /// (None = the embedded font)
+    pub fn with_symbol_style(mut self, style: SymbolStyle, font: Option<PathBuf>) -> Self {
+        self.symbol_style = style;
+        self.symbol_font = font;
+        self
+    }
+
+    /// Choose what happens when a new page can't be confirmed after the creation gesture
+    pub fn with_page_create_fallback(mut self, fallback: PageCreateFallback, retries: u32) -> Self {
+        self.page_create_fallback = fallback;
+        self.page_create_retries = retries;
+        self
+    }
+
+    /// Allow deleting pages
+    pub fn with_page_delete(mut self, allowed: bool) -> Self {
+        self.allow_page_delete = allowed;
+        self
+    }
+
+    /// Menu tap positions to use on this firmware
+    pub fn with_ui_layout(mut self, ui_layout: UiLayout) -> Self {
+        self.ui_layout = ui_layout;
+        self
+    }
+
+    /// Save every captured screenshot for debugging, keeping only the newest `keep` files
+    /// (0 keeps everything)
+    pub fn with_debug_dump(mut self, enabled: bool, keep: usize) -> Self {
+        self.debug_dump = enabled;
+        self.debug_keep = keep;
+        self
+    }
+
+    /// Write debug artifacts under `dir` instead of DEFAULT_DEBUG_DIR
+    pub fn with_debug_dir(mut self, dir: PathBuf) -> Self {
+        self.debug_dir = dir;
+        self
+    }
+
+    /// Choose the ink threshold used by erasing and page checks
+    pub fn with_threshold_mode(mut self, threshold_mode: ThresholdMode) -> Self {
+        self.threshold_mode = threshold_mode;
+        self
+    }
+
+    /// Whether the most recent capture has (next to) no ink
+    pub fn last_screenshot_is_blank(&self) -> Result<bool> {
+        is_blank_page(self.screenshot.get_image_data(), self.threshold_mode)
+    }
+
+    /// Analyze this saved PNG instead of capturing the screen, without waiting for a trigger
+    /// Only the first capture is replaced; later ones (page checks, settling) see the device
+    pub fn with_replay(mut self, png_data: Option<Vec<u8>>) -> Self {
+        self.set_replay(png_data);
+        self
+    }
+
+    /// Replace the next capture with this saved PNG (see with_replay)
+    pub fn set_replay(&mut self, png_data: Option<Vec<u8>>) {
+        self.replay = png_data;
+    }
+
+    /// Listen on several trigger corners (e.g. one per bound action)
+    pub fn with_trigger_corners(mut self, corners: Vec<TriggerCorner>) -> Self {
+        if !corners.is_empty() {
+            self.touch.set_trigger_corners(corners);
+        }
+        self
+    }
+
     /// Wait for user to trigger the workflow (touch in corner)
-    pub fn wait_for_trigger(&mut self) -> Result<()> {
+    /// Returns the corner that was tapped
+    pub fn wait_for_trigger(&mut self) -> Result<TriggerCorner> {
+        if self.replay.is_some() {
+            info!("Replaying a saved screenshot; not waiting for a trigger");
+            return self
+                .touch
+                .trigger_corners()
+                .first()
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("No trigger corner configured"));
+        }
+
         info!("Waiting for trigger...");
-        self.touch.wait_for_trigger()?;
+        let corner = self.touch.wait_for_trigger()?;
         self.touch.tap_middle_bottom()?;
+        Ok(corner)
+    }
+
+    /// Wait up to `timeout` for a trigger; None if nothing happened
+    /// Lets callers do periodic work between polls instead of blocking in wait_for_trigger
+    pub fn poll_for_trigger(&mut self, timeout: Duration) -> Result<Option<TriggerCorner>> {
+        let Some(corner) = self.touch.poll_trigger(timeout)? else {
+            return Ok(None);
+        };
+        self.touch.tap_middle_bottom()?;
+        Ok(Some(corner))
+    }
+
+    /// PNG of the most recent capture (768x1024, before any LLM preprocessing)
+    pub fn last_screenshot(&self) -> &[u8] {
+        self.screenshot.get_image_data()
+    }
+
+    /// Capture the screen as it is now, for comparing against later with `shows_page`
+    pub fn snapshot_page(&mut self) -> Result<Vec<u8>> {
+        self.screenshot.take_screenshot()?;
+        Ok(self.screenshot.get_image_data().to_vec())
+    }
+
+    /// Whether the screen shows the same page as `snapshot`
+    /// Looser than the settle threshold, since e-ink ghosting lingers after page turns
+    pub fn shows_page(&mut self, snapshot: &[u8]) -> Result<bool> {
+        // Different pages of text differ by far more than this
+        const SAME_PAGE_THRESHOLD: f32 = 0.01;
+
+        self.screenshot.take_screenshot()?;
+        let difference = image_difference(snapshot, self.screenshot.get_image_data())?;
+        debug!("Difference from the expected page: {:.4}", difference);
+        Ok(difference <= self.settle_threshold.max(SAME_PAGE_THRESHOLD))
+    }
+
+    /// Take a screenshot, or use the pending replay image instead
+    fn capture(&mut self) -> Result<()> {
+        match self.replay.take() {
+            Some(png_data) => {
+                info!("Using replayed screenshot");
+                self.screenshot.load_png(&png_data)
+            }
+            None => {
+                info!("Capturing screenshot...");
+                self.screenshot.take_screenshot()
+            }
+        }
+    }
+
+    /// Capture, then run the quality check; a capture that fails it is retaken once
+    /// (replayed screenshots are not, since a retake would read the real screen)
+    fn capture_checked(&mut self) -> Result<()> {
+        let replayed = self.replay.is_some();
+        self.capture()?;
+        let Some(quality_check) = self.quality_check else {
+            return Ok(());
+        };
+
+        if let Err(e) = quality_check.check(self.screenshot.get_image_data()) {
+            if replayed {
+                return Err(e);
+            }
+            warn!("{}; capturing again", e);
+            sleep(Duration::from_millis(500));
+            self.capture()?;
+            quality_check
+                .check(self.screenshot.get_image_data())
+                .map_err(|e| anyhow::anyhow!("Capture error: {}", e))?;
+        }
         Ok(())
     }
 
     /// Take a screenshot and return the base64-encoded image
     pub fn capture_screenshot(&mut self) -> Result<String> {
-        info!("Capturing screenshot...");
-        self.screenshot.take_screenshot()?;
+        self.capture()?;
+        if self.debug_dump {
+            self.dump_screenshot()?;
+        }
         self.screenshot.base64()
     }
 
+    /// Take a screenshot and return it as a PNG data URI ready to send to the LLM
+    /// Lower-allocation alternative to capture_screenshot for large images
+    pub fn capture_screenshot_data_uri(&mut self) -> Result<String> {
+        self.capture()?;
+        if self.debug_dump {
+            self.dump_screenshot()?;
+        }
+        self.screenshot.data_uri()
+    }
+
+    /// Take a screenshot and return the image URL to send to the LLM
+    /// This is an http:// link when an image server is configured, otherwise a data URI
+    pub fn capture_screenshot_for_llm(&mut self) -> Result<String> {
+        if self.image_server.is_none() {
+            self.capture_checked()?;
+            if self.debug_dump {
+                self.dump_screenshot()?;
+            }
+            return self.screenshot.data_uri();
+        }
+
+        self.capture_checked()?;
+        let image_server = self.image_server.as_mut().expect("checked above");
+        let url = image_server.publish(&self.screenshot.llm_image_data()?)?;
+        if self.debug_dump {
+            self.dump_screenshot()?;
+        }
+        Ok(url)
+    }
+
+    /// Write the current screenshot to the debug dump location and prune old dumps
+    fn dump_screenshot(&mut self) -> Result<()> {
+        let filename = self
+            .debug_dir
+            .join(format!(
+                "{}{:03}.png",
+                DEBUG_DUMP_PREFIX, self.debug_dump_count
+            ))
+            .to_string_lossy()
+            .to_string();
+        self.screenshot.save_image(&filename)?;
+        info!("Saved debug screenshot to {}", filename);
+
+        // Keep the preprocessed LLM image next to the raw capture for comparison
+        let mut files_per_dump = 1;
+        if self.screenshot.has_preprocessing() {
+            let processed_filename = self.debug_dir.join(format!(
+                "{}{:03}-processed.png",
+                DEBUG_DUMP_PREFIX, self.debug_dump_count
+            ));
+            fs::write(&processed_filename, self.screenshot.llm_image_data()?)?;
+            info!(
+                "Saved preprocessed debug screenshot to {}",
+                processed_filename.display()
+            );
+            files_per_dump = 2;
+        }
+        self.debug_dump_count += 1;
+
+        if self.debug_keep > 0 {
+            Self::prune_debug_dumps(&self.debug_dir, self.debug_keep * files_per_dump)?;
+        }
+        Ok(())
+    }
+
+    /// Delete all but the `keep` most recently written debug dumps
+    fn prune_debug_dumps(dir: &Path, keep: usize) -> Result<()> {
+        let mut dumps = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with(DEBUG_DUMP_PREFIX) && name.ends_with(".png") {
+                let modified = entry.metadata()?.modified()?;
+                dumps.push((modified, entry.path()));
+            }
+        }
+
+        // Newest first
+        dumps.sort_by_key(|d| std::cmp::Reverse(d.0));
+        for (_, path) in dumps.iter().skip(keep) {
+            debug!("Pruning old debug dump {}", path.display());
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
     /// Show progress indicator to user
     pub fn show_progress(&mut self, message: &str) -> Result<()> {
         self.keyboard.progress(message)?;
@@ -59,6 +920,21 @@ impl Workflow {
             region.x, region.y, region.width, region.height
         );
 
+        if self.erase_mode == EraseMode::Contour {
+            self.screenshot.take_screenshot()?;
+            let strokes = contour_erase_strokes(
+                self.screenshot.get_image_data(),
+                region,
+                self.erase_margin,
+                self.threshold_mode,
+                self.erase_threshold,
+            )?;
+            if !strokes.is_empty() {
+                return self.pen.erase_strokes(&strokes);
+            }
+            debug!("No ink found in the region; erasing the whole box");
+        }
+
         let top_left = (region.x, region.y);
         let bottom_right = (region.x + region.width, region.y + region.height);
 
@@ -68,34 +944,119 @@ impl Workflow {
         Ok(())
     }
 
+    /// Draw the answer template on the current page if it is still blank
+    /// Pages that already have content (earlier answers, or a template) are left alone
+    pub fn apply_answer_template(&mut self) -> Result<()> {
+        if self.answer_template == AnswerTemplate::Blank {
+            return Ok(());
+        }
+        self.screenshot.take_screenshot()?;
+        if !is_blank_page(self.screenshot.get_image_data(), self.threshold_mode)? {
+            return Ok(());
+        }
+
+        info!("Drawing {:?} answer template", self.answer_template);
+        for y in (TEMPLATE_TOP..=TEMPLATE_BOTTOM).step_by(TEMPLATE_LINE_SPACING as usize) {
+            self.pen
+                .draw_line_screen((TEMPLATE_LEFT, y), (TEMPLATE_RIGHT, y))?;
+        }
+        if self.answer_template == AnswerTemplate::Grid {
+            for x in (TEMPLATE_LEFT..=TEMPLATE_RIGHT).step_by(TEMPLATE_LINE_SPACING as usize) {
+                self.pen
+                    .draw_line_screen((x, TEMPLATE_TOP), (x, TEMPLATE_BOTTOM))?;
+            }
+        }
+        Ok(())
+    }
+
     /// Draw a reference symbol at a location using bitmap rendering
     pub fn draw_symbol(&mut self, x: i32, y: i32, symbol: &str) -> Result<()> {
         info!("Drawing reference symbol '{}' at ({}, {})", symbol, x, y);
 
-        // Convert symbol to bitmap
-        let size = 20; // Symbol size in pixels
-        let bitmap = symbol_pool::SymbolPool::symbol_to_bitmap(symbol, size);
+        let bitmap = self.symbol_bitmap(symbol)?;
 
-        // Draw the bitmap at the specified location
-        // Note: This draws the full bitmap starting at (x, y)
-        // For centered placement, we'd offset by -size/2
-        let offset_x = x - (size as i32 / 2);
-        let offset_y = y - (size as i32 / 2);
+        // Center the symbol on (x, y)
+        let offset_x = x - (SYMBOL_SIZE as i32 / 2);
+        let offset_y = y - (SYMBOL_SIZE as i32 / 2);
+        self.draw_bitmap_at(offset_x, offset_y, &bitmap)
+    }
+
+    /// Which of `symbols` is drawn on the current page centered near one of `positions`
+    /// Takes a fresh capture; symbols are compared as draw_symbol would draw them
+    pub fn find_symbol_near(
+        &mut self,
+        positions: &[(i32, i32)],
+        symbols: &[String],
+    ) -> Result<Option<String>> {
+        let candidates = symbols
+            .iter()
+            .map(|symbol| Ok((symbol.clone(), self.symbol_bitmap(symbol)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.screenshot.take_screenshot()?;
+        for &position in positions {
+            let found = find_symbol(
+                self.screenshot.get_image_data(),
+                self.threshold_mode,
+                position,
+                &candidates,
+            )?;
+            if found.is_some() {
+                return Ok(found);
+            }
+        }
+        Ok(None)
+    }
+
+    /// A reference symbol as drawn in the configured style
+    fn symbol_bitmap(&self, symbol: &str) -> Result<Vec<Vec<bool>>> {
+        Ok(match self.symbol_style {
+            SymbolStyle::Pixel => symbol_pool::SymbolPool::symbol_to_bitmap(symbol, SYMBOL_SIZE),
+            SymbolStyle::Font => symbol_pool::SymbolPool::symbol_to_bitmap_font(
+                symbol,
+                SYMBOL_SIZE,
+                self.symbol_font.as_deref(),
+            )?,
+        })
+    }
+
+    /// Draw an arrow from `from` to `to` on the current page
+    pub fn draw_arrow(&mut self, from: (i32, i32), to: (i32, i32)) -> Result<()> {
+        info!("Drawing arrow from {:?} to {:?}", from, to);
+        self.pen.draw_arrow(from, to)
+    }
 
+    /// Draw the answer page marker in the top margin of the current page
+    /// Later checks recognise the page by it (see detect_answer_page_marker)
+    pub fn draw_answer_page_marker(&mut self) -> Result<()> {
+        info!("Drawing answer page marker");
+        self.draw_bitmap_at(MARKER_X, MARKER_Y, &answer_page_marker_bitmap())
+    }
+
+    /// Whether the current page carries the answer page marker
+    pub fn detect_answer_page_marker(&mut self) -> Result<bool> {
+        self.screenshot.take_screenshot()?;
+        has_answer_page_marker(self.screenshot.get_image_data(), self.threshold_mode)
+    }
+
+    /// Draw a bitmap with its top-left corner at (x, y), clipped to the screen
+    fn draw_bitmap_at(&mut self, x: i32, y: i32, bitmap: &[Vec<bool>]) -> Result<()> {
         // Create a positioned bitmap by building a temporary full-size bitmap
         // This is not optimal but works for MVP
         let mut positioned_bitmap = vec![vec![false; 768]; 1024];
         for (dy, row) in bitmap.iter().enumerate() {
             for (dx, &pixel) in row.iter().enumerate() {
-                let px = offset_x + dx as i32;
-                let py = offset_y + dy as i32;
+                let px = x + dx as i32;
+                let py = y + dy as i32;
                 if (0..768).contains(&px) && (0..1024).contains(&py) {
                     positioned_bitmap[py as usize][px as usize] = pixel;
                 }
             }
         }
 
+        let start = Instant::now();
         self.pen.draw_bitmap(&positioned_bitmap)?;
+        debug!("Drew bitmap in {}ms", start.elapsed().as_millis());
 
         Ok(())
     }
@@ -103,9 +1064,199 @@ impl Workflow {
     /// Render text on the screen using the keyboard
     pub fn render_text(&mut self, text: &str) -> Result<()> {
         info!("Rendering text: {}", text);
+        // Start from the end so we never type into the middle of existing text
+        self.keyboard.move_to_document_end()?;
         self.keyboard.key_cmd_body()?;
-        self.keyboard.string_to_keypresses(text)?;
-        self.keyboard.string_to_keypresses("\n\n")?;
+        self.type_entry(text)
+    }
+
+    /// Render an answer entry on the answer page, honoring the answer order
+    /// With --answer-as-ink the entry is drawn instead (always below existing ink)
+    pub fn render_answer_text(&mut self, text: &str) -> Result<()> {
+        if let Some(layout) = self.answer_ink {
+            return self.render_answer_ink(text, layout);
+        }
+        match self.answer_order {
+            AnswerOrder::Append => self.render_text(text),
+            AnswerOrder::Prepend => {
+                info!("Rendering text at top: {}", text);
+                self.begin_answer_entry()?;
+                self.type_entry(text)
+            }
+        }
+    }
+
+    /// Put the cursor where the next answer entry goes, per the answer order
+    pub fn begin_answer_entry(&mut self) -> Result<()> {
+        // Always insert at the very start or end so placement doesn't depend on existing text
+        match self.answer_order {
+            AnswerOrder::Append => self.keyboard.move_to_document_end()?,
+            AnswerOrder::Prepend => self.keyboard.move_to_document_start()?,
+        }
+        self.keyboard.key_cmd_body()
+    }
+
+    /// Type part of an answer entry at the cursor (see begin_answer_entry)
+    /// Pieces are typed as they come, so --verify-typing doesn't apply
+    pub fn type_answer_part(&mut self, text: &str) -> Result<()> {
+        debug!("Typing answer part: {}", text);
+        self.keyboard.string_to_keypresses(text)
+    }
+
+    /// Draw `text` with the pen below whatever ink the page already has
+    ///
+    /// The text is rendered with the SVG font into a bitmap, so it lives on the ink
+    /// layer and can't be edited or selected like typed text.
+    fn render_answer_ink(&mut self, text: &str, layout: InkLayout) -> Result<()> {
+        // Space left between earlier ink and the new entry
+        const ENTRY_GAP: i32 = 24;
+
+        info!("Drawing answer as ink: {}", text);
+        let bitmap = svg_to_bitmap(&layout.svg(text), self.symbol_font.as_deref())?;
+
+        self.screenshot.take_screenshot()?;
+        let top = match lowest_ink_row(self.screenshot.get_image_data(), self.threshold_mode)? {
+            Some(row) => layout.y.max(row + ENTRY_GAP),
+            None => layout.y,
+        };
+        if top + bitmap.len() as i32 > 1024 {
+            anyhow::bail!(
+                "Answer needs {} rows of ink but only {} are free below y={}",
+                bitmap.len(),
+                (1024 - top).max(0),
+                top
+            );
+        }
+        self.draw_bitmap_at(layout.x, top, &bitmap)
+    }
+
+    /// Type `text` and a blank line at the cursor
+    ///
+    /// With --verify-typing the screen change is compared with what the probe predicts
+    /// for this many characters. Text that mostly didn't appear (dropped keystrokes,
+    /// lost focus) is backspaced away and typed again, up to TYPING_ATTEMPTS times.
+    /// With chunked typing each chunk is checked and retried on its own.
+    fn type_entry(&mut self, text: &str) -> Result<()> {
+        let Some(ink_per_char) = self.verify_text_input()? else {
+            self.keyboard.string_to_keypresses(text)?;
+            return self.keyboard.string_to_keypresses("\n\n");
+        };
+
+        let entry: Vec<char> = format!("{}\n\n", text).chars().collect();
+        let chunk_size = match self.keyboard.chunk_size() {
+            0 => entry.len(),
+            size => size,
+        };
+        for chunk in entry.chunks(chunk_size.max(1)) {
+            let chunk: String = chunk.iter().collect();
+            self.type_verified(&chunk, ink_per_char)?;
+        }
+        Ok(())
+    }
+
+    /// Type `text`, check that roughly all of it appeared, and retype it if not
+    fn type_verified(&mut self, text: &str, ink_per_char: f32) -> Result<()> {
+        let visible_chars = text
+            .chars()
+            .filter(|c| c.is_ascii_graphic())
+            .count()
+            .min(MAX_VERIFIED_CHARS);
+        let expected = ink_per_char * visible_chars as f32 * MIN_TYPED_INK_RATIO;
+
+        for attempt in 1..=TYPING_ATTEMPTS {
+            self.screenshot.take_screenshot()?;
+            let before = self.screenshot.get_image_data().to_vec();
+            self.keyboard.string_to_keypresses(text)?;
+            sleep(Duration::from_millis(500));
+
+            self.screenshot.take_screenshot()?;
+            let difference = image_difference(&before, self.screenshot.get_image_data())?;
+            if difference >= expected {
+                debug!(
+                    "Typed text verified (difference {:.5}, expected at least {:.5})",
+                    difference, expected
+                );
+                return Ok(());
+            }
+            warn!(
+                "Typed text looks incomplete (difference {:.5}, expected at least {:.5}), attempt {}/{}",
+                difference, expected, attempt, TYPING_ATTEMPTS
+            );
+            if attempt == TYPING_ATTEMPTS {
+                break;
+            }
+
+            // Remove what did arrive; if keys were dropped this can also take some
+            // characters before the text, so only retry when the page is back as it was
+            for _ in 0..text.chars().count() {
+                self.keyboard.string_to_keypresses("\x08")?;
+            }
+            sleep(Duration::from_millis(500));
+            self.screenshot.take_screenshot()?;
+            if image_difference(&before, self.screenshot.get_image_data())? > self.settle_threshold
+            {
+                anyhow::bail!(
+                    "Typed text was incomplete and could not be removed cleanly; not retrying"
+                );
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "Typed text still incomplete after {} attempt(s)",
+            TYPING_ATTEMPTS
+        ))
+    }
+
+    /// Type a probe, check that it appeared on screen, then delete it (--verify-typing)
+    /// Fails without typing anything else when xochitl isn't in a text-capable document
+    /// (e.g. showing the file browser), where keystrokes would vanish or act as shortcuts.
+    /// Returns the screen change per probe character (None when verification is off)
+    fn verify_text_input(&mut self) -> Result<Option<f32>> {
+        if !self.verify_typing {
+            return Ok(None);
+        }
+
+        self.screenshot.take_screenshot()?;
+        let before = self.screenshot.get_image_data().to_vec();
+        self.keyboard.string_to_keypresses(TYPING_PROBE)?;
+        // Give the e-ink display time to show the probe
+        sleep(Duration::from_millis(500));
+        self.screenshot.take_screenshot()?;
+        let difference = image_difference(&before, self.screenshot.get_image_data())?;
+        if difference == 0.0 {
+            anyhow::bail!(
+                "Typed text does not appear on the page; xochitl may not be showing a \
+                 document with text support. Nothing was typed"
+            );
+        }
+
+        debug!("Typing check passed (difference {:.5})", difference);
+        for _ in 0..TYPING_PROBE.len() {
+            self.keyboard.string_to_keypresses("\x08")?;
+        }
+        Ok(Some(difference / TYPING_PROBE.len() as f32))
+    }
+
+    /// Show an error to the user using the configured error display
+    pub fn show_error(&mut self, message: &str) -> Result<()> {
+        match self.error_display {
+            ErrorDisplay::Corner => {
+                info!("Marking error in corner: {}", message);
+                // A small "X" in the upper-left margin, away from the text and trigger zone
+                self.pen.draw_line_screen((12, 12), (36, 36))?;
+                self.pen.draw_line_screen((36, 12), (12, 36))?;
+            }
+            ErrorDisplay::LogPage => {
+                info!("Writing error to log page: {}", message);
+                // A blank page at the end, never the next page of the document
+                let pages = self.create_page_at_end()?;
+                self.render_text(&format!("Reader Buddy Log\n\nError: {}", message))?;
+                self.navigate_back(pages)?;
+            }
+            ErrorDisplay::Inline => {
+                self.render_text(&format!("Error: {}", message))?;
+            }
+        }
         Ok(())
     }
 
@@ -124,15 +1275,431 @@ impl Workflow {
         &mut self.touch
     }
 
-    /// Create a new page to the right of the current page
-    pub fn create_new_page_right(&mut self) -> Result<()> {
-        page_manager::PageManager::create_page_right(&mut self.touch)?;
-        Ok(())
+    /// Go to a blank page at the end of the document, creating it if needed
+    ///
+    /// Turns forward to the last page (see navigate_to_last_page). If that page isn't
+    /// blank, the document didn't grow a new page, and --page-create-fallback decides:
+    /// retry turns past the last page again, menu adds a page through xochitl's menu
+    /// (the `new_page` taps), abort gives up. A page only counts once the screen
+    /// shows a blank page. On failure the device is taken back to where it started.
+    /// Returns the number of pages moved forward.
+    pub fn create_page_at_end(&mut self) -> Result<usize> {
+        let mut pages = self.navigate_to_last_page()?;
+        if self.last_screenshot_is_blank()? {
+            return Ok(pages);
+        }
+
+        let attempts = match self.page_create_fallback {
+            PageCreateFallback::Retry | PageCreateFallback::Menu => self.page_create_retries,
+            PageCreateFallback::Abort => 0,
+        };
+        for attempt in 1..=attempts {
+            let before = self.screenshot.get_image_data().to_vec();
+            if self.page_create_fallback == PageCreateFallback::Menu {
+                info!(
+                    "Last page is not blank; adding a page via the menu ({}/{})",
+                    attempt, attempts
+                );
+                PageManager::create_page(&mut self.touch, &self.ui_layout.new_page)?;
+            } else {
+                info!(
+                    "Last page is not blank; turning past it again ({}/{})",
+                    attempt, attempts
+                );
+                self.turn_page(true)?;
+            }
+            self.wait_for_page_settle(Duration::from_millis(500))?;
+
+            self.screenshot.take_screenshot()?;
+            let current = self.screenshot.get_image_data();
+            let changed = image_difference(&before, current)? > self.settle_threshold;
+            if changed && is_blank_page(current, self.threshold_mode)? {
+                info!("Created a new page at the end");
+                return Ok(pages + 1);
+            }
+            // A turn that lands on another page with content still moved forward
+            if changed && self.page_create_fallback == PageCreateFallback::Retry {
+                pages += 1;
+            }
+            warn!(
+                "No blank page after creation attempt {}/{}",
+                attempt, attempts
+            );
+        }
+
+        self.navigate_back(pages)?;
+        Err(anyhow::anyhow!(
+            "Could not get a blank page at the end of the document \
+             (--page-create-fallback {:?}, {} attempt(s))",
+            self.page_create_fallback,
+            attempts
+        ))
+    }
+
+    /// Send one page turn, forward or back
+    ///
+    /// With the swipe guard on, a swipe that drew a line (pen tool active) instead
+    /// of turning the page is undone and the turn is repeated with the arrow keys,
+    /// which can't draw; later turns keep using the keyboard.
+    fn turn_page(&mut self, forward: bool) -> Result<()> {
+        let guard = self.swipe_guard && self.nav_method == NavMethod::Swipe;
+        let before = if guard {
+            self.screenshot.take_screenshot()?;
+            Some(self.screenshot.get_image_data().to_vec())
+        } else {
+            None
+        };
+
+        if forward {
+            PageManager::next_page(
+                &mut self.touch,
+                &mut self.keyboard,
+                self.nav_method,
+                &self.swipe,
+            )?;
+        } else {
+            PageManager::previous_page(
+                &mut self.touch,
+                &mut self.keyboard,
+                self.nav_method,
+                &self.swipe,
+            )?;
+        }
+
+        let Some(before) = before else {
+            return Ok(());
+        };
+        self.wait_for_page_settle(Duration::from_millis(500))?;
+        self.screenshot.take_screenshot()?;
+        let (start_x, end_x) = if forward {
+            (self.swipe.start_x, self.swipe.end_x)
+        } else {
+            (self.swipe.end_x, self.swipe.start_x)
+        };
+        if !is_swipe_stroke(
+            &before,
+            self.screenshot.get_image_data(),
+            start_x,
+            end_x,
+            self.swipe.y,
+        )? {
+            return Ok(());
+        }
+
+        warn!("Swipe drew a line instead of turning the page (pen tool active?); undoing it");
+        self.keyboard.undo()?;
+        self.wait_for_page_settle(Duration::from_millis(500))?;
+        warn!("Switching page turns to the keyboard for the rest of this session");
+        self.nav_method = NavMethod::Keyboard;
+        if forward {
+            self.keyboard.arrow_right()
+        } else {
+            self.keyboard.arrow_left()
+        }
     }
 
     /// Navigate back to the previous page
     pub fn navigate_to_previous_page(&mut self) -> Result<()> {
-        page_manager::PageManager::previous_page(&mut self.touch)?;
+        self.turn_page(false)?;
+        self.wait_for_page_settle(Duration::from_millis(300))?;
+        Ok(())
+    }
+
+    /// Navigate forward to the next page
+    pub fn navigate_to_next_page(&mut self) -> Result<()> {
+        self.turn_page(true)?;
+        self.wait_for_page_settle(Duration::from_millis(300))?;
+        Ok(())
+    }
+
+    /// Turn to the next page and check whether answers can be written there
+    /// Leaves the device on the next page (or a newly created one if this was the last page)
+    pub fn check_if_next_page_is_answer_page(&mut self) -> Result<AnswerPageCheck> {
+        self.turn_page(true)?;
+        self.wait_for_page_settle(Duration::from_millis(500))?;
+
+        self.screenshot.take_screenshot()?;
+        let page = self.screenshot.get_image_data();
+        let check = if has_answer_page_marker(page, self.threshold_mode)? {
+            AnswerPageCheck::IsAnswerPage
+        } else {
+            classify_answer_page(page, self.threshold_mode)?
+        };
+        info!("Next page check: {:?}", check);
+        Ok(check)
+    }
+
+    /// Delete all typed text on the answer page after the current page
+    /// Refuses (and returns to the current page) unless the next page is an answer page
+    pub fn clear_answer_page(&mut self) -> Result<()> {
+        let check = self.check_if_next_page_is_answer_page()?;
+        if check != AnswerPageCheck::IsAnswerPage {
+            self.navigate_to_previous_page()?;
+            anyhow::bail!(
+                "Next page does not look like an answer page ({:?}); not clearing it",
+                check
+            );
+        }
+
+        info!("Clearing the answer page");
+        self.keyboard.select_all()?;
+        self.keyboard.delete_selection()?;
+        self.navigate_to_previous_page()?;
+        Ok(())
+    }
+
+    /// Delete the current page (needs --allow-page-delete)
+    ///
+    /// Fails if the screen is unchanged afterwards, i.e. the menu taps did not remove
+    /// a page. The page count itself can't be read, so a changed screen is the only
+    /// confirmation that a page went away.
+    pub fn delete_current_page(&mut self) -> Result<()> {
+        if !self.allow_page_delete {
+            anyhow::bail!("Page deletion is disabled; start with --allow-page-delete to enable it");
+        }
+
+        self.screenshot.take_screenshot()?;
+        let before = self.screenshot.get_image_data().to_vec();
+
+        PageManager::delete_page(&mut self.touch, &self.ui_layout.delete_page)?;
+        self.wait_for_page_settle(Duration::from_millis(500))?;
+
+        self.screenshot.take_screenshot()?;
+        let difference = image_difference(&before, self.screenshot.get_image_data())?;
+        if difference <= self.settle_threshold {
+            anyhow::bail!("Screen did not change after the delete gesture; page not deleted");
+        }
+        info!("Page deleted (screen difference {:.4})", difference);
+        Ok(())
+    }
+
+    /// Wipe the typed text on a scratch page and delete the page if allowed
+    ///
+    /// The page must be blank once the text is gone; otherwise it holds something
+    /// else (e.g. handwriting, or we are on the wrong page) and is left alone.
+    /// Returns whether the page was deleted. Without --allow-page-delete the blank page
+    /// stays at the end of the document, where the next scratch or new answer page reuses it.
+    pub fn discard_scratch_page(&mut self) -> Result<bool> {
+        info!("Discarding scratch page");
+        self.keyboard.select_all()?;
+        self.keyboard.delete_selection()?;
+        self.wait_for_page_settle(Duration::from_millis(500))?;
+
+        self.screenshot.take_screenshot()?;
+        if !is_blank_page(self.screenshot.get_image_data(), self.threshold_mode)? {
+            anyhow::bail!("Scratch page is not blank after clearing its text; not deleting it");
+        }
+        if !self.allow_page_delete {
+            info!("Page deletion not enabled; leaving the blank page in place");
+            return Ok(false);
+        }
+
+        self.delete_current_page()?;
+        Ok(true)
+    }
+
+    /// Open a document by the name shown in the xochitl library (--answer-document)
+    ///
+    /// The name is looked up in xochitl's metadata first, so a missing document fails
+    /// before anything is tapped. Then the `open_search` taps leave the current
+    /// document for the library search, the name is typed, and the `search_result`
+    /// taps open the first hit. xochitl reopens a document on the page last shown.
+    pub fn open_document(&mut self, name: &str) -> Result<()> {
+        let document = find_document_by_name(name)?
+            .ok_or_else(|| anyhow::anyhow!("No document named '{}' in the library", name))?;
+        info!(
+            "Opening document '{}' ({})",
+            document.visible_name, document.id
+        );
+        let before = self.snapshot_page()?;
+
+        PageManager::open_search(&mut self.touch, &self.ui_layout.open_search)?;
+        self.keyboard.string_to_keypresses(&document.visible_name)?;
+        self.wait_for_page_settle(Duration::from_millis(1000))?;
+        PageManager::open_search_result(&mut self.touch, &self.ui_layout.search_result)?;
+        self.wait_for_page_settle(Duration::from_millis(1500))?;
+
+        if self.virtual_devices && self.shows_page(&before)? {
+            anyhow::bail!(
+                "Screen unchanged after opening '{}'; check the open_search and \
+                 search_result taps in [ui_layout]",
+                document.visible_name
+            );
+        }
+        Ok(())
+    }
+
+    /// Navigate back `pages` pages
+    pub fn navigate_back(&mut self, pages: usize) -> Result<()> {
+        for _ in 0..pages {
+            self.navigate_to_previous_page()?;
+        }
+        Ok(())
+    }
+
+    /// Turn pages forward until the end of the document
+    ///
+    /// The end is detected when a page turn leaves the screen unchanged. Turning
+    /// forward from a non-empty last page creates a blank page, so this can finish
+    /// on a new empty page. Gives up after max_nav_steps page turns, turning back
+    /// to the page it started on first.
+    /// Returns the number of page turns that changed the page.
+    pub fn navigate_to_last_page(&mut self) -> Result<usize> {
+        self.screenshot.take_screenshot()?;
+        let start = self.screenshot.get_image_data().to_vec();
+        let (max_steps, threshold) = (self.max_nav_steps, self.settle_threshold);
+        find_last_page(self, start, max_steps, threshold)
+    }
+
+    /// Capture every answer page from the current page to the end of the document
+    ///
+    /// Answer pages are recognized by their marker. Pages are turned forward the
+    /// same way as navigate_to_last_page (so a non-empty last page gets a blank page
+    /// after it), then the device returns to the page it started on.
+    pub fn capture_answer_pages(&mut self) -> Result<Vec<Vec<u8>>> {
+        self.screenshot.take_screenshot()?;
+        let mut previous = self.screenshot.get_image_data().to_vec();
+        let mut pages = Vec::new();
+        if has_answer_page_marker(&previous, self.threshold_mode)? {
+            info!("Current page is an answer page");
+            pages.push(previous.clone());
+        }
+
+        let mut turns = 0;
+        loop {
+            if turns == self.max_nav_steps {
+                warn!(
+                    "Still turning pages after {} steps; exporting what was found",
+                    self.max_nav_steps
+                );
+                break;
+            }
+            self.turn_page(true)?;
+            self.wait_for_page_settle(Duration::from_millis(300))?;
+
+            self.screenshot.take_screenshot()?;
+            let current = self.screenshot.get_image_data().to_vec();
+            if image_difference(&previous, &current)? <= self.settle_threshold {
+                break;
+            }
+            turns += 1;
+            if has_answer_page_marker(&current, self.threshold_mode)? {
+                info!("Page {} ahead is an answer page", turns);
+                pages.push(current.clone());
+            }
+            previous = current;
+        }
+
+        info!(
+            "Found {} answer page(s) in {} page(s); returning",
+            pages.len(),
+            turns
+        );
+        self.navigate_back(turns)?;
+        Ok(pages)
+    }
+
+    /// Wait for a page transition to finish
+    /// Uses the screen stability check when enabled, otherwise sleeps for `fallback`
+    pub fn wait_for_page_settle(&mut self, fallback: Duration) -> Result<()> {
+        let Some(timeout) = self.settle_timeout else {
+            sleep(fallback);
+            return Ok(());
+        };
+
+        if let Err(e) = self.wait_for_stable_screen(timeout, self.settle_threshold) {
+            warn!("Screen stability check failed, using fixed delay: {}", e);
+            sleep(fallback);
+        }
         Ok(())
     }
+
+    /// Capture repeatedly until two consecutive screenshots differ by at most `threshold`
+    /// Returns false if the screen was still changing when `timeout` ran out
+    pub fn wait_for_stable_screen(&mut self, timeout: Duration, threshold: f32) -> Result<bool> {
+        let start = Instant::now();
+        self.screenshot.take_screenshot()?;
+        let mut previous = self.screenshot.get_image_data().to_vec();
+
+        while start.elapsed() < timeout {
+            sleep(Duration::from_millis(100));
+            self.screenshot.take_screenshot()?;
+            let current = self.screenshot.get_image_data();
+            let difference = image_difference(&previous, current)?;
+            if difference <= threshold {
+                debug!(
+                    "Screen settled after {}ms (difference {:.4})",
+                    start.elapsed().as_millis(),
+                    difference
+                );
+                return Ok(true);
+            }
+            previous = current.to_vec();
+        }
+
+        warn!("Screen did not settle within {}ms", timeout.as_millis());
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, ImageFormat, Luma};
+    use std::io::Cursor;
+
+    /// A document whose pages are plain gray screens; turning past the last page
+    /// stays on it. `pages: None` never ends.
+    struct FakeDocument {
+        pages: Option<usize>,
+        position: usize,
+    }
+
+    impl FakeDocument {
+        fn screen(&self) -> Vec<u8> {
+            let shade = (self.position * 40 % 256) as u8;
+            let mut png = Vec::new();
+            GrayImage::from_pixel(8, 8, Luma([shade]))
+                .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+                .unwrap();
+            png
+        }
+    }
+
+    impl PageTurner for FakeDocument {
+        fn forward_and_capture(&mut self) -> Result<Vec<u8>> {
+            if self.pages.is_none_or(|pages| self.position + 1 < pages) {
+                self.position += 1;
+            }
+            Ok(self.screen())
+        }
+
+        fn back(&mut self, pages: usize) -> Result<()> {
+            self.position -= pages;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_find_last_page_stops_at_the_end() {
+        let mut document = FakeDocument {
+            pages: Some(4),
+            position: 1,
+        };
+        let start = document.screen();
+        assert_eq!(find_last_page(&mut document, start, 10, 0.01).unwrap(), 2);
+        assert_eq!(document.position, 3);
+    }
+
+    #[test]
+    fn test_find_last_page_goes_back_when_over_the_cap() {
+        let mut document = FakeDocument {
+            pages: None,
+            position: 2,
+        };
+        let start = document.screen();
+        let error = find_last_page(&mut document, start, 5, 0.01).unwrap_err();
+        assert!(error.to_string().contains("--max-nav-steps"));
+        assert_eq!(document.position, 2);
+    }
 }
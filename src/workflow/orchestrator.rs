@@ -1,16 +1,176 @@
 use anyhow::Result;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{mpsc, Mutex};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
 
-use super::{symbol_pool::SymbolPool, Workflow};
-use crate::analysis::BoundingBox;
+use super::{
+    answer_stream::{AnswerStream, StreamPart},
+    response_format::ResponseFormat,
+    symbol_pool::SymbolPool,
+    validation,
+    watchdog::Watchdog,
+    webhook::Webhook,
+    AnswerFormat, CornerAction, OnAmbiguous, PostAnswerAction, Workflow, SYMBOL_SIZE,
+};
+use crate::analysis::{
+    answer_page::AnswerPageCheck, AnalysisMetadata, AnalysisResult, BoundingBox, QaItem,
+};
+use crate::device::{documents::current_document, speech::Speaker, touch::TriggerCorner};
 use crate::llm::{openai::OpenAI, LLMEngine};
 
-/// Result from LLM analysis containing question, answer, and bounding boxes
-struct AnalysisResult {
-    question: String,
-    answer: String,
-    question_box: Option<BoundingBox>,
-    _outline_box: Option<BoundingBox>,
+/// Prompt for the single analysis + answer call
+const ANALYSIS_PROMPT: &str =
+    "Look at this reMarkable tablet screenshot (768x1024 pixels). The user is reading and has:\n\
+     1. Drawn an outline (circle, rectangle, or any closed shape) around some content\n\
+     2. Written a handwritten question nearby about that content\n\n\
+     Your task:\n\
+     1. Identify what content has been outlined\n\
+     2. Read the handwritten question text\n\
+     3. Provide a clear, helpful answer based on the outlined content\n\
+     4. Provide approximate bounding boxes for the outline and question regions\n\n\
+     Respond EXACTLY in this format:\n\
+     QUESTION: [the extracted question text]\n\
+     QUESTION_BOX: x,y,width,height (approximate pixels where the question text is)\n\
+     OUTLINE_BOX: x,y,width,height (approximate pixels of the outline shape)\n\
+     ---\n\
+     ANSWER: [your answer]\n\n\
+     If you cannot find a clear outline or question, respond with just:\n\
+     NONE\n\n\
+     If the handwritten question is a numbered list (1. ... 2. ...), keep the numbering in \
+     QUESTION and answer every item as a matching numbered list in ANSWER.\n\n\
+     Keep the answer concise and focused. Boxes are in pixels with origin (0,0) at top-left.";
+
+/// Added to the answer prompt when the whole response is parsed at once
+const MULTI_PAIR_PROMPT: &str = "If there are several outline-question pairs on the page, \
+     answer each of them: repeat the format above once per pair, one after another, each \
+     starting with its own QUESTION: line.";
+
+/// Added to the answer prompt when the answer is streamed as it is written
+const SINGLE_PAIR_PROMPT: &str = "Process only ONE outline-question pair (the most \
+     prominent one if multiple exist).";
+
+/// Question recorded for describe requests, which have nothing written
+const DESCRIBE_QUESTION: &str = "Describe the outlined figure";
+
+/// Prompt for describe mode: no question extraction, nothing is erased
+const DESCRIBE_PROMPT: &str =
+    "Look at this reMarkable tablet screenshot (768x1024 pixels). The user has drawn an \
+     outline (circle, rectangle, or any closed shape) around a figure, diagram, chart, or \
+     image and wants it explained.\n\n\
+     Describe the outlined figure in plain language: what it shows, its parts, and what it \
+     is meant to convey. Ignore any handwriting.\n\n\
+     Respond EXACTLY in this format:\n\
+     OUTLINE_BOX: x,y,width,height (approximate pixels of the outline shape)\n\
+     ---\n\
+     ANSWER: [your description]\n\n\
+     If you cannot find an outlined figure, respond with just:\n\
+     NONE\n\n\
+     Keep the description concise. Boxes are in pixels with origin (0,0) at top-left.";
+
+/// Added to the prompt with --cite
+const CITE_PROMPT: &str = "Also add this line to the header, just before the --- line:\n\
+     QUOTE: [a short verbatim quote (one sentence or less) from the outlined content that \
+     the answer is based on]\n\
+     Copy the quote exactly as printed; do not paraphrase.";
+
+/// Added to the prompt with --answer-page-context, alongside the answer page image
+const ANSWER_PAGE_CONTEXT_PROMPT: &str = "The second image is the answer page, holding answers \
+     given earlier. Use it only as context: match its style, don't repeat an answer that is \
+     already there (refer to it briefly instead), and keep all boxes relative to the first \
+     image.";
+
+/// One answer produced by an iteration
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnsweredItem {
+    pub question: String,
+    pub answer: String,
+    /// Reference symbol linking the pages (None for scratch answers)
+    pub symbol: Option<String>,
+}
+
+/// What a call to `run_iteration` did
+#[derive(Debug, Clone, PartialEq)]
+pub enum IterationOutcome {
+    /// The tapped corner had nothing to do (unbound, nothing to redo, unconfirmed clear)
+    Ignored,
+    /// The answer page was cleared
+    Cleared,
+    /// The model found no outlined content
+    NoContentFound,
+    /// Answers were rendered; too-short answers that were skipped are not listed
+    Answered {
+        answers: Vec<AnsweredItem>,
+        /// Whether a new page at the end of the document was used for the answers
+        created_page: bool,
+    },
+    /// The model answered but rendering failed; the error was shown on the device
+    Error(String),
+}
+
+/// Per-stage timings of the runs made by `Orchestrator::benchmark`
+#[derive(Debug, Clone, Default)]
+pub struct BenchmarkReport {
+    /// Loading the PNG and preparing the image sent to the LLM
+    pub capture: Vec<Duration>,
+    /// The LLM request, including any continuations
+    pub llm: Vec<Duration>,
+    /// Parsing the response
+    pub parse: Vec<Duration>,
+    /// Runs that failed; they have no timings
+    pub failures: usize,
+}
+
+/// Analysis of one saved screenshot by `Orchestrator::evaluate`
+#[derive(Debug, Clone, Serialize)]
+pub struct Evaluation {
+    pub file: PathBuf,
+    /// Pairs the model found; empty when it answered NONE
+    pub items: Vec<QaItem>,
+    /// Why the file could not be analyzed (unusable image or failed LLM call)
+    pub error: Option<String>,
+}
+
+/// A single page mutation planned by render_answers
+#[derive(Debug, Clone)]
+enum RenderStep {
+    /// Erase the handwritten question on the current page
+    EraseQuestion(BoundingBox),
+    /// Draw the reference symbol where the question was
+    DrawSymbol { symbol: String, x: i32, y: i32 },
+    /// Draw an arrow from the symbol toward the outlined content
+    DrawArrow { from: (i32, i32), to: (i32, i32) },
+    /// Move to the answer page: the next page if it is one, otherwise a new page
+    OpenAnswerPage,
+    /// Type one formatted Q&A entry on the answer page
+    TypeAnswer(String),
+    /// Navigate back to the page being read (unless --post-answer-action says otherwise)
+    ReturnToPage,
+}
+
+impl RenderStep {
+    /// Short description for progress and failure reports
+    fn describe(&self) -> String {
+        match self {
+            RenderStep::EraseQuestion(b) => {
+                format!(
+                    "erase question at ({}, {}) {}x{}",
+                    b.x, b.y, b.width, b.height
+                )
+            }
+            RenderStep::DrawSymbol { symbol, x, y } => {
+                format!("draw symbol {} at ({}, {})", symbol, x, y)
+            }
+            RenderStep::DrawArrow { from, to } => {
+                format!("draw arrow from {:?} to {:?}", from, to)
+            }
+            RenderStep::OpenAnswerPage => "open answer page".to_string(),
+            RenderStep::TypeAnswer(entry) => format!("type answer ({} chars)", entry.len()),
+            RenderStep::ReturnToPage => "return to original page".to_string(),
+        }
+    }
 }
 
 /// High-level orchestrator for the complete workflow
@@ -18,6 +178,69 @@ pub struct Orchestrator {
     workflow: Workflow,
     llm: OpenAI,
     symbol_pool: SymbolPool,
+    /// Keep a separate symbol sequence for each document
+    per_document_symbols: bool,
+    /// Document id to use instead of detecting the open document
+    document_id: Option<String>,
+    instruction: Option<String>,
+    answer_prefix: String,
+    answer_suffix: String,
+    answer_format: AnswerFormat,
+    cite: bool,
+    answer_page_context: bool,
+    non_destructive: bool,
+    /// Labels and separator the prompt asks for and the parser reads
+    response_format: ResponseFormat,
+    /// Skip questions whose reference symbol is already on the reading page
+    skip_answered: bool,
+    /// Type the answer onto the answer page while it is being generated
+    stream_to_page: bool,
+    /// Draw an arrow from each placed symbol to its outline
+    draw_arrow: bool,
+    default_action: CornerAction,
+    corner_actions: Vec<(TriggerCorner, CornerAction)>,
+    /// Model used for actions without an override (the engine's model at construction)
+    default_model: String,
+    /// Per-action model overrides, e.g. a cheaper model for definitions
+    action_models: Vec<(CornerAction, String)>,
+    last_action: Option<CornerAction>,
+    on_ambiguous: OnAmbiguous,
+    post_answer_action: PostAnswerAction,
+    /// Always answer on a new page at the end, without probing the next page
+    always_new_page: bool,
+    /// Notebook every answer goes to, by its library name (--answer-document)
+    answer_document: Option<String>,
+    allow_clear: bool,
+    /// Answers shorter than this (in characters, trimmed) are not rendered
+    min_answer_chars: usize,
+    /// Answers are cut to this many characters before typing (0 = no limit)
+    max_answer_chars: usize,
+    /// Briefly show a note on screen when every answer was too short
+    show_skipped: bool,
+    /// Font for rendered SVG text (None = embedded font)
+    font: Option<PathBuf>,
+    /// Reads each answer aloud after it is rendered
+    speaker: Option<Speaker>,
+    /// Show answers on a throwaway page for this long, then discard it (--scratch)
+    scratch: Option<Duration>,
+    /// Receives every analysis result (e.g. a companion app on a laptop)
+    webhook: Option<Webhook>,
+    /// Set by a first clear-answers tap; the next trigger must be another one to clear
+    clear_armed: bool,
+    /// Pages moved forward to reach the answer page in the current render
+    pages_forward: usize,
+    /// The reading page as it looked when leaving it, to confirm the way back
+    reading_page: Option<Vec<u8>>,
+    /// The reading document's name while the answer document is open
+    reading_document: Option<String>,
+    /// Whether the current render went to a new page at the end of the document
+    created_page: bool,
+    consecutive_failures: u32,
+    max_consecutive_failures: u32,
+    failure_backoff: Duration,
+    exit_on_repeated_failure: bool,
+    /// Exits the process when an iteration stops making progress
+    watchdog: Option<Watchdog>,
 }
 
 impl Orchestrator {
@@ -26,57 +249,369 @@ impl Orchestrator {
         // Load previous state (if any)
         let _ = symbol_pool.load();
 
+        Self::with_symbol_pool(workflow, llm, symbol_pool)
+    }
+
+    /// Create an orchestrator with a caller-provided symbol pool
+    /// The pool is used as-is; no state is loaded from disk
+    pub fn with_symbol_pool(workflow: Workflow, llm: OpenAI, symbol_pool: SymbolPool) -> Self {
+        let default_model = llm.model().to_string();
         Self {
             workflow,
             llm,
             symbol_pool,
+            per_document_symbols: false,
+            document_id: None,
+            instruction: None,
+            answer_prefix: String::new(),
+            answer_suffix: String::new(),
+            answer_format: AnswerFormat::Qa,
+            cite: false,
+            answer_page_context: false,
+            non_destructive: false,
+            response_format: ResponseFormat::default(),
+            skip_answered: false,
+            stream_to_page: false,
+            draw_arrow: false,
+            default_action: CornerAction::Answer,
+            corner_actions: Vec::new(),
+            default_model,
+            action_models: Vec::new(),
+            last_action: None,
+            on_ambiguous: OnAmbiguous::Create,
+            post_answer_action: PostAnswerAction::Return,
+            always_new_page: false,
+            answer_document: None,
+            allow_clear: false,
+            min_answer_chars: 1,
+            max_answer_chars: 1500,
+            show_skipped: false,
+            font: None,
+            speaker: None,
+            scratch: None,
+            webhook: None,
+            clear_armed: false,
+            pages_forward: 0,
+            reading_page: None,
+            reading_document: None,
+            created_page: false,
+            consecutive_failures: 0,
+            max_consecutive_failures: 5,
+            failure_backoff: Duration::from_secs(300),
+            exit_on_repeated_failure: false,
+            watchdog: None,
+        }
+    }
+
+    /// Number answers per document instead of with one global sequence
+    ///
+    /// The document is `document_id` if given, else the one most recently opened in
+    /// xochitl. When it can't be identified the global sequence is used.
+    pub fn with_per_document_symbols(mut self, enabled: bool, document_id: Option<String>) -> Self {
+        self.per_document_symbols = enabled;
+        self.document_id = document_id;
+        self
+    }
+
+    /// Append a custom instruction/persona to every prompt
+    pub fn with_instruction(mut self, instruction: Option<String>) -> Self {
+        self.instruction = instruction.filter(|i| !i.trim().is_empty());
+        self
+    }
+
+    /// Wrap each rendered Q&A entry in marker text (e.g. "[RB]" / "[/RB]")
+    /// so exported notebooks can be post-processed; only characters the virtual keyboard can type survive
+    pub fn with_answer_markers(mut self, prefix: String, suffix: String) -> Self {
+        self.answer_prefix = prefix;
+        self.answer_suffix = suffix;
+        self
+    }
+
+    /// Layout of each entry on the answer page
+    pub fn with_answer_format(mut self, answer_format: AnswerFormat) -> Self {
+        self.answer_format = answer_format;
+        self
+    }
+
+    /// Ask the model to quote the outlined text its answer is based on
+    pub fn with_citations(mut self, cite: bool) -> Self {
+        self.cite = cite;
+        self
+    }
+
+    /// Show the model the existing answer page so it can stay consistent and skip repeats
+    pub fn with_answer_page_context(mut self, answer_page_context: bool) -> Self {
+        self.answer_page_context = answer_page_context;
+        self
+    }
+
+    /// Ask for and parse responses with these labels and separator
+    pub fn with_response_format(mut self, response_format: ResponseFormat) -> Self {
+        self.response_format = response_format;
+        self
+    }
+
+    /// Leave the reading page alone: no question erasure and no symbol beside it
+    /// The answer page entry still carries the symbol
+    pub fn with_non_destructive(mut self, non_destructive: bool) -> Self {
+        self.non_destructive = non_destructive;
+        self
+    }
+
+    /// Don't answer a question again when its reference symbol is already drawn
+    /// where this question's symbol would go (e.g. after an accidental re-trigger)
+    pub fn with_skip_answered(mut self, skip_answered: bool) -> Self {
+        self.skip_answered = skip_answered;
+        self
+    }
+
+    /// Type answers onto the answer page as the model streams them, instead of
+    /// after the whole response (ignored for scratch answers)
+    pub fn with_stream_to_page(mut self, stream_to_page: bool) -> Self {
+        self.stream_to_page = stream_to_page;
+        self
+    }
+
+    /// Point an arrow from each reference symbol to the outlined content
+    pub fn with_draw_arrow(mut self, draw_arrow: bool) -> Self {
+        self.draw_arrow = draw_arrow;
+        self
+    }
+
+    /// Allow the clear-answers action to wipe the answer page
+    pub fn with_allow_clear(mut self, allow_clear: bool) -> Self {
+        self.allow_clear = allow_clear;
+        self
+    }
+
+    /// Skip answers shorter than `min_chars` so weak responses don't create answer pages
+    /// `show_skipped` flashes a short note when nothing was rendered because of this
+    pub fn with_min_answer_length(mut self, min_chars: usize, show_skipped: bool) -> Self {
+        self.min_answer_chars = min_chars;
+        self.show_skipped = show_skipped;
+        self
+    }
+
+    /// Cut answers longer than `max_chars` characters (0 = no limit) so a runaway
+    /// response can't keep the keyboard busy for minutes or overflow the page
+    pub fn with_max_answer_length(mut self, max_chars: usize) -> Self {
+        self.max_answer_chars = max_chars;
+        self
+    }
+
+    /// Use this font file for rendered SVG text instead of the embedded one
+    pub fn with_font(mut self, font: Option<PathBuf>) -> Self {
+        self.font = font;
+        self
+    }
+
+    /// Speak answers aloud through this text-to-speech engine
+    pub fn with_speaker(mut self, speaker: Option<Speaker>) -> Self {
+        self.speaker = speaker;
+        self
+    }
+
+    /// Answer on a throwaway page shown for `duration`, leaving the reading page untouched
+    /// The page is deleted if the workflow allows page deletion, otherwise wiped and left blank
+    pub fn with_scratch(mut self, duration: Option<Duration>) -> Self {
+        self.scratch = duration;
+        self
+    }
+
+    /// POST every analysis result to this webhook
+    pub fn with_webhook(mut self, webhook: Option<Webhook>) -> Self {
+        self.webhook = webhook;
+        self
+    }
+
+    /// Action for the trigger corner when no corner bindings are configured
+    pub fn with_default_action(mut self, action: CornerAction) -> Self {
+        self.default_action = action;
+        self
+    }
+
+    /// Use a different model for some actions; the rest use the engine's model
+    pub fn with_action_models(mut self, action_models: Vec<(CornerAction, String)>) -> Self {
+        self.action_models = action_models;
+        self
+    }
+
+    /// Bind trigger corners to actions; taps on unbound corners are ignored
+    /// With no bindings, every trigger runs the default action
+    pub fn with_corner_actions(
+        mut self,
+        corner_actions: Vec<(TriggerCorner, CornerAction)>,
+    ) -> Self {
+        self.corner_actions = corner_actions;
+        self
+    }
+
+    /// What to do when the next page might not be an answer page
+    pub fn with_on_ambiguous(mut self, on_ambiguous: OnAmbiguous) -> Self {
+        self.on_ambiguous = on_ambiguous;
+        self
+    }
+
+    /// Where to leave the user after writing answers (default: back on the reading page)
+    pub fn with_post_answer_action(mut self, action: PostAnswerAction) -> Self {
+        self.post_answer_action = action;
+        self
+    }
+
+    /// Skip the answer page check and always write answers on a new page at the end
+    /// The next page is never visited, so on_ambiguous and the answer page context
+    /// are unused
+    pub fn with_always_new_answer_page(mut self, always_new_page: bool) -> Self {
+        self.always_new_page = always_new_page;
+        self
+    }
+
+    /// Write every answer in this notebook (its name in the xochitl library) and
+    /// switch back to the reading document afterwards; answer page checks and the
+    /// answer page context don't apply there
+    pub fn with_answer_document(mut self, name: Option<String>) -> Self {
+        self.answer_document = name;
+        self
+    }
+
+    /// Configure the session-wide failure budget for run_loop
+    /// After `max_failures` consecutive failed iterations (0 = unlimited), the loop either
+    /// sleeps for `backoff` between attempts or exits if `exit_on_failure` is set
+    pub fn with_failure_budget(
+        mut self,
+        max_failures: u32,
+        backoff: Duration,
+        exit_on_failure: bool,
+    ) -> Self {
+        self.max_consecutive_failures = max_failures;
+        self.failure_backoff = backoff;
+        self.exit_on_repeated_failure = exit_on_failure;
+        self
+    }
+
+    /// Exit the process when an iteration makes no progress for `timeout` (None = never)
+    pub fn with_watchdog(mut self, timeout: Option<Duration>) -> Self {
+        self.watchdog = timeout.map(Watchdog::start);
+        self
+    }
+
+    /// Report progress to the watchdog, arming it for the rest of the iteration
+    fn pet_watchdog(&self) {
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.pet();
+        }
+    }
+
+    /// Stop the watchdog while nothing is expected to happen (trigger wait, backoff)
+    fn idle_watchdog(&self) {
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.idle();
         }
     }
 
     /// Run one complete iteration of the reader buddy workflow
-    /// NOTE: v0.1 processes ONE outline-question pair per trigger
-    pub fn run_iteration(&mut self) -> Result<()> {
+    ///
+    /// Failures before anything is rendered are returned as errors; a failed render
+    /// has already been reported on the device and comes back as `IterationOutcome::Error`.
+    pub fn run_iteration(&mut self) -> Result<IterationOutcome> {
         info!("=== Starting Reader Buddy Iteration ===");
 
-        // Step 1: Wait for trigger
-        self.workflow.wait_for_trigger()?;
+        // Step 1: Wait for trigger and work out what the tapped corner should do
+        self.idle_watchdog();
+        let corner = self.workflow.wait_for_trigger()?;
+        self.pet_watchdog();
+        let Some(action) = self.resolve_action(corner) else {
+            self.clear_armed = false;
+            return Ok(IterationOutcome::Ignored);
+        };
+        if action == CornerAction::ClearAnswers {
+            return self.handle_clear_request();
+        }
+        self.clear_armed = false;
         self.workflow.show_progress("Processing...")?;
 
         // Step 2: Capture screenshot
-        let screenshot_base64 = self.workflow.capture_screenshot()?;
+        let screenshot_uri = self.workflow.capture_screenshot_for_llm()?;
+        // Keep the page for webhook crops; capturing the answer page replaces it
+        let page_png = self
+            .webhook
+            .as_ref()
+            .filter(|webhook| webhook.includes_images())
+            .map(|_| self.workflow.last_screenshot().to_vec());
+        let answer_page_uri = if self.answer_page_context
+            && !self.always_new_page
+            && self.answer_document.is_none()
+        {
+            self.capture_answer_page()?
+        } else {
+            None
+        };
+        self.pet_watchdog();
         self.workflow.show_progress("Analyzing...")?;
 
+        if self.stream_to_page && self.scratch.is_none() {
+            return self.stream_answer(screenshot_uri, answer_page_uri, action, page_png);
+        }
+
         // Step 3: Single LLM call does everything:
         // - Detect outlined region
         // - Extract question text
         // - Generate answer
-        let result = self.analyze_and_answer_single_call(&screenshot_base64)?;
+        let result =
+            self.analyze_and_answer_single_call(screenshot_uri, answer_page_uri, action)?;
+
+        let Some(result) = result else {
+            info!("No outlined regions or questions detected");
+            self.workflow.clear_progress()?;
+            self.workflow.render_text("No outlined content found. Please draw an outline around content and write a question nearby.")?;
+            return Ok(IterationOutcome::NoContentFound);
+        };
+
+        for item in &result.items {
+            info!(
+                "Got Q&A - Question: {} | Answer: {}",
+                item.question, item.answer
+            );
+        }
+        debug!("Analysis metadata: {:?}", result.metadata);
+        if let Some(webhook) = &self.webhook {
+            if let Err(e) = webhook.post(&result, page_png.as_deref()) {
+                warn!("Could not send result to webhook: {}", e);
+            }
+        }
+        self.workflow.show_progress("Rendering...")?;
 
-        match result {
-            None => {
-                info!("No outlined regions or questions detected");
+        self.created_page = false;
+        let rendered = if self.scratch.is_some() {
+            self.render_scratch(&result.items)
+        } else {
+            self.render_answers(&result.items)
+        };
+        let outcome = match rendered {
+            Ok(answers) => IterationOutcome::Answered {
+                answers,
+                created_page: self.created_page,
+            },
+            Err(e) => {
+                error!("Error rendering answer: {}", e);
                 self.workflow.clear_progress()?;
-                self.workflow.render_text("No outlined content found. Please draw an outline around content and write a question nearby.")?;
-                return Ok(());
+                self.workflow.show_error(&e.to_string())?;
+                IterationOutcome::Error(e.to_string())
             }
-            Some(result) => {
-                info!(
-                    "Got Q&A - Question: {} | Answer: {}",
-                    result.question, result.answer
-                );
-                self.workflow.show_progress("Rendering...")?;
+        };
 
-                if let Err(e) = self.render_answer(&result) {
-                    error!("Error rendering answer: {}", e);
-                    self.workflow.clear_progress()?;
-                    self.workflow.render_text(&format!("Error: {}", e))?;
+        // Speech is a bonus on top of the page; a failing engine shouldn't stop the loop
+        if let Some(speaker) = &self.speaker {
+            for item in &result.items {
+                if let Err(e) = speaker.speak(&item.answer) {
+                    warn!("Could not speak answer: {}", e);
                 }
             }
         }
 
         self.workflow.clear_progress()?;
         info!("=== Iteration Complete ===");
-        Ok(())
+        Ok(outcome)
     }
 
     /// Single LLM call that does everything:
@@ -85,192 +620,1893 @@ impl Orchestrator {
     /// 3. Generates answer
     /// 4. Provides bounding boxes
     ///
-    /// Returns None if no outline/question found, or Some((question, answer, question_box, outline_box))
+    /// Returns None if no outline/question found, or the Q&A items with request metadata
     fn analyze_and_answer_single_call(
         &mut self,
-        screenshot_base64: &str,
+        screenshot_uri: String,
+        answer_page_uri: Option<String>,
+        action: CornerAction,
     ) -> Result<Option<AnalysisResult>> {
-        info!("Sending single LLM call for analysis + answer");
-
-        self.llm.clear_content();
-        self.llm.add_text_content(
-            "Look at this reMarkable tablet screenshot (768x1024 pixels). The user is reading and has:\n\
-             1. Drawn an outline (circle, rectangle, or any closed shape) around some content\n\
-             2. Written a handwritten question nearby about that content\n\n\
-             Your task:\n\
-             1. Identify what content has been outlined\n\
-             2. Read the handwritten question text\n\
-             3. Provide a clear, helpful answer based on the outlined content\n\
-             4. Provide approximate bounding boxes for the outline and question regions\n\n\
-             Respond EXACTLY in this format:\n\
-             QUESTION: [the extracted question text]\n\
-             QUESTION_BOX: x,y,width,height (approximate pixels where the question text is)\n\
-             OUTLINE_BOX: x,y,width,height (approximate pixels of the outline shape)\n\
-             ---\n\
-             ANSWER: [your answer]\n\n\
-             If you cannot find a clear outline or question, respond with just:\n\
-             NONE\n\n\
-             Note: Process only ONE outline-question pair (the most prominent one if multiple exist). \
-             Keep the answer concise and focused. Boxes are in pixels with origin (0,0) at top-left."
-        );
-        self.llm.add_image_content(screenshot_base64);
+        self.prepare_analysis(screenshot_uri, answer_page_uri, action);
 
         let response = self.llm.execute()?;
+        self.pet_watchdog();
         info!("LLM Response: {}", response);
 
-        // Parse the response
-        if response.trim().to_uppercase().starts_with("NONE") {
+        let items = self.parse_pairs(&response, action);
+        if items.is_empty() {
             return Ok(None);
         }
-
-        // Parse the structured response
-        let parts: Vec<&str> = response.split("---").collect();
-        if parts.len() < 2 {
-            // Fallback: treat whole response as answer
-            return Ok(Some(AnalysisResult {
-                question: "What does this mean?".to_string(),
-                answer: response,
-                question_box: None,
-                _outline_box: None,
-            }));
+        if items.len() > 1 {
+            info!("Response answers {} outline-question pairs", items.len());
         }
 
-        let header = parts[0];
-        let answer_text = parts[1]
-            .trim()
-            .strip_prefix("ANSWER:")
-            .unwrap_or(parts[1])
-            .trim();
-
-        // Extract question text
-        let question_text = Self::extract_field(header, "QUESTION:");
-
-        // Extract bounding boxes
-        let question_box = Self::parse_bounding_box(&Self::extract_field(header, "QUESTION_BOX:"));
-        let outline_box = Self::parse_bounding_box(&Self::extract_field(header, "OUTLINE_BOX:"));
-
-        debug!("Parsed - Question: {}", question_text);
-        debug!("Question box: {:?}", question_box);
-        debug!("Outline box: {:?}", outline_box);
-
         Ok(Some(AnalysisResult {
-            question: question_text,
-            answer: answer_text.to_string(),
-            question_box,
-            _outline_box: outline_box,
+            items,
+            metadata: AnalysisMetadata::now(
+                self.llm.model(),
+                self.llm.last_usage(),
+                self.llm.last_finish_reason().map(str::to_string),
+            ),
         }))
     }
 
-    /// Extract a field value from the response
-    fn extract_field(text: &str, field_name: &str) -> String {
-        for line in text.lines() {
-            if let Some(value) = line.strip_prefix(field_name) {
-                return value.trim().to_string();
-            }
+    /// Pick the action's model and load the prompt and images into the LLM request
+    fn prepare_analysis(
+        &mut self,
+        screenshot_uri: String,
+        answer_page_uri: Option<String>,
+        action: CornerAction,
+    ) {
+        let model = self
+            .action_models
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map_or(self.default_model.as_str(), |(_, model)| model.as_str());
+        self.llm.set_model(model);
+        info!(
+            "Sending single LLM call for analysis + answer ({:?}, model {})",
+            action,
+            self.llm.model()
+        );
+
+        let mut prompt = self.build_prompt(action);
+        if answer_page_uri.is_some() {
+            prompt.push_str("\n\n");
+            prompt.push_str(ANSWER_PAGE_CONTEXT_PROMPT);
+        }
+        self.llm.clear_content();
+        self.llm.add_text_content(&prompt);
+        self.llm.add_image_url(screenshot_uri);
+        if let Some(answer_page_uri) = answer_page_uri {
+            self.llm.add_image_url(answer_page_uri);
         }
-        "".to_string()
     }
 
-    /// Parse bounding box from "x,y,width,height" format
-    fn parse_bounding_box(text: &str) -> Option<BoundingBox> {
-        let parts: Vec<&str> = text.split(',').collect();
-        if parts.len() == 4 {
-            if let (Ok(x), Ok(y), Ok(w), Ok(h)) = (
-                parts[0].trim().parse::<i32>(),
-                parts[1].trim().parse::<i32>(),
-                parts[2].trim().parse::<i32>(),
-                parts[3].trim().parse::<i32>(),
-            ) {
-                return Some(BoundingBox {
-                    x,
-                    y,
-                    width: w,
-                    height: h,
-                });
-            }
+    /// parse_response, plus the fixed question of describe requests
+    /// Every outline-question pair in a response (see split_pairs)
+    fn parse_pairs(&self, response: &str, action: CornerAction) -> Vec<QaItem> {
+        Self::split_pairs(response, &self.response_format)
+            .into_iter()
+            .filter_map(|pair| self.parse_action_response(pair, action))
+            .collect()
+    }
+
+    fn parse_action_response(&self, response: &str, action: CornerAction) -> Option<QaItem> {
+        let mut item = Self::parse_response(response, &self.response_format)?;
+        if action == CornerAction::Describe {
+            // Nothing was asked in writing, so there is nothing to erase
+            item.question = DESCRIBE_QUESTION.to_string();
+            item.question_box = None;
         }
-        None
+        Some(item)
     }
 
-    /// Render the answer on a new page with proper cleanup
-    fn render_answer(&mut self, result: &AnalysisResult) -> Result<()> {
-        info!("Rendering Q&A on new page");
+    /// Stream the answer onto the answer page while the model is still writing it
+    ///
+    /// The answer page is only opened once there is something to write: a header
+    /// (question and boxes) followed by at least min_answer_chars of answer. The
+    /// header is checked against --skip-answered when it arrives. The reading page
+    /// is marked (question erased, symbol drawn) from the header's boxes just before
+    /// leaving it, then the answer is typed chunk by chunk. If the stream breaks
+    /// off, what arrived stays on the page, marked incomplete.
+    fn stream_answer(
+        &mut self,
+        screenshot_uri: String,
+        answer_page_uri: Option<String>,
+        action: CornerAction,
+        page_png: Option<Vec<u8>>,
+    ) -> Result<IterationOutcome> {
+        self.prepare_analysis(screenshot_uri, answer_page_uri, action);
+        if self.per_document_symbols {
+            self.select_symbol_document()?;
+        }
+        let symbol = self.symbol_pool.current_symbol();
+        self.created_page = false;
 
-        // Get the next symbol from the pool
-        let symbol = self.symbol_pool.next_symbol()?;
-        info!("Using reference symbol: {}", symbol);
+        let mut stream = AnswerStream::new(&self.response_format);
+        // Whether the answer page is open and the entry begun
+        let mut opened = false;
+        // Why the answer is not written, once known
+        let mut skipped: Option<&str> = None;
+        let mut head = String::new();
+        // Question and boxes from the header, to mark the reading page with
+        let mut header_item: Option<QaItem> = None;
+        // Answer text held back until it reaches min_answer_chars
+        let mut held = String::new();
+        let mut answer_chars = 0;
 
-        // Step 1: Erase question text if we have its location
-        // IMPORTANT: Only erase question, preserve outline
-        if let Some(question_box) = &result.question_box {
-            info!(
-                "Erasing question at ({}, {}) size {}x{}",
-                question_box.x, question_box.y, question_box.width, question_box.height
-            );
-            self.workflow.show_progress("Erasing question...")?;
-            self.workflow.erase_region(question_box)?;
-        } else {
-            debug!("No question bounding box provided, skipping erasure");
-        }
+        // Chunks are pulled from the stream rather than handed to a callback, so
+        // opening the answer page can use the whole orchestrator meanwhile
+        let streamed = self.llm.start_streaming().and_then(|mut response| {
+            let mut read = || -> Result<()> {
+                while let Some(chunk) = response.next_chunk()? {
+                    self.pet_watchdog();
+                    for part in stream.push(&chunk) {
+                        if skipped.is_some() {
+                            break;
+                        }
+                        match part {
+                            StreamPart::Header(header) => {
+                                let question = match action {
+                                    CornerAction::Describe => DESCRIBE_QUESTION.to_string(),
+                                    _ => {
+                                        Self::extract_field(&header, &self.response_format.question)
+                                    }
+                                };
+                                let reply = format!("{}{}", header, self.response_format.separator);
+                                header_item = self.parse_action_response(&reply, action);
+                                if self.already_answered(header_item.as_ref())? {
+                                    skipped = Some("Already answered");
+                                    break;
+                                }
+                                head = Self::entry_head(
+                                    self.answer_format,
+                                    &self.answer_prefix,
+                                    &symbol,
+                                    &question,
+                                );
+                            }
+                            StreamPart::Answer(text) => {
+                                let text = Self::cap_streamed(
+                                    &text,
+                                    &mut answer_chars,
+                                    self.max_answer_chars,
+                                );
+                                held.push_str(&text);
+                                if !opened {
+                                    if held.trim().chars().count() < self.min_answer_chars {
+                                        continue;
+                                    }
+                                    if let Some(item) = &header_item {
+                                        self.execute_render(&self.plan_marks(&symbol, item))?;
+                                    }
+                                    self.open_answer_page()?;
+                                    self.workflow.begin_answer_entry()?;
+                                    self.workflow.type_answer_part(&head)?;
+                                    opened = true;
+                                }
+                                if !held.is_empty() {
+                                    self.workflow.type_answer_part(&std::mem::take(&mut held))?;
+                                }
+                            }
+                        }
+                    }
+                }
+                Ok(())
+            };
+            let result = read();
+            self.llm.finish_streaming(response);
+            result
+        });
+        info!("LLM Response: {}", stream.received());
+        let item = self.parse_action_response(stream.received(), action);
 
-        // Step 2: Draw symbol on current page (where question was)
-        self.workflow.show_progress("Marking original...")?;
-        let symbol_x = if let Some(qbox) = &result.question_box {
-            qbox.x + qbox.width / 2
+        // Finish the entry: the end of the streamed one, or all of it if none was typed
+        let typed = if opened {
+            let mut tail = String::new();
+            if streamed.is_err() {
+                tail.push_str(" [incomplete]");
+            }
+            if let Some(quote) = item.as_ref().and_then(|item| item.quote.as_ref()) {
+                tail.push_str(&Self::quote_block(quote));
+            }
+            tail.push_str(&self.answer_suffix);
+            tail.push_str(Self::entry_separator(self.answer_format));
+            self.workflow.type_answer_part(&tail)?;
+            true
         } else {
-            50 // Default location if no box
+            match &item {
+                Some(item) if skipped.is_none() && streamed.is_ok() => {
+                    if !self.long_enough(item) {
+                        skipped = Some("Answer too short, skipped");
+                        false
+                    // The header of an unstructured reply was not checked while streaming
+                    } else if !stream.answer_started()
+                        && self.skip_answered
+                        && !self.non_destructive
+                        && self.drop_answered(vec![item.clone()])?.is_empty()
+                    {
+                        skipped = Some("Already answered");
+                        false
+                    } else {
+                        self.execute_render(&self.plan_marks(&symbol, item))?;
+                        self.open_answer_page()?;
+                        self.workflow.begin_answer_entry()?;
+                        opened = true;
+                        let entry = self.format_entry(&symbol, &self.capped(item));
+                        self.workflow.type_answer_part(&entry)?;
+                        true
+                    }
+                }
+                _ => false,
+            }
         };
-        let symbol_y = if let Some(qbox) = &result.question_box {
-            qbox.y + qbox.height / 2
-        } else {
-            950 // Default location if no box
+        if typed {
+            self.symbol_pool.next_symbol()?;
+        }
+        if opened {
+            self.leave_answer_page()?;
+        }
+
+        if let Some(note) = skipped {
+            info!("{}; leaving the notebook untouched", note);
+            self.workflow.clear_progress()?;
+            self.note_skipped(note)?;
+            info!("=== Iteration Complete ===");
+            return Ok(IterationOutcome::Answered {
+                answers: Vec::new(),
+                created_page: false,
+            });
+        }
+        let Some(item) = item.filter(|_| typed) else {
+            streamed?;
+            info!("No outlined regions or questions detected");
+            self.workflow.clear_progress()?;
+            self.workflow.render_text("No outlined content found. Please draw an outline around content and write a question nearby.")?;
+            return Ok(IterationOutcome::NoContentFound);
         };
-        self.draw_symbol_on_page(&symbol, symbol_x, symbol_y)?;
 
-        // Step 3: Create new page to the right
-        self.workflow.show_progress("Creating page...")?;
-        self.workflow.create_new_page_right()?;
+        let outcome = match streamed {
+            Ok(_) => {
+                let result = AnalysisResult {
+                    items: vec![item.clone()],
+                    metadata: AnalysisMetadata::now(
+                        self.llm.model(),
+                        self.llm.last_usage(),
+                        self.llm.last_finish_reason().map(str::to_string),
+                    ),
+                };
+                if let Some(webhook) = &self.webhook {
+                    if let Err(e) = webhook.post(&result, page_png.as_deref()) {
+                        warn!("Could not send result to webhook: {}", e);
+                    }
+                }
+                if let Some(speaker) = &self.speaker {
+                    if let Err(e) = speaker.speak(&item.answer) {
+                        warn!("Could not speak answer: {}", e);
+                    }
+                }
+                IterationOutcome::Answered {
+                    answers: vec![AnsweredItem {
+                        question: item.question,
+                        answer: item.answer,
+                        symbol: Some(symbol),
+                    }],
+                    created_page: self.created_page,
+                }
+            }
+            Err(e) => {
+                let message = format!("Answer incomplete: {}", e);
+                error!("{}", message);
+                self.workflow.clear_progress()?;
+                self.workflow.show_error(&message)?;
+                IterationOutcome::Error(message)
+            }
+        };
 
-        // Step 4: Render Q&A on new page with matching symbol
         self.workflow.clear_progress()?;
+        info!("=== Iteration Complete ===");
+        Ok(outcome)
+    }
 
-        let formatted_output = format!(
-            "{} Q: {}\n\nA: {}\n\n---\n\n",
-            symbol, result.question, result.answer
+    /// The part of a streamed answer chunk that fits in max_answer_chars (0 = no limit)
+    /// `typed` counts the answer characters seen so far; "..." marks the cut
+    fn cap_streamed(text: &str, typed: &mut usize, max_chars: usize) -> String {
+        let length = text.chars().count();
+        let room = max_chars.saturating_sub(*typed);
+        let already_cut = max_chars > 0 && *typed >= max_chars;
+        *typed += length;
+        if max_chars == 0 || length <= room {
+            return text.to_string();
+        }
+        if already_cut {
+            return String::new();
+        }
+        warn!(
+            "Streamed answer is over {} characters; truncating",
+            max_chars
         );
+        let cut: String = text.chars().take(room).collect();
+        format!("{}...", cut.trim_end())
+    }
 
-        self.workflow.render_text(&formatted_output)?;
+    /// Capture the answer page after the reading page, if there is one, and come back
+    /// Sent inline: publishing it would replace the reading page on the image server
+    fn capture_answer_page(&mut self) -> Result<Option<String>> {
+        // Progress text is typed on the current page; clear it before leaving
+        self.workflow.clear_progress()?;
 
-        // Step 5: Navigate back to original page to preserve reading context
+        let check = self.workflow.check_if_next_page_is_answer_page()?;
+        let uri = if check == AnswerPageCheck::IsAnswerPage {
+            info!("Including the answer page as context");
+            Some(self.workflow.capture_screenshot_data_uri()?)
+        } else {
+            debug!("Next page is not an answer page; no answer page context");
+            None
+        };
         self.workflow.navigate_to_previous_page()?;
+        Ok(uri)
+    }
 
-        info!("Q&A rendered successfully with symbol {}", symbol);
-        Ok(())
+    /// Map a tapped corner to the action to run
+    /// Returns None for unbound corners and for actions that have nothing to do
+    fn resolve_action(&mut self, corner: TriggerCorner) -> Option<CornerAction> {
+        let action = if self.corner_actions.is_empty() {
+            self.default_action
+        } else {
+            match self.corner_actions.iter().find(|(c, _)| *c == corner) {
+                Some(&(_, action)) => action,
+                None => {
+                    debug!(
+                        "Corner {} is not bound to an action; ignoring",
+                        corner.name()
+                    );
+                    return None;
+                }
+            }
+        };
+        info!("Corner {} triggered {:?}", corner.name(), action);
+
+        let action = match action {
+            CornerAction::Redo => match self.last_action {
+                Some(previous) => previous,
+                None => {
+                    info!("Nothing to redo yet");
+                    return None;
+                }
+            },
+            CornerAction::Undo => {
+                warn!("Undo is not supported yet; ignoring");
+                return None;
+            }
+            // Not remembered for redo; a wipe should always be asked for explicitly
+            CornerAction::ClearAnswers => return Some(action),
+            action => action,
+        };
+        self.last_action = Some(action);
+        Some(action)
     }
 
-    /// Draw a symbol on the current page
-    fn draw_symbol_on_page(&mut self, symbol: &str, x: i32, y: i32) -> Result<()> {
-        info!("Drawing symbol {} at ({}, {})", symbol, x, y);
+    /// Clear the answer page, but only on the second consecutive clear-answers tap
+    fn handle_clear_request(&mut self) -> Result<IterationOutcome> {
+        if !self.allow_clear {
+            warn!("Clearing the answer page is disabled; start with --allow-clear to enable it");
+            return Ok(IterationOutcome::Ignored);
+        }
 
-        // Use the workflow's draw_symbol method which converts to bitmap and draws
-        self.workflow.draw_symbol(x, y, symbol)?;
+        if !self.clear_armed {
+            info!("Clear requested; tap the same corner again to confirm");
+            self.clear_armed = true;
+            self.workflow.show_progress("Tap again to clear answers")?;
+            sleep(Duration::from_secs(2));
+            self.workflow.clear_progress()?;
+            return Ok(IterationOutcome::Ignored);
+        }
 
-        Ok(())
+        self.clear_armed = false;
+        self.workflow.clear_answer_page()?;
+        Ok(IterationOutcome::Cleared)
     }
 
-    /// Run the main loop
-    pub fn run_loop(&mut self) -> Result<()> {
-        info!("Starting Reader Buddy main loop");
+    /// What the model should produce as the ANSWER for actions other than a plain answer
+    fn action_task(action: CornerAction) -> Option<&'static str> {
+        match action {
+            CornerAction::Define => {
+                Some("give a concise definition of the outlined word or phrase")
+            }
+            CornerAction::Summarize => Some("summarize the outlined passage in a few sentences"),
+            CornerAction::Translate => Some("translate the outlined passage into English"),
+            _ => None,
+        }
+    }
 
-        loop {
-            match self.run_iteration() {
-                Ok(_) => info!("Iteration completed successfully"),
+    /// Full analysis prompt, including any custom instruction
+    fn build_prompt(&self, action: CornerAction) -> String {
+        let mut prompt = match action {
+            CornerAction::Describe => DESCRIBE_PROMPT.to_string(),
+            _ => ANALYSIS_PROMPT.to_string(),
+        };
+        if action == CornerAction::Answer {
+            prompt.push_str("\n\n");
+            // A streamed response is typed as one entry, so it can only hold one pair
+            prompt.push_str(if self.stream_to_page {
+                SINGLE_PAIR_PROMPT
+            } else {
+                MULTI_PAIR_PROMPT
+            });
+        }
+        if let Some(task) = Self::action_task(action) {
+            prompt.push_str(&format!(
+                "\n\nThe user asked you to {}; that is the ANSWER. A handwritten question \
+                 is optional for this request; if there is none, set QUESTION to a short \
+                 description of the request and leave QUESTION_BOX empty.",
+                task
+            ));
+        }
+        if self.cite {
+            prompt.push_str("\n\n");
+            prompt.push_str(CITE_PROMPT);
+        }
+        if let Some(instruction) = &self.instruction {
+            // Appended after the format instructions so it shapes the answer, not the format
+            prompt.push_str(&format!(
+                "\n\nAdditional instructions for the ANSWER (keep the response format above): {}",
+                instruction
+            ));
+        }
+        self.response_format.apply_to_prompt(&prompt)
+    }
+
+    /// Run the analysis `runs` times on `png` and time each stage; nothing is drawn
+    ///
+    /// Uses the default action and model, the same image path as a real capture
+    /// (quality check, preprocessing, image server) and the same prompt. Failed runs
+    /// are logged and counted but don't stop the benchmark.
+    pub fn benchmark(&mut self, png: &[u8], runs: usize) -> Result<BenchmarkReport> {
+        let mut report = BenchmarkReport::default();
+        let action = self.default_action;
+        let model = self.default_model.clone();
+        self.llm.set_model(&model);
+
+        for run in 1..=runs {
+            info!("Benchmark run {}/{}", run, runs);
+
+            let start = Instant::now();
+            self.workflow.set_replay(Some(png.to_vec()));
+            let screenshot_uri = match self.workflow.capture_screenshot_for_llm() {
+                Ok(uri) => uri,
                 Err(e) => {
-                    error!("Error in iteration: {}", e);
-                    // Try to show error to user
-                    let _ = self.workflow.render_text(&format!("Error: {}", e));
+                    warn!("Benchmark run {} failed to load the image: {}", run, e);
+                    report.failures += 1;
+                    continue;
+                }
+            };
+            let capture = start.elapsed();
+
+            let start = Instant::now();
+            self.llm.clear_content();
+            self.llm.add_text_content(&self.build_prompt(action));
+            self.llm.add_image_url(screenshot_uri);
+            let response = match self.llm.execute() {
+                Ok(response) => response,
+                Err(e) => {
+                    warn!("Benchmark run {} LLM call failed: {}", run, e);
+                    report.failures += 1;
+                    continue;
+                }
+            };
+            let llm = start.elapsed();
+
+            let start = Instant::now();
+            let parsed = Self::parse_response(&response, &self.response_format);
+            let parse = start.elapsed();
+            if parsed.is_none() {
+                warn!("Benchmark run {}: model found no outline/question", run);
+            }
+
+            info!(
+                "Benchmark run {}: capture {}ms, LLM {}ms, parse {}us",
+                run,
+                capture.as_millis(),
+                llm.as_millis(),
+                parse.as_micros()
+            );
+            report.capture.push(capture);
+            report.llm.push(llm);
+            report.parse.push(parse);
+        }
+        Ok(report)
+    }
+
+    /// Run the analysis on each PNG in `files`, up to `concurrency` LLM calls at a
+    /// time; nothing is drawn. Results come back in the order of `files`
+    ///
+    /// The images take the same path as a real capture (quality check, preprocessing)
+    /// one after another; only the LLM calls run in parallel, each worker with its
+    /// own OpenAI client since execute mutates the request content. Rate limits are
+    /// left to the client's retries with backoff (--api-retries).
+    pub fn evaluate(&mut self, files: &[PathBuf], concurrency: usize) -> Result<Vec<Evaluation>> {
+        let action = self.default_action;
+        let model = self.default_model.clone();
+        self.llm.set_model(&model);
+        let prompt = self.build_prompt(action);
+
+        let mut responses: Vec<Option<Result<String, String>>> = vec![None; files.len()];
+        let mut jobs = Vec::new();
+        for (index, file) in files.iter().enumerate() {
+            let image = std::fs::read(file)
+                .map_err(anyhow::Error::from)
+                .and_then(|png| {
+                    self.workflow.set_replay(Some(png));
+                    self.workflow.capture_screenshot_for_llm()
+                });
+            match image {
+                Ok(uri) => jobs.push((index, uri)),
+                Err(e) => {
+                    warn!("Skipping {}: {}", file.display(), e);
+                    responses[index] = Some(Err(e.to_string()));
+                }
+            }
+        }
+
+        let workers = concurrency.clamp(1, jobs.len().max(1));
+        let total = jobs.len();
+        info!("Analyzing {} image(s) with {} worker(s)", total, workers);
+        let queue = Mutex::new(jobs.into_iter());
+        let (sender, receiver) = mpsc::channel();
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                let mut llm = self.llm.worker();
+                let (queue, sender, prompt) = (&queue, sender.clone(), &prompt);
+                scope.spawn(move || loop {
+                    let Some((index, uri)) = queue.lock().expect("queue lock").next() else {
+                        break;
+                    };
+                    llm.clear_content();
+                    llm.add_text_content(prompt);
+                    llm.add_image_url(uri);
+                    let response = llm.execute().map_err(|e| e.to_string());
+                    if sender.send((index, response)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(sender);
+            for (done, (index, response)) in receiver.iter().enumerate() {
+                info!(
+                    "Analyzed {} ({}/{})",
+                    files[index].display(),
+                    done + 1,
+                    total
+                );
+                responses[index] = Some(response);
+            }
+        });
+
+        Ok(files
+            .iter()
+            .zip(responses)
+            .map(|(file, response)| {
+                let (items, error) = match response {
+                    Some(Ok(response)) => (self.parse_pairs(&response, action), None),
+                    Some(Err(e)) => (Vec::new(), Some(e)),
+                    None => (Vec::new(), Some("Not analyzed".to_string())),
+                };
+                Evaluation {
+                    file: file.clone(),
+                    items,
+                    error,
                 }
+            })
+            .collect())
+    }
+
+    /// Check that the current prompt and model produce parseable output
+    ///
+    /// Sends a small synthetic page (circled phrase plus a written question) through
+    /// the normal LLM path and strictly checks the response format. Nothing is drawn.
+    pub fn validate_prompt(&mut self) -> Result<()> {
+        info!("Validating prompt against model {}", self.llm.model());
+
+        let prompt = self.build_prompt(CornerAction::Answer);
+        self.llm.clear_content();
+        self.llm.add_text_content(&prompt);
+        self.llm
+            .add_image_url(validation::validation_image_data_uri(self.font.as_deref())?);
+
+        let response = self.llm.execute()?;
+        debug!("Validation response: {}", response);
+
+        match Self::check_response_format(&response, &self.response_format) {
+            Ok(item) => {
+                info!("Prompt validation passed");
+                info!("  Question: {}", item.question);
+                info!("  Question box: {:?}", item.question_box);
+                info!("  Outline box: {:?}", item.outline_box);
+                info!("  Answer: {}", item.answer);
+                Ok(())
             }
+            Err(e) => Err(anyhow::anyhow!(
+                "Prompt validation failed: {}\n--- raw response ---\n{}",
+                e,
+                response
+            )),
+        }
+    }
+
+    /// Strictly check a response against the expected format
+    /// Unlike parse_response, every field must be present and well-formed
+    fn check_response_format(response: &str, format: &ResponseFormat) -> Result<QaItem> {
+        if format.is_none(response) {
+            anyhow::bail!(
+                "model answered {} (no outline/question found in the test image)",
+                format.none
+            );
+        }
+
+        let Some((header, body)) = response.split_once(format.separator.as_str()) else {
+            anyhow::bail!(
+                "missing '{}' separator between the header and {}",
+                format.separator,
+                format.answer
+            );
+        };
+
+        let question = Self::extract_field(header, &format.question);
+        if question.is_empty() {
+            anyhow::bail!("missing or empty {} line", format.question);
+        }
+
+        let question_box_text = Self::extract_field(header, &format.question_box);
+        let question_box = Self::parse_bounding_box(&question_box_text).ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} is missing or not x,y,width,height: '{}'",
+                format.question_box,
+                question_box_text
+            )
+        })?;
+
+        let outline_box_text = Self::extract_field(header, &format.outline_box);
+        let outline_box = Self::parse_bounding_box(&outline_box_text).ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} is missing or not x,y,width,height: '{}'",
+                format.outline_box,
+                outline_box_text
+            )
+        })?;
+
+        let Some(answer) = body.trim().strip_prefix(format.answer.as_str()) else {
+            anyhow::bail!("answer section does not start with {}", format.answer);
+        };
+        if answer.trim().is_empty() {
+            anyhow::bail!("{} is empty", format.answer);
+        }
+
+        Ok(QaItem {
+            question,
+            answer: answer.trim().to_string(),
+            question_box: Some(question_box),
+            outline_box: Some(outline_box),
+            quote: Self::extract_quote(header, format),
+        })
+    }
+
+    /// Parse the structured LLM response into a Q&A item
+    /// Returns None if the model reported no outline/question
+    fn parse_response(response: &str, format: &ResponseFormat) -> Option<QaItem> {
+        if format.is_none(response) {
+            return None;
+        }
+
+        // Parse the structured response; without the separator, split at the ANSWER: line
+        let parts: Vec<&str> = response.splitn(2, format.separator.as_str()).collect();
+        let (header, answer_text) = match parts.as_slice() {
+            [header, answer] => (
+                *header,
+                answer
+                    .trim()
+                    .strip_prefix(format.answer.as_str())
+                    .unwrap_or(answer)
+                    .trim()
+                    .to_string(),
+            ),
+            _ => Self::split_unseparated(response, format),
+        };
+
+        // Extract question text
+        let question_text = Self::extract_field(header, &format.question);
+
+        // Extract bounding boxes
+        let question_box =
+            Self::parse_bounding_box(&Self::extract_field(header, &format.question_box));
+        let outline_box =
+            Self::parse_bounding_box(&Self::extract_field(header, &format.outline_box));
+
+        // Unstructured replies may not name the question at all
+        let question_text = if question_text.is_empty() && parts.len() < 2 {
+            "What does this mean?".to_string()
+        } else {
+            question_text
+        };
+
+        debug!("Parsed - Question: {}", question_text);
+        debug!("Question box: {:?}", question_box);
+        debug!("Outline box: {:?}", outline_box);
+
+        Some(QaItem {
+            question: question_text,
+            answer: answer_text,
+            question_box,
+            outline_box,
+            quote: Self::extract_quote(header, format),
+        })
+    }
+
+    /// Split a response answering several outline-question pairs into one part per pair
+    ///
+    /// A new pair starts at a QUESTION: line after the previous pair's separator, if it
+    /// has a separator of its own; otherwise the line is taken as part of the answer.
+    fn split_pairs<'a>(response: &'a str, format: &ResponseFormat) -> Vec<&'a str> {
+        // (start offset, whether the part has a separator line)
+        let mut parts = vec![(0, false)];
+        let mut offset = 0;
+        for line in response.split_inclusive('\n') {
+            let trimmed = line.trim_start();
+            let separated = parts.last().is_some_and(|&(_, separated)| separated);
+            if separated && trimmed.starts_with(format.question.as_str()) {
+                parts.push((offset, false));
+            }
+            if trimmed.starts_with(format.separator.as_str()) {
+                if let Some(part) = parts.last_mut() {
+                    part.1 = true;
+                }
+            }
+            offset += line.len();
+        }
+        // A trailing part without a separator belongs to the answer before it
+        if parts.len() > 1 && parts.last().is_some_and(|&(_, separated)| !separated) {
+            parts.pop();
+        }
+
+        parts
+            .iter()
+            .enumerate()
+            .map(|(n, &(start, _))| {
+                &response[start..parts.get(n + 1).map_or(response.len(), |&(next, _)| next)]
+            })
+            .collect()
+    }
+
+    /// Split a response that lacks the "---" separator into header and answer
+    /// Everything from an ANSWER: line on is the answer; without one, the answer is
+    /// every line that isn't a recognised header field
+    fn split_unseparated<'a>(response: &'a str, format: &ResponseFormat) -> (&'a str, String) {
+        let header_fields = format.header_fields();
+
+        debug!(
+            "Response has no '{}' separator, parsing fields line by line",
+            format.separator
+        );
+        let mut offset = 0;
+        for line in response.split_inclusive('\n') {
+            if let Some(answer) = line.trim_start().strip_prefix(format.answer.as_str()) {
+                let rest = &response[offset + line.len()..];
+                let answer = format!("{}{}", answer, rest);
+                return (&response[..offset], answer.trim().to_string());
+            }
+            offset += line.len();
+        }
+
+        let answer: Vec<&str> = response
+            .lines()
+            .filter(|line| {
+                let line = line.trim_start();
+                !header_fields.iter().any(|field| line.starts_with(field))
+            })
+            .collect();
+        (response, answer.join("\n").trim().to_string())
+    }
+
+    /// QUOTE: field with surrounding quotation marks removed (None if absent)
+    fn extract_quote(header: &str, format: &ResponseFormat) -> Option<String> {
+        let quote = Self::extract_field(header, &format.quote);
+        let quote = quote.trim_matches(|c| matches!(c, '"' | '\u{201C}' | '\u{201D}'));
+        (!quote.is_empty()).then(|| quote.trim().to_string())
+    }
+
+    /// Extract a field value from the response
+    fn extract_field(text: &str, field_name: &str) -> String {
+        for line in text.lines() {
+            if let Some(value) = line.trim_start().strip_prefix(field_name) {
+                return value.trim().to_string();
+            }
+        }
+        "".to_string()
+    }
+
+    /// Parse bounding box from "x,y,width,height" format
+    ///
+    /// Tolerates the variations models actually produce: labels ("x=10, w=30",
+    /// "width: 30"), parentheses/brackets, units ("10px"), floats (rounded) and
+    /// extra whitespace. Labelled values may come in any order; unlabelled ones
+    /// are taken as the first four numbers in x, y, width, height order.
+    fn parse_bounding_box(text: &str) -> Option<BoundingBox> {
+        let numbers = Self::labelled_numbers(text);
+        if numbers.len() < 4 {
+            return None;
+        }
+
+        let find = |names: &[&str]| {
+            numbers
+                .iter()
+                .find(|(label, _)| names.contains(&label.as_str()))
+                .map(|&(_, value)| value)
+        };
+        let labelled = (
+            find(&["x", "left"]),
+            find(&["y", "top"]),
+            find(&["w", "width"]),
+            find(&["h", "height"]),
+        );
+
+        let (x, y, width, height) = match labelled {
+            (Some(x), Some(y), Some(w), Some(h)) => (x, y, w, h),
+            _ => (numbers[0].1, numbers[1].1, numbers[2].1, numbers[3].1),
+        };
+
+        Some(BoundingBox {
+            x: x.round() as i32,
+            y: y.round() as i32,
+            width: width.round() as i32,
+            height: height.round() as i32,
+        })
+    }
+
+    /// Pull every number out of `text`, each paired with the lowercase word
+    /// directly before it (empty if the number has no label)
+    fn labelled_numbers(text: &str) -> Vec<(String, f32)> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut numbers = Vec::new();
+        let mut label = String::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            let starts_number = c.is_ascii_digit()
+                || (c == '-' && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit()));
+
+            if starts_number {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let literal: String = chars[start..i].iter().collect();
+                if let Ok(value) = literal.trim_end_matches('.').parse::<f32>() {
+                    numbers.push((std::mem::take(&mut label), value));
+                }
+                // Skip a unit suffix such as "px" so it isn't read as the next label
+                while i < chars.len() && chars[i].is_alphabetic() {
+                    i += 1;
+                }
+                continue;
+            }
+
+            if c.is_alphabetic() {
+                // A new word replaces any earlier label
+                if i == 0 || !chars[i - 1].is_alphabetic() {
+                    label.clear();
+                }
+                label.extend(c.to_lowercase());
+            }
+            i += 1;
+        }
+
+        numbers
+    }
+
+    /// Split text written as a numbered list ("1. ... 2. ...") into its items
+    ///
+    /// Items may span lines or be run together on one line. Only the top-level
+    /// sequence counts: markers must follow 1, 2, 3..., and markers indented at
+    /// the start of a line (or like "1.1") are kept as part of the current item.
+    /// Returns None unless the text starts with "1." and has at least two items.
+    fn parse_numbered_list(text: &str) -> Option<Vec<String>> {
+        let text = text.trim();
+        let bytes = text.as_bytes();
+
+        // (marker start, content start) for each top-level item
+        let mut markers = Vec::new();
+        let mut expected = 1;
+        let mut i = 0;
+        while i < bytes.len() {
+            let at_word_start = i == 0 || bytes[i - 1].is_ascii_whitespace();
+            if !at_word_start || !bytes[i].is_ascii_digit() {
+                i += 1;
+                continue;
+            }
+
+            let digits_end = i + bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+            let is_marker = matches!(bytes.get(digits_end), Some(b'.' | b')'))
+                && matches!(
+                    bytes.get(digits_end + 1),
+                    None | Some(b' ' | b'\t' | b'\n' | b'\r')
+                );
+
+            // An indented marker at the start of a line is a nested list
+            let line_prefix = &text[text[..i].rfind('\n').map_or(0, |n| n + 1)..i];
+            let nested = !line_prefix.is_empty() && line_prefix.trim().is_empty();
+
+            if is_marker && !nested && text[i..digits_end].parse::<usize>() == Ok(expected) {
+                markers.push((i, digits_end + 1));
+                expected += 1;
+                i = digits_end + 1;
+            } else {
+                i = digits_end;
+            }
+        }
+
+        if markers.len() < 2 || markers[0].0 != 0 {
+            return None;
+        }
+
+        let items = markers
+            .iter()
+            .enumerate()
+            .map(|(n, &(_, content_start))| {
+                let end = markers.get(n + 1).map_or(text.len(), |&(next, _)| next);
+                text[content_start..end]
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        Some(items)
+    }
+
+    /// `item` with its answer cut to max_answer_chars, ending in "..." when cut
+    fn capped(&self, item: &QaItem) -> QaItem {
+        let mut item = item.clone();
+        let length = item.answer.chars().count();
+        if self.max_answer_chars > 0 && length > self.max_answer_chars {
+            warn!(
+                "Answer to '{}' is {} characters; truncating to {}",
+                item.question, length, self.max_answer_chars
+            );
+            let cut: String = item.answer.chars().take(self.max_answer_chars).collect();
+            item.answer = format!("{}...", cut.trim_end());
+        }
+        item
+    }
+
+    /// Format one Q&A entry for the answer page, wrapped in the configured markers
+    fn format_entry(&self, symbol: &str, item: &QaItem) -> String {
+        format!(
+            "{}{}{}{}",
+            self.answer_prefix,
+            Self::format_qa(self.answer_format, symbol, item).trim_end(),
+            self.answer_suffix,
+            Self::entry_separator(self.answer_format)
+        )
+    }
+
+    /// Typed after each entry to set it apart from the next one
+    fn entry_separator(format: AnswerFormat) -> &'static str {
+        match format {
+            AnswerFormat::Qa => "\n\n---\n\n",
+            AnswerFormat::AnswerOnly | AnswerFormat::Compact => "\n\n",
+        }
+    }
+
+    /// Start of an entry, up to where the answer text goes (see format_qa)
+    /// Numbered-list questions are kept on one line, since the answer isn't known yet
+    fn entry_head(format: AnswerFormat, prefix: &str, symbol: &str, question: &str) -> String {
+        let head = match format {
+            AnswerFormat::Qa => format!("{} Q: {}\n\nA: ", symbol, question),
+            AnswerFormat::AnswerOnly => format!("{} ", symbol),
+            AnswerFormat::Compact => format!("{} {}\n", symbol, question),
+        };
+        format!("{}{}", prefix, head)
+    }
+
+    /// The supporting quote, indented below the answer
+    fn quote_block(quote: &str) -> String {
+        // The model picks the quote; flag it so it gets checked against the page
+        format!(
+            "\n\n    Source: \"{}\"\n    (quote chosen by the model, verify on the page)",
+            quote
+        )
+    }
+
+    /// Format the Q&A text itself, with the supporting quote indented below the answer
+    fn format_qa(format: AnswerFormat, symbol: &str, item: &QaItem) -> String {
+        let mut entry = match format {
+            AnswerFormat::Qa => Self::format_question_answer(symbol, item),
+            AnswerFormat::AnswerOnly => format!("{} {}", symbol, item.answer),
+            AnswerFormat::Compact => format!("{} {}\n{}", symbol, item.question, item.answer),
+        };
+        if let Some(quote) = &item.quote {
+            entry.push_str(&Self::quote_block(quote));
+        }
+        entry
+    }
+
+    /// A numbered-list question is rendered as a numbered list of Q/A pairs under one symbol
+    fn format_question_answer(symbol: &str, item: &QaItem) -> String {
+        let Some(questions) = Self::parse_numbered_list(&item.question) else {
+            return format!("{} Q: {}\n\nA: {}", symbol, item.question, item.answer);
+        };
+
+        let mut entry = format!("{} Q:\n", symbol);
+        match Self::parse_numbered_list(&item.answer) {
+            Some(answers) if answers.len() == questions.len() => {
+                for (n, (question, answer)) in questions.iter().zip(&answers).enumerate() {
+                    entry.push_str(&format!("{}. {}\nA: {}\n\n", n + 1, question, answer));
+                }
+            }
+            _ => {
+                // Answer doesn't line up item-for-item; keep it as one block
+                for (n, question) in questions.iter().enumerate() {
+                    entry.push_str(&format!("{}. {}\n", n + 1, question));
+                }
+                entry.push_str(&format!("\nA: {}", item.answer));
+            }
+        }
+        entry
+    }
+
+    /// Render a batch of answers on a single new page with proper cleanup
+    ///
+    /// The page mutations are planned up front and then executed in order, so a
+    /// failure while planning leaves the notebook untouched and a failure while
+    /// executing reports exactly which steps completed. All marking on the
+    /// original page happens first, then the answer page is visited once for all
+    /// entries.
+    /// Returns the rendered answers with their symbols.
+    fn render_answers(&mut self, results: &[QaItem]) -> Result<Vec<AnsweredItem>> {
+        if results.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Don't create an answer page (or mark the reading page) for empty or weak answers
+        let results: Vec<QaItem> = results
+            .iter()
+            .filter(|item| self.long_enough(item))
+            .cloned()
+            .collect();
+        if results.is_empty() {
+            info!("No answer long enough to render, leaving the notebook untouched");
+            self.note_skipped("Answer too short, skipped")?;
+            return Ok(Vec::new());
+        }
+        let results = if self.skip_answered && !self.non_destructive {
+            self.drop_answered(results)?
+        } else {
+            results
+        };
+        if results.is_empty() {
+            info!("Every question is already answered, leaving the notebook untouched");
+            self.note_skipped("Already answered")?;
+            return Ok(Vec::new());
+        }
+        let results = results.as_slice();
+        info!("Rendering {} Q&A(s) on new page", results.len());
+
+        let (plan, symbols) = self.plan_render(results)?;
+        self.execute_render(&plan)?;
+
+        info!(
+            "Rendered {} Q&A(s) with one answer page visit ({} visit(s) saved over one per answer)",
+            results.len(),
+            results.len() - 1
+        );
+        Ok(results
+            .iter()
+            .zip(symbols)
+            .map(|(item, symbol)| AnsweredItem {
+                question: item.question.clone(),
+                answer: item.answer.clone(),
+                symbol: Some(symbol),
+            })
+            .collect())
+    }
+
+    /// Show answers on a blank page at the end of the document, then discard it
+    /// Nothing is erased or marked on the reading page and no symbols are used
+    fn render_scratch(&mut self, results: &[QaItem]) -> Result<Vec<AnsweredItem>> {
+        let Some(duration) = self.scratch else {
+            return Ok(Vec::new());
+        };
+        info!("Showing {} Q&A(s) on a scratch page", results.len());
+
+        self.workflow.clear_progress()?;
+        let pages = self.workflow.create_page_at_end()?;
+
+        for item in results {
+            let item = self.capped(item);
+            let entry = format!("Q: {}\n\nA: {}", item.question, item.answer);
+            self.workflow.render_text(&entry)?;
+        }
+        sleep(duration);
+
+        let discarded = self.workflow.discard_scratch_page();
+        // Deleting the last page leaves xochitl on the page before it
+        let back = match discarded {
+            Ok(true) => pages.saturating_sub(1),
+            _ => pages,
+        };
+        self.workflow.navigate_back(back)?;
+        discarded?;
+        Ok(results
+            .iter()
+            .map(|item| AnsweredItem {
+                question: item.question.clone(),
+                answer: item.answer.clone(),
+                symbol: None,
+            })
+            .collect())
+    }
+
+    /// Whether the answer has at least min_answer_chars characters
+    fn long_enough(&self, item: &QaItem) -> bool {
+        let length = item.answer.trim().chars().count();
+        if length < self.min_answer_chars {
+            info!(
+                "Skipping answer to '{}': {} characters, minimum is {}",
+                item.question, length, self.min_answer_chars
+            );
+        }
+        length >= self.min_answer_chars
+    }
+
+    /// Flash why nothing was rendered, with --show-skipped
+    fn note_skipped(&mut self, note: &str) -> Result<()> {
+        if self.show_skipped {
+            self.workflow.show_progress(note)?;
+            sleep(Duration::from_secs(2));
+            self.workflow.clear_progress()?;
+        }
+        Ok(())
+    }
+
+    /// Whether the question in a streamed header already has a symbol on the
+    /// reading page (--skip-answered)
+    fn already_answered(&mut self, item: Option<&QaItem>) -> Result<bool> {
+        if !self.skip_answered || self.non_destructive {
+            return Ok(false);
+        }
+        let Some(item) = item else {
+            return Ok(false);
+        };
+        Ok(self.drop_answered(vec![item.clone()])?.is_empty())
+    }
+
+    /// Drop the questions that already have a reference symbol on the reading page
+    ///
+    /// The symbol from an earlier answer sits where the question was, or beside the
+    /// outline when there was no question box. A symbol in the top margin (no boxes
+    /// at all) can't be told apart from other answers', so those are always kept.
+    fn drop_answered(&mut self, results: Vec<QaItem>) -> Result<Vec<QaItem>> {
+        let symbols = self.symbol_pool.symbols().to_vec();
+        let mut remaining = Vec::with_capacity(results.len());
+        for item in results {
+            let positions: Vec<(i32, i32)> = [
+                item.question_box.as_ref().map(Self::box_center),
+                item.outline_box.as_ref().map(Self::beside_outline),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            if positions.is_empty() {
+                remaining.push(item);
+                continue;
+            }
+            match self.workflow.find_symbol_near(&positions, &symbols)? {
+                Some(symbol) => info!(
+                    "'{}' already has symbol {} on the page; not answering it again",
+                    item.question, symbol
+                ),
+                None => remaining.push(item),
+            }
+        }
+        Ok(remaining)
+    }
+
+    /// Point the symbol pool at the current document's sequence
+    fn select_symbol_document(&mut self) -> Result<()> {
+        let document_id = match &self.document_id {
+            Some(id) => Some(id.clone()),
+            None => match current_document() {
+                Ok(Some(document)) => {
+                    debug!(
+                        "Current document: {} ({})",
+                        document.visible_name, document.id
+                    );
+                    Some(document.id)
+                }
+                Ok(None) => {
+                    warn!("No open document found; using the global symbol sequence");
+                    None
+                }
+                Err(e) => {
+                    warn!(
+                        "Could not identify the open document, using the global symbol sequence: {}",
+                        e
+                    );
+                    None
+                }
+            },
+        };
+        self.symbol_pool.set_document(document_id)
+    }
+
+    /// Build the ordered list of page mutations for a batch of answers, and the
+    /// symbol taken for each answer
+    /// Nothing on the device is changed here; only symbols are taken from the pool
+    fn plan_render(&mut self, results: &[QaItem]) -> Result<(Vec<RenderStep>, Vec<String>)> {
+        if self.per_document_symbols {
+            self.select_symbol_document()?;
+        }
+        let mut marks = Vec::new();
+        let mut entries = Vec::with_capacity(results.len());
+        let mut symbols = Vec::with_capacity(results.len());
+
+        for result in results {
+            let symbol = self.symbol_pool.next_symbol()?;
+            info!("Using reference symbol: {}", symbol);
+            entries.push(RenderStep::TypeAnswer(
+                self.format_entry(&symbol, &self.capped(result)),
+            ));
+            marks.extend(self.plan_marks(&symbol, result));
+            symbols.push(symbol);
+        }
+
+        let mut plan = marks;
+        plan.push(RenderStep::OpenAnswerPage);
+        plan.extend(entries);
+        // Navigate back to original page to preserve reading context
+        plan.push(RenderStep::ReturnToPage);
+
+        debug!("Render plan: {:?}", plan);
+        Ok((plan, symbols))
+    }
+
+    /// Steps that mark the reading page for one answer: erase the question, draw
+    /// the symbol and optionally an arrow (none with --non-destructive)
+    fn plan_marks(&self, symbol: &str, result: &QaItem) -> Vec<RenderStep> {
+        let mut marks = Vec::new();
+        if self.non_destructive {
+            return marks;
+        }
+
+        // IMPORTANT: Only erase question, preserve outline
+        match &result.question_box {
+            Some(question_box) => marks.push(RenderStep::EraseQuestion(question_box.clone())),
+            None => debug!("No question bounding box provided, skipping erasure"),
+        }
+
+        let (x, y) = Self::symbol_position(result);
+        marks.push(RenderStep::DrawSymbol {
+            symbol: symbol.to_string(),
+            x,
+            y,
+        });
+
+        if self.draw_arrow {
+            match result.outline_box.as_ref() {
+                Some(outline) => match Self::arrow_to_outline((x, y), outline) {
+                    Some((from, to)) => marks.push(RenderStep::DrawArrow { from, to }),
+                    None => debug!("Symbol is on or next to the outline, skipping arrow"),
+                },
+                None => debug!("No outline bounding box provided, skipping arrow"),
+            }
+        }
+        marks
+    }
+
+    /// Execute a render plan step by step
+    /// On failure the error lists the steps that had already been applied
+    fn execute_render(&mut self, plan: &[RenderStep]) -> Result<()> {
+        for (index, step) in plan.iter().enumerate() {
+            self.pet_watchdog();
+            if let Err(e) = self.execute_step(step) {
+                let completed: Vec<String> = plan[..index].iter().map(|s| s.describe()).collect();
+                error!(
+                    "Render step {}/{} ({}) failed; completed: [{}]",
+                    index + 1,
+                    plan.len(),
+                    step.describe(),
+                    completed.join(", ")
+                );
+                // Don't leave the user on the answer page (or further) after a failure
+                let pages = std::mem::take(&mut self.pages_forward);
+                if pages > 0 || self.reading_document.is_some() {
+                    warn!(
+                        "Returning {} page(s) to the reading page after the failure",
+                        pages
+                    );
+                    if let Err(back) = self.return_to_reading_page(pages) {
+                        warn!("Could not return to the reading page: {}", back);
+                    }
+                }
+                return Err(anyhow::anyhow!(
+                    "Render failed at step {}/{} ({}) after {} completed step(s): {}",
+                    index + 1,
+                    plan.len(),
+                    step.describe(),
+                    index,
+                    e
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn execute_step(&mut self, step: &RenderStep) -> Result<()> {
+        match step {
+            RenderStep::EraseQuestion(question_box) => {
+                info!(
+                    "Erasing question at ({}, {}) size {}x{}",
+                    question_box.x, question_box.y, question_box.width, question_box.height
+                );
+                self.workflow.show_progress("Erasing question...")?;
+                self.workflow.erase_region(question_box)
+            }
+            RenderStep::DrawSymbol { symbol, x, y } => {
+                self.workflow.show_progress("Marking original...")?;
+                self.draw_symbol_on_page(symbol, *x, *y)
+            }
+            RenderStep::DrawArrow { from, to } => self.workflow.draw_arrow(*from, *to),
+            RenderStep::OpenAnswerPage => self.open_answer_page(),
+            RenderStep::TypeAnswer(entry) => self.workflow.render_answer_text(entry),
+            RenderStep::ReturnToPage => self.leave_answer_page(),
+        }
+    }
+
+    /// Final navigation once the answers are written, per --post-answer-action
+    fn leave_answer_page(&mut self) -> Result<()> {
+        let pages = std::mem::take(&mut self.pages_forward);
+        match self.post_answer_action {
+            PostAnswerAction::Return => {
+                info!("Returning {} page(s) to the reading page", pages);
+                self.return_to_reading_page(pages)
+            }
+            PostAnswerAction::AnswerPage => {
+                info!(
+                    "Staying on the answer page, {} page(s) past the reading page",
+                    pages
+                );
+                self.reading_page = None;
+                self.reading_document = None;
+                Ok(())
+            }
+        }
+    }
+
+    /// Navigate back `pages` pages and check that the reading page is showing
+    ///
+    /// There is no page index to read, so the screen is compared with the capture
+    /// taken when leaving. On a mismatch one more page back is tried (a turn that
+    /// went uncounted); if that isn't it either, the extra turn is undone and an
+    /// error is returned so the user knows to check where they are. From the
+    /// answer document the reading document is reopened instead, which xochitl
+    /// shows on the page it was left on.
+    fn return_to_reading_page(&mut self, pages: usize) -> Result<()> {
+        if let Some(document) = self.reading_document.take() {
+            info!("Switching back to the reading document '{}'", document);
+            self.workflow.open_document(&document)?;
+            let Some(reading_page) = self.reading_page.take() else {
+                return Ok(());
+            };
+            if !self.workflow.shows_page(&reading_page)? {
+                anyhow::bail!(
+                    "Reopened '{}', but it doesn't show the page being read",
+                    document
+                );
+            }
+            return Ok(());
+        }
+
+        self.workflow.navigate_back(pages)?;
+        let Some(reading_page) = self.reading_page.take() else {
+            return Ok(());
+        };
+        if self.workflow.shows_page(&reading_page)? {
+            return Ok(());
+        }
+
+        warn!(
+            "Not back on the reading page after {} page(s); trying one more",
+            pages
+        );
+        self.workflow.navigate_to_previous_page()?;
+        if self.workflow.shows_page(&reading_page)? {
+            info!("Found the reading page one page further back");
+            return Ok(());
+        }
+        self.workflow.navigate_to_next_page()?;
+        anyhow::bail!(
+            "Could not confirm the return to the reading page after {} page(s)",
+            pages
+        )
+    }
+
+    /// Move forward to a page the answers can be written on
+    /// Uses the next page if it is an answer page (or empty); otherwise, or when
+    /// unsure and --on-ambiguous says so, a new page at the end of the document.
+    /// With --answer-document the answers go to that notebook instead.
+    fn open_answer_page(&mut self) -> Result<()> {
+        // Progress text is typed on the current page; clear it before leaving
+        self.workflow.clear_progress()?;
+        self.reading_page = Some(self.workflow.snapshot_page()?);
+
+        if let Some(name) = self.answer_document.clone() {
+            let reading = current_document()?.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Could not tell which document is open; not switching to '{}'",
+                    name
+                )
+            })?;
+            if !reading.visible_name.eq_ignore_ascii_case(&name) {
+                return self.open_answer_document(&name, reading.visible_name);
+            }
+            debug!("Already reading the answer document; answering in place");
+        }
+
+        if self.always_new_page {
+            info!("Using a new page at the end (--always-new-answer-page)");
+            self.pages_forward = self.workflow.create_page_at_end()?;
+            self.created_page = true;
+            return self.prepare_answer_page();
+        }
+
+        let check = self.workflow.check_if_next_page_is_answer_page()?;
+        self.pages_forward = 1;
+
+        let use_new_page = match check {
+            AnswerPageCheck::IsAnswerPage => false,
+            AnswerPageCheck::NotAnswerPage => true,
+            AnswerPageCheck::Uncertain => match self.on_ambiguous {
+                OnAmbiguous::Create => true,
+                OnAmbiguous::Reuse => false,
+                OnAmbiguous::Abort => {
+                    let pages = std::mem::take(&mut self.pages_forward);
+                    self.workflow.navigate_back(pages)?;
+                    anyhow::bail!(
+                        "Could not tell whether the next page is an answer page; \
+                         nothing written (--on-ambiguous abort)"
+                    );
+                }
+            },
+        };
+
+        if use_new_page {
+            info!("Next page is not an answer page; using a new page at the end");
+            self.pages_forward += self.workflow.create_page_at_end()?;
+            self.created_page = true;
+        }
+        self.prepare_answer_page()
+    }
+
+    /// Switch to the answer document and a page there the answers can go on
+    ///
+    /// xochitl reopens the document on the page last shown, normally the page the
+    /// previous answers were written on; it is reused while it carries the answer
+    /// page marker, otherwise a new page at the end is used. On failure the
+    /// reading document is reopened.
+    fn open_answer_document(&mut self, name: &str, reading_document: String) -> Result<()> {
+        info!("Answering in '{}' (--answer-document)", name);
+        self.workflow.open_document(name)?;
+        self.reading_document = Some(reading_document);
+        self.pages_forward = 0;
+
+        let opened = match self.workflow.detect_answer_page_marker() {
+            Ok(true) => {
+                info!("Reusing the answer page '{}' opened on", name);
+                self.prepare_answer_page()
+            }
+            Ok(false) => self.workflow.create_page_at_end().and_then(|_| {
+                self.created_page = true;
+                self.prepare_answer_page()
+            }),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = opened {
+            if let Err(back) = self.return_to_reading_page(0) {
+                warn!("Could not switch back to the reading document: {}", back);
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Apply the answer template and mark the current page as an answer page
+    fn prepare_answer_page(&mut self) -> Result<()> {
+        self.workflow.apply_answer_template()?;
+        if !self.workflow.detect_answer_page_marker()? {
+            self.workflow.draw_answer_page_marker()?;
+        }
+        Ok(())
+    }
+
+    /// Where the reference symbol goes: where the question was, else in the margin
+    /// left of the outline, else centered in the top margin
+    fn symbol_position(item: &QaItem) -> (i32, i32) {
+        // Top margin, clear of the corner trigger areas and the answer page marker
+        const TOP_MARGIN_POSITION: (i32, i32) = (384, 30);
+
+        if let Some(qbox) = &item.question_box {
+            return Self::box_center(qbox);
+        }
+        if let Some(outline) = &item.outline_box {
+            debug!("No question box, placing the symbol beside the outline");
+            return Self::beside_outline(outline);
+        }
+        debug!("No boxes, placing the symbol in the top margin");
+        TOP_MARGIN_POSITION
+    }
+
+    fn box_center(bbox: &BoundingBox) -> (i32, i32) {
+        (bbox.x + bbox.width / 2, bbox.y + bbox.height / 2)
+    }
+
+    /// Symbol position in the margin left of the outline's top edge
+    fn beside_outline(outline: &BoundingBox) -> (i32, i32) {
+        let half = SYMBOL_SIZE as i32 / 2;
+        let x = (outline.x - half - 4).max(half);
+        let y = (outline.y + half).clamp(half, 1024 - half);
+        (x, y)
+    }
+
+    /// Arrow from a symbol centered at `symbol` toward the center of `outline`
+    /// It starts just outside the symbol and stops at the outline box edge so it
+    /// doesn't draw over the circled text. None if there is no room for one.
+    fn arrow_to_outline(
+        symbol: (i32, i32),
+        outline: &BoundingBox,
+    ) -> Option<((i32, i32), (i32, i32))> {
+        // Gap left between the arrow and both the symbol and the outline
+        const GAP: f32 = 4.0;
+        const MIN_LENGTH: f32 = 12.0;
+
+        let center = (
+            outline.x as f32 + outline.width as f32 / 2.0,
+            outline.y as f32 + outline.height as f32 / 2.0,
+        );
+        let (dx, dy) = (center.0 - symbol.0 as f32, center.1 - symbol.1 as f32);
+        let distance = dx.hypot(dy);
+        if distance < 1.0 {
+            return None;
+        }
+
+        // Fraction of the way from the center back to the symbol where the box edge is
+        let half = (outline.width as f32 / 2.0, outline.height as f32 / 2.0);
+        let edge = (half.0 / dx.abs().max(f32::EPSILON)).min(half.1 / dy.abs().max(f32::EPSILON));
+        if edge >= 1.0 {
+            // The symbol sits inside the outline box
+            return None;
+        }
+
+        let start = SYMBOL_SIZE as f32 / 2.0 + GAP;
+        let end = distance * (1.0 - edge) - GAP;
+        if end - start < MIN_LENGTH {
+            return None;
+        }
+        let point = |along: f32| {
+            (
+                symbol.0 + (dx / distance * along).round() as i32,
+                symbol.1 + (dy / distance * along).round() as i32,
+            )
+        };
+        Some((point(start), point(end)))
+    }
+
+    /// Draw a symbol on the current page
+    fn draw_symbol_on_page(&mut self, symbol: &str, x: i32, y: i32) -> Result<()> {
+        info!("Drawing symbol {} at ({}, {})", symbol, x, y);
+
+        // Use the workflow's draw_symbol method which converts to bitmap and draws
+        self.workflow.draw_symbol(x, y, symbol)?;
+
+        Ok(())
+    }
+
+    /// Run the main loop
+    pub fn run_loop(&mut self) -> Result<()> {
+        info!("Starting Reader Buddy main loop");
+
+        loop {
+            match self.run_iteration() {
+                Ok(IterationOutcome::Error(message)) => {
+                    // Already shown on the device; the iteration itself didn't fail
+                    warn!("Iteration completed with a render error: {}", message);
+                }
+                Ok(outcome) => {
+                    info!("Iteration completed successfully: {:?}", outcome);
+                    self.consecutive_failures = 0;
+                }
+                Err(e) => {
+                    error!("Error in iteration: {}", e);
+                    self.consecutive_failures += 1;
+
+                    let budget_exhausted = self.max_consecutive_failures > 0
+                        && self.consecutive_failures >= self.max_consecutive_failures;
+                    if !budget_exhausted {
+                        // Try to show error to user
+                        let _ = self.workflow.show_error(&e.to_string());
+                        continue;
+                    }
+
+                    if self.exit_on_repeated_failure {
+                        return Err(anyhow::anyhow!(
+                            "Giving up after {} consecutive failed iterations: {}",
+                            self.consecutive_failures,
+                            e
+                        ));
+                    }
+
+                    // Only mark the page once when the budget runs out, not on every retry
+                    if self.consecutive_failures == self.max_consecutive_failures {
+                        let _ = self.workflow.show_error(&e.to_string());
+                    }
+                    warn!(
+                        "{} consecutive failed iterations, backing off for {}s",
+                        self.consecutive_failures,
+                        self.failure_backoff.as_secs()
+                    );
+                    self.idle_watchdog();
+                    sleep(self.failure_backoff);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbered_list_with_dots() {
+        assert_eq!(
+            Orchestrator::parse_numbered_list("1. First question\n2. Second question"),
+            Some(vec![
+                "First question".to_string(),
+                "Second question".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn numbered_list_with_parentheses() {
+        assert_eq!(
+            Orchestrator::parse_numbered_list("1) First 2) Second 3) Third"),
+            Some(vec![
+                "First".to_string(),
+                "Second".to_string(),
+                "Third".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn numbered_list_joins_wrapped_lines() {
+        let text = "1. A question that is\n   wrapped over two lines\n2. Another one\nthat wraps";
+        assert_eq!(
+            Orchestrator::parse_numbered_list(text),
+            Some(vec![
+                "A question that is wrapped over two lines".to_string(),
+                "Another one that wraps".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn numbered_list_needs_to_start_with_the_first_item() {
+        assert_eq!(
+            Orchestrator::parse_numbered_list("Two questions:\n1. First\n2. Second"),
+            None
+        );
+        assert_eq!(Orchestrator::parse_numbered_list("1. Only one"), None);
+    }
+
+    fn bounding_box(text: &str) -> Option<(i32, i32, i32, i32)> {
+        Orchestrator::parse_bounding_box(text).map(|b| (b.x, b.y, b.width, b.height))
+    }
+
+    #[test]
+    fn bounding_box_unlabelled() {
+        assert_eq!(bounding_box("10,20,30,40"), Some((10, 20, 30, 40)));
+        assert_eq!(
+            bounding_box("(10px, 20px, 30.6, 40.2)"),
+            Some((10, 20, 31, 40))
+        );
+    }
+
+    #[test]
+    fn bounding_box_labelled_in_any_order() {
+        assert_eq!(
+            bounding_box("width: 30, height: 40, x=10, y=20"),
+            Some((10, 20, 30, 40))
+        );
+        assert_eq!(
+            bounding_box("[left 10, top 20, w 30, h 40]"),
+            Some((10, 20, 30, 40))
+        );
+        assert_eq!(
+            Orchestrator::labelled_numbers("x=10, y: -5, 7px w3"),
+            vec![
+                ("x".to_string(), 10.0),
+                ("y".to_string(), -5.0),
+                (String::new(), 7.0),
+                ("w".to_string(), 3.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn bounding_box_out_of_range_values_are_kept() {
+        // Positions off the screen are left for the caller to clamp
+        assert_eq!(bounding_box("-20, 5000, 30, 40"), Some((-20, 5000, 30, 40)));
+        // Numbers too large for the screen type saturate instead of overflowing
+        assert_eq!(
+            bounding_box("1, 2, 99999999999999999999, 4"),
+            Some((1, 2, i32::MAX, 4))
+        );
+    }
+
+    #[test]
+    fn bounding_box_garbage() {
+        assert_eq!(bounding_box(""), None);
+        assert_eq!(bounding_box("none"), None);
+        assert_eq!(bounding_box("somewhere near the top"), None);
+        assert_eq!(bounding_box("10, 20, 30"), None);
+        assert!(Orchestrator::labelled_numbers("x=-, y=., --").is_empty());
+    }
+
+    #[test]
+    fn unseparated_response_is_split_into_fields_and_answer() {
+        let format = ResponseFormat::default();
+        let response = "QUESTION: What is a monad?\nQUESTION_BOX: 10,20,30,40\nANSWER: A monoid\nin the category of endofunctors.";
+        let (header, answer) = Orchestrator::split_unseparated(response, &format);
+        assert_eq!(
+            header,
+            "QUESTION: What is a monad?\nQUESTION_BOX: 10,20,30,40\n"
+        );
+        assert_eq!(answer, "A monoid\nin the category of endofunctors.");
+
+        // Without an ANSWER: line, every line that isn't a header field is the answer
+        let response = "QUESTION: Why?\nOUTLINE_BOX: 1,2,3,4\nBecause it is.";
+        let (header, answer) = Orchestrator::split_unseparated(response, &format);
+        assert_eq!(header, response);
+        assert_eq!(answer, "Because it is.");
+
+        let item = Orchestrator::parse_response(response, &format).unwrap();
+        assert_eq!(item.question, "Why?");
+        assert!(item.outline_box.is_some());
+    }
+
+    #[test]
+    fn plain_or_separated_text_is_left_unchanged() {
+        let format = ResponseFormat::default();
+        let plain = "Just an answer,\nover two lines.";
+        assert_eq!(
+            Orchestrator::split_unseparated(plain, &format),
+            (plain, plain.to_string())
+        );
+
+        let response = "QUESTION: Why?\n---\nANSWER: Because\nit is.";
+        let item = Orchestrator::parse_response(response, &format).unwrap();
+        assert_eq!(item.question, "Why?");
+        assert_eq!(item.answer, "Because\nit is.");
+    }
+
+    fn qa_item(question: &str, answer: &str) -> QaItem {
+        QaItem {
+            question: question.to_string(),
+            answer: answer.to_string(),
+            question_box: None,
+            outline_box: None,
+            quote: None,
+        }
+    }
+
+    #[test]
+    fn format_qa_for_each_answer_format() {
+        let item = qa_item("Why?", "Because.");
+        assert_eq!(
+            Orchestrator::format_qa(AnswerFormat::Qa, "①", &item),
+            "① Q: Why?\n\nA: Because."
+        );
+        assert_eq!(
+            Orchestrator::format_qa(AnswerFormat::AnswerOnly, "①", &item),
+            "① Because."
+        );
+        assert_eq!(
+            Orchestrator::format_qa(AnswerFormat::Compact, "①", &item),
+            "① Why?\nBecause."
+        );
+    }
+
+    #[test]
+    fn format_qa_numbered_questions() {
+        let item = qa_item("1. Who? 2. When?", "1. Ada 2. 1843");
+        assert_eq!(
+            Orchestrator::format_qa(AnswerFormat::Qa, "①", &item),
+            "① Q:\n1. Who?\nA: Ada\n\n2. When?\nA: 1843\n\n"
+        );
+    }
+
+    #[test]
+    fn format_qa_quote_goes_below_the_answer() {
+        let mut item = qa_item("Why?", "Because.");
+        item.quote = Some("it is".to_string());
+        assert_eq!(
+            Orchestrator::format_qa(AnswerFormat::AnswerOnly, "①", &item),
+            "① Because.\n\n    Source: \"it is\"\n    (quote chosen by the model, verify on the page)"
+        );
+    }
+
+    #[test]
+    fn entry_head_for_each_answer_format() {
+        assert_eq!(
+            Orchestrator::entry_head(AnswerFormat::Qa, "", "①", "Why?"),
+            "① Q: Why?\n\nA: "
+        );
+        assert_eq!(
+            Orchestrator::entry_head(AnswerFormat::AnswerOnly, "", "①", "Why?"),
+            "① "
+        );
+        assert_eq!(
+            Orchestrator::entry_head(AnswerFormat::Compact, "> ", "①", "Why?"),
+            "> ① Why?\n"
+        );
+    }
+
+    #[test]
+    fn entry_head_starts_the_formatted_entry() {
+        let item = qa_item("Why?", "Because.");
+        for format in [
+            AnswerFormat::Qa,
+            AnswerFormat::AnswerOnly,
+            AnswerFormat::Compact,
+        ] {
+            let head = Orchestrator::entry_head(format, "", "①", &item.question);
+            let entry = Orchestrator::format_qa(format, "①", &item);
+            assert_eq!(entry, format!("{}{}", head, item.answer), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn several_pairs_are_split_apart() {
+        let format = ResponseFormat::default();
+        let response = "QUESTION: Why?\nOUTLINE_BOX: 1,2,3,4\n---\nANSWER: Because.\n\n\
+                        QUESTION: Who?\nOUTLINE_BOX: 5,6,7,8\n---\nANSWER: Ada.";
+        let pairs = Orchestrator::split_pairs(response, &format);
+        assert_eq!(
+            pairs,
+            [
+                "QUESTION: Why?\nOUTLINE_BOX: 1,2,3,4\n---\nANSWER: Because.\n\n",
+                "QUESTION: Who?\nOUTLINE_BOX: 5,6,7,8\n---\nANSWER: Ada."
+            ]
+        );
+        let answers: Vec<String> = pairs
+            .iter()
+            .filter_map(|pair| Orchestrator::parse_response(pair, &format))
+            .map(|item| item.answer)
+            .collect();
+        assert_eq!(answers, ["Because.", "Ada."]);
+    }
+
+    #[test]
+    fn a_single_pair_is_not_split() {
+        let format = ResponseFormat::default();
+        for response in [
+            "QUESTION: Why?\n---\nANSWER: Because.\nQUESTION: is quoted in the answer",
+            "NONE",
+        ] {
+            assert_eq!(Orchestrator::split_pairs(response, &format), [response]);
         }
     }
 }
@@ -3,98 +3,337 @@ use log::{debug, info};
 use std::thread::sleep;
 use std::time::Duration;
 
-use crate::device::touch::Touch;
+use crate::device::{keyboard::Keyboard, touch::Touch, DeviceModel};
+
+// Page-turn hot zones along the left/right screen edges (virtual coordinates)
+const LEFT_EDGE_X: i32 = 20;
+const RIGHT_EDGE_X: i32 = 748;
+
+/// A sequence of menu taps (virtual coordinates)
+type Taps = &'static [(i32, i32)];
+
+// Page creation path through xochitl's menus (virtual coordinates): the document
+// menu, its page options, "add page after", then a tap on the new page to close
+// the menu. Approximate positions for the 3.x toolbar layout
+const NEW_PAGE_TAPS_3X: Taps = &[(700, 50), (650, 400), (384, 450), (100, 900)];
+
+// Page deletion path through xochitl's page overview (virtual coordinates):
+// overview button, the current page's menu, Delete, then the confirmation button.
+// Approximate positions for the 3.x toolbar layout
+const DELETE_PAGE_TAPS_3X: Taps = &[(40, 115), (384, 430), (384, 560), (470, 600)];
+
+// From an open document to the library search (virtual coordinates): the close
+// button in the toolbar, then the search field at the top of the library.
+// Approximate positions for the 3.x toolbar layout
+const OPEN_SEARCH_TAPS_3X: Taps = &[(40, 45), (384, 45)];
+
+// Opening the first entry of the search results (virtual coordinates).
+// Approximate position for the 3.x library layout
+const SEARCH_RESULT_TAPS_3X: Taps = &[(384, 200)];
+
+/// Built-in UI layouts by name (--ui-layout): new page, delete page, open search
+/// and search result taps. The first is the default
+const UI_LAYOUT_PRESETS: &[(&str, Taps, Taps, Taps, Taps)] = &[(
+    "3.x",
+    NEW_PAGE_TAPS_3X,
+    DELETE_PAGE_TAPS_3X,
+    OPEN_SEARCH_TAPS_3X,
+    SEARCH_RESULT_TAPS_3X,
+)];
+
+/// How page turns are sent to xochitl
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NavMethod {
+    /// Horizontal swipe across the page
+    Swipe,
+    /// Tap the left/right edge navigation zones
+    EdgeTap,
+    /// Left/right arrow keys on the virtual keyboard
+    Keyboard,
+}
+
+impl NavMethod {
+    pub fn from_string(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "swipe" => Ok(NavMethod::Swipe),
+            "edge-tap" => Ok(NavMethod::EdgeTap),
+            "keyboard" => Ok(NavMethod::Keyboard),
+            _ => Err(anyhow::anyhow!(
+                "Invalid navigation method: {}. Use swipe, edge-tap, or keyboard",
+                s
+            )),
+        }
+    }
+}
+
+/// Shape of the page-turn swipe (virtual coordinates)
+///
+/// Some firmware reads a short or fast swipe as a pen stroke instead of a page
+/// turn, so every part of the gesture can be tuned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SwipeConfig {
+    /// x where a next-page swipe starts (previous-page swipes run the other way)
+    pub start_x: i32,
+    /// x where a next-page swipe ends
+    pub end_x: i32,
+    /// Height the swipe runs at
+    pub y: i32,
+    /// Intermediate touch points between start and end
+    pub steps: u32,
+    /// Pause after each intermediate point
+    pub step_delay_ms: u64,
+}
+
+impl SwipeConfig {
+    /// Defaults known to turn pages on each model
+    pub fn for_model(model: DeviceModel) -> Self {
+        let (steps, step_delay_ms) = match model {
+            // Larger, denser touch panel; a quick swipe is often taken as a stroke
+            DeviceModel::RemarkablePaperPro => (20, 15),
+            DeviceModel::Remarkable1 => (15, 10),
+            DeviceModel::Remarkable2 | DeviceModel::Unknown => (10, 10),
+        };
+        Self {
+            start_x: 700,
+            end_x: 100,
+            y: 512,
+            steps,
+            step_delay_ms,
+        }
+    }
+
+    /// Swipe `distance` units, centered horizontally on the screen
+    pub fn with_distance(mut self, distance: i32) -> Self {
+        let half = distance.clamp(1, 767) / 2;
+        self.start_x = 384 + half;
+        self.end_x = 384 - half;
+        self
+    }
+}
+
+/// Where xochitl's menus are, as the tap sequences replayed to use them
+///
+/// Menus move between firmware versions, so the taps come from a named preset
+/// (--ui-layout) and can be overridden per step from the config file or the
+/// command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UiLayout {
+    /// Taps that add a page after the current one and open it (virtual coordinates)
+    pub new_page: Vec<(i32, i32)>,
+    /// Taps that delete the current page (virtual coordinates)
+    pub delete_page: Vec<(i32, i32)>,
+    /// Taps from an open document to the focused library search field
+    pub open_search: Vec<(i32, i32)>,
+    /// Taps that open the first search result
+    pub search_result: Vec<(i32, i32)>,
+}
+
+impl UiLayout {
+    /// Built-in layout for a firmware family, e.g. "3.x"
+    pub fn preset(name: &str) -> Result<Self> {
+        UI_LAYOUT_PRESETS
+            .iter()
+            .find(|(preset, ..)| preset.eq_ignore_ascii_case(name))
+            .map(
+                |(_, new_page, delete_page, open_search, search_result)| Self {
+                    new_page: new_page.to_vec(),
+                    delete_page: delete_page.to_vec(),
+                    open_search: open_search.to_vec(),
+                    search_result: search_result.to_vec(),
+                },
+            )
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unknown UI layout: {}. Use {}",
+                    name,
+                    Self::preset_names().join(", ")
+                )
+            })
+    }
+
+    /// Names accepted by `preset`
+    pub fn preset_names() -> Vec<&'static str> {
+        UI_LAYOUT_PRESETS.iter().map(|(name, ..)| *name).collect()
+    }
+
+    /// The layout as a [ui_layout] config file section (see `Config`)
+    pub fn to_toml(&self) -> String {
+        let taps = |taps: &[(i32, i32)]| {
+            taps.iter()
+                .map(|(x, y)| format!("[{}, {}]", x, y))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        format!(
+            "[ui_layout]\nnew_page = [{}]\ndelete_page = [{}]\nopen_search = [{}]\nsearch_result = [{}]\n",
+            taps(&self.new_page),
+            taps(&self.delete_page),
+            taps(&self.open_search),
+            taps(&self.search_result)
+        )
+    }
+}
+
+impl Default for UiLayout {
+    fn default() -> Self {
+        Self {
+            new_page: NEW_PAGE_TAPS_3X.to_vec(),
+            delete_page: DELETE_PAGE_TAPS_3X.to_vec(),
+            open_search: OPEN_SEARCH_TAPS_3X.to_vec(),
+            search_result: SEARCH_RESULT_TAPS_3X.to_vec(),
+        }
+    }
+}
 
 /// Manages page navigation and creation on the reMarkable using touch gestures
+/// or the virtual keyboard
+///
+/// These only send the navigation input; callers wait for the page to settle
+/// (see `Workflow::wait_for_page_settle`).
 pub struct PageManager;
 
 impl PageManager {
-    /// Create a new page to the right of the current page
-    /// Uses swipe gesture simulation to navigate and create pages
-    pub fn create_page_right(touch: &mut Touch) -> Result<()> {
-        info!("Creating new page to the right via swipe gesture");
+    /// Navigate to the next page
+    pub fn next_page(
+        touch: &mut Touch,
+        keyboard: &mut Keyboard,
+        method: NavMethod,
+        swipe: &SwipeConfig,
+    ) -> Result<()> {
+        info!("Navigating to next page");
+        Self::forward(touch, keyboard, method, swipe)
+    }
 
-        // Strategy: Swipe left to go to next page
-        // If we're at the last page, xochitl will create a new blank page
+    /// Navigate to the previous page
+    pub fn previous_page(
+        touch: &mut Touch,
+        keyboard: &mut Keyboard,
+        method: NavMethod,
+        swipe: &SwipeConfig,
+    ) -> Result<()> {
+        info!("Navigating to previous page");
+        Self::backward(touch, keyboard, method, swipe)
+    }
 
-        Self::swipe_left(touch)?;
-        sleep(Duration::from_millis(500)); // Wait for page transition
+    /// Add a page after the current one through xochitl's menus, using the
+    /// default (3.x) layout; see `create_page` for other firmware
+    pub fn create_page_after_current(touch: &mut Touch) -> Result<()> {
+        Self::create_page(touch, &UiLayout::default().new_page)
+    }
 
-        Ok(())
+    /// Add a page after the current one by replaying `taps` (virtual coordinates)
+    /// through xochitl's menus
+    ///
+    /// The menu layout differs between firmware versions (see `UiLayout`). Callers
+    /// check the result, e.g. that the screen now shows a blank page.
+    pub fn create_page(touch: &mut Touch, taps: &[(i32, i32)]) -> Result<()> {
+        info!(
+            "Creating a page after the current one via {} menu taps",
+            taps.len()
+        );
+        Self::menu_taps(touch, taps)
     }
 
-    /// Navigate to the next page (swipe left)
-    pub fn next_page(touch: &mut Touch) -> Result<()> {
-        info!("Navigating to next page");
-        Self::swipe_left(touch)?;
-        sleep(Duration::from_millis(300));
-        Ok(())
+    /// Delete the current page through the page overview context menu
+    /// (overview -> page menu -> delete -> confirm), using the default (3.x) layout
+    pub fn delete_current_page(touch: &mut Touch) -> Result<()> {
+        Self::delete_page(touch, &UiLayout::default().delete_page)
     }
 
-    /// Navigate to the previous page (swipe right)
-    pub fn previous_page(touch: &mut Touch) -> Result<()> {
-        info!("Navigating to previous page");
-        Self::swipe_right(touch)?;
-        sleep(Duration::from_millis(300));
-        Ok(())
+    /// Delete the current page by replaying `taps` (virtual coordinates) through xochitl's menus
+    ///
+    /// The menu layout differs between firmware versions, so callers may supply
+    /// their own sequence. A wrong sequence can delete or change a different page;
+    /// callers must make sure the current page is the one to delete and check the result.
+    pub fn delete_page(touch: &mut Touch, taps: &[(i32, i32)]) -> Result<()> {
+        info!("Deleting current page via {} menu taps", taps.len());
+        Self::menu_taps(touch, taps)
     }
 
-    /// Simulate a left swipe (next page)
-    /// Swipes from right edge to left
-    fn swipe_left(touch: &mut Touch) -> Result<()> {
-        debug!("Simulating left swipe");
+    /// Leave the open document for the library search field by replaying `taps`
+    /// (virtual coordinates); the search text is typed by the caller
+    pub fn open_search(touch: &mut Touch, taps: &[(i32, i32)]) -> Result<()> {
+        info!("Opening the library search via {} taps", taps.len());
+        Self::menu_taps(touch, taps)
+    }
 
-        // Start from right edge, middle height
-        let start_x = 700;
-        let start_y = 512;
+    /// Open the first search result by replaying `taps` (virtual coordinates)
+    pub fn open_search_result(touch: &mut Touch, taps: &[(i32, i32)]) -> Result<()> {
+        info!("Opening the first search result via {} taps", taps.len());
+        Self::menu_taps(touch, taps)
+    }
 
-        // End at left side, same height
-        let end_x = 100;
-        let _end_y = 512;
+    /// Tap each position in turn, waiting for the menus in between
+    fn menu_taps(touch: &mut Touch, taps: &[(i32, i32)]) -> Result<()> {
+        for &(x, y) in taps {
+            debug!("Menu tap at ({}, {})", x, y);
+            touch.touch_start((x, y))?;
+            sleep(Duration::from_millis(100));
+            touch.touch_stop()?;
+            // Menus animate in; give each one time to appear before the next tap
+            sleep(Duration::from_millis(600));
+        }
+        Ok(())
+    }
 
-        // Perform swipe with multiple touch points for smooth gesture
-        touch.touch_start((start_x, start_y))?;
-        sleep(Duration::from_millis(50));
+    fn forward(
+        touch: &mut Touch,
+        keyboard: &mut Keyboard,
+        method: NavMethod,
+        swipe: &SwipeConfig,
+    ) -> Result<()> {
+        match method {
+            NavMethod::Swipe => Self::swipe(touch, swipe, true),
+            NavMethod::EdgeTap => Self::tap_edge(touch, RIGHT_EDGE_X),
+            NavMethod::Keyboard => keyboard.arrow_right(),
+        }
+    }
 
-        // Interpolate between start and end
-        let steps = 10;
-        for i in 1..=steps {
-            let t = i as f32 / steps as f32;
-            let x = start_x - ((start_x - end_x) as f32 * t) as i32;
-            let y = start_y;
-            touch.goto_xy((x, y))?;
-            sleep(Duration::from_millis(10));
+    fn backward(
+        touch: &mut Touch,
+        keyboard: &mut Keyboard,
+        method: NavMethod,
+        swipe: &SwipeConfig,
+    ) -> Result<()> {
+        match method {
+            NavMethod::Swipe => Self::swipe(touch, swipe, false),
+            NavMethod::EdgeTap => Self::tap_edge(touch, LEFT_EDGE_X),
+            NavMethod::Keyboard => keyboard.arrow_left(),
         }
+    }
 
+    /// Tap a page-turn hot zone at the given x position, middle height
+    fn tap_edge(touch: &mut Touch, x: i32) -> Result<()> {
+        debug!("Simulating edge tap at x={}", x);
+        touch.touch_start((x, 512))?;
+        sleep(Duration::from_millis(100));
         touch.touch_stop()?;
         Ok(())
     }
 
-    /// Simulate a right swipe (previous page)
-    /// Swipes from left edge to right
-    fn swipe_right(touch: &mut Touch) -> Result<()> {
-        debug!("Simulating right swipe");
-
-        // Start from left edge, middle height
-        let start_x = 100;
-        let start_y = 512;
-
-        // End at right side, same height
-        let end_x = 700;
-        let _end_y = 512;
+    /// Swipe horizontally along `swipe`'s path; forward swipes right to left (next page)
+    fn swipe(touch: &mut Touch, swipe: &SwipeConfig, forward: bool) -> Result<()> {
+        let (start_x, end_x) = if forward {
+            (swipe.start_x, swipe.end_x)
+        } else {
+            (swipe.end_x, swipe.start_x)
+        };
+        debug!(
+            "Simulating swipe from x={} to x={} at y={} ({} steps, {}ms each)",
+            start_x, end_x, swipe.y, swipe.steps, swipe.step_delay_ms
+        );
 
         // Perform swipe with multiple touch points for smooth gesture
-        touch.touch_start((start_x, start_y))?;
+        touch.touch_start((start_x, swipe.y))?;
         sleep(Duration::from_millis(50));
 
         // Interpolate between start and end
-        let steps = 10;
+        let steps = swipe.steps.max(1);
         for i in 1..=steps {
             let t = i as f32 / steps as f32;
-            let x = start_x + ((end_x - start_x) as f32 * t) as i32;
-            let y = start_y;
-            touch.goto_xy((x, y))?;
-            sleep(Duration::from_millis(10));
+            let x = start_x + ((end_x - start_x) as f32 * t).round() as i32;
+            touch.goto_xy((x, swipe.y))?;
+            sleep(Duration::from_millis(swipe.step_delay_ms));
         }
 
         touch.touch_stop()?;
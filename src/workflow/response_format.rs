@@ -0,0 +1,79 @@
+use serde::Deserialize;
+
+/// Labels and separator of the structured LLM response
+///
+/// The built-in prompts ask for this format and the parser reads it, so both are
+/// driven from here: changing a label changes the prompt and the parser together.
+/// Set from the [response_format] section of the config file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ResponseFormat {
+    /// Line between the header fields and the answer
+    pub separator: String,
+    pub question: String,
+    pub question_box: String,
+    pub outline_box: String,
+    pub quote: String,
+    pub answer: String,
+    /// Whole reply meaning "no outline/question found"
+    pub none: String,
+}
+
+impl Default for ResponseFormat {
+    fn default() -> Self {
+        Self {
+            separator: "---".to_string(),
+            question: "QUESTION:".to_string(),
+            question_box: "QUESTION_BOX:".to_string(),
+            outline_box: "OUTLINE_BOX:".to_string(),
+            quote: "QUOTE:".to_string(),
+            answer: "ANSWER:".to_string(),
+            none: "NONE".to_string(),
+        }
+    }
+}
+
+impl ResponseFormat {
+    /// Header fields, i.e. every label that comes before the separator
+    pub fn header_fields(&self) -> [&str; 4] {
+        [
+            &self.question,
+            &self.question_box,
+            &self.outline_box,
+            &self.quote,
+        ]
+    }
+
+    /// Whether the model reported that there is nothing to answer
+    pub fn is_none(&self, response: &str) -> bool {
+        response
+            .trim()
+            .to_uppercase()
+            .starts_with(&self.none.to_uppercase())
+    }
+
+    /// Rewrite a prompt written for the default format to ask for this one
+    pub fn apply_to_prompt(&self, prompt: &str) -> String {
+        let default = Self::default();
+        if *self == default {
+            return prompt.to_string();
+        }
+        // No default label is part of another, so the order doesn't matter
+        [
+            (&default.question_box, &self.question_box),
+            (&default.outline_box, &self.outline_box),
+            (&default.question, &self.question),
+            (&default.quote, &self.quote),
+            (&default.answer, &self.answer),
+            (&default.separator, &self.separator),
+        ]
+        .iter()
+        .fold(prompt.to_string(), |prompt, (from, to)| {
+            prompt.replace(from.as_str(), to)
+        })
+        .replace(
+            &format!("respond with just:\n{}", default.none),
+            &format!("respond with just:\n{}", self.none),
+        )
+    }
+}
@@ -0,0 +1,164 @@
+use anyhow::Result;
+use log::{debug, info};
+use std::path::Path;
+use std::thread::sleep;
+use std::time::Duration;
+
+use crate::device::{keyboard::Keyboard, pen::Pen, touch::Touch};
+
+// Swipe timing, same as a default page-turn swipe
+const SWIPE_STEPS: u32 = 10;
+const SWIPE_STEP_DELAY: Duration = Duration::from_millis(10);
+
+/// A single device action (virtual 768x1024 coordinates)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Touch and release at a point
+    Tap((i32, i32)),
+    /// Touch at `from`, move to `to` in `steps` points, release
+    Swipe {
+        from: (i32, i32),
+        to: (i32, i32),
+        steps: u32,
+    },
+    /// Type text on the virtual keyboard
+    Type(String),
+    /// Draw a straight pen line
+    DrawLine { from: (i32, i32), to: (i32, i32) },
+    /// Pause, e.g. for a menu to open
+    Wait(Duration),
+}
+
+/// A sequence of device actions, replayed in order by `run`
+///
+/// Lets xochitl UI automations (menus, page overview, ...) be written as data
+/// and adjusted without recompiling. One action per line:
+///
+/// ```text
+/// # open the page overview, then the page menu
+/// tap 40,115
+/// wait 600
+/// swipe 700,512 100,512 20
+/// line 100,900 600,900
+/// type Hello
+/// ```
+///
+/// `swipe` takes an optional step count; `type` takes the rest of the line as is.
+/// Blank lines and lines starting with `#` are ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Script {
+    pub actions: Vec<Action>,
+}
+
+impl Script {
+    pub fn new(actions: Vec<Action>) -> Self {
+        Self { actions }
+    }
+
+    /// Parse the line format described above
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut actions = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim_start();
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let action = Self::parse_action(line)
+                .map_err(|e| anyhow::anyhow!("Script line {}: {}", number + 1, e))?;
+            actions.push(action);
+        }
+        Ok(Self { actions })
+    }
+
+    /// Read and parse a script file
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Could not read script {}: {}", path.display(), e))?;
+        Self::parse(&text)
+    }
+
+    fn parse_action(line: &str) -> Result<Action> {
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        if command == "type" {
+            // Keep the text exactly as written, including inner and trailing spaces
+            return Ok(Action::Type(rest.to_string()));
+        }
+
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        match (command, args.as_slice()) {
+            ("tap", [point]) => Ok(Action::Tap(parse_point(point)?)),
+            ("swipe", [from, to]) => Ok(Action::Swipe {
+                from: parse_point(from)?,
+                to: parse_point(to)?,
+                steps: SWIPE_STEPS,
+            }),
+            ("swipe", [from, to, steps]) => Ok(Action::Swipe {
+                from: parse_point(from)?,
+                to: parse_point(to)?,
+                steps: steps.parse()?,
+            }),
+            ("line", [from, to]) => Ok(Action::DrawLine {
+                from: parse_point(from)?,
+                to: parse_point(to)?,
+            }),
+            ("wait", [ms]) => Ok(Action::Wait(Duration::from_millis(ms.parse()?))),
+            ("tap" | "swipe" | "line" | "wait", _) => {
+                anyhow::bail!("Wrong arguments for '{}': {}", command, line)
+            }
+            _ => anyhow::bail!(
+                "Unknown action '{}'. Use tap, swipe, type, line, or wait",
+                command
+            ),
+        }
+    }
+
+    /// Replay the actions on the given devices
+    pub fn run(&self, touch: &mut Touch, keyboard: &mut Keyboard, pen: &mut Pen) -> Result<()> {
+        info!("Running script of {} action(s)", self.actions.len());
+        for action in &self.actions {
+            debug!("Script action: {:?}", action);
+            match action {
+                Action::Tap(point) => {
+                    touch.touch_start(*point)?;
+                    sleep(Duration::from_millis(100));
+                    touch.touch_stop()?;
+                }
+                Action::Swipe { from, to, steps } => swipe(touch, *from, *to, *steps)?,
+                Action::Type(text) => keyboard.string_to_keypresses(text)?,
+                Action::DrawLine { from, to } => pen.draw_line(*from, *to)?,
+                Action::Wait(duration) => sleep(*duration),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse "x,y" and check it is on the 768x1024 screen
+fn parse_point(text: &str) -> Result<(i32, i32)> {
+    let (x, y) = text
+        .split_once(',')
+        .ok_or_else(|| anyhow::anyhow!("Invalid point '{}': expected x,y", text))?;
+    let x: i32 = x.trim().parse()?;
+    let y: i32 = y.trim().parse()?;
+    if !(0..768).contains(&x) || !(0..1024).contains(&y) {
+        anyhow::bail!("Point ({}, {}) is outside the 768x1024 screen", x, y);
+    }
+    Ok((x, y))
+}
+
+fn swipe(touch: &mut Touch, from: (i32, i32), to: (i32, i32), steps: u32) -> Result<()> {
+    touch.touch_start(from)?;
+    sleep(Duration::from_millis(50));
+
+    let steps = steps.max(1);
+    for i in 1..=steps {
+        let t = i as f32 / steps as f32;
+        let x = from.0 + ((to.0 - from.0) as f32 * t).round() as i32;
+        let y = from.1 + ((to.1 - from.1) as f32 * t).round() as i32;
+        touch.goto_xy((x, y))?;
+        sleep(SWIPE_STEP_DELAY);
+    }
+
+    touch.touch_stop()?;
+    Ok(())
+}
@@ -1,19 +1,73 @@
 use anyhow::Result;
-use log::debug;
+use log::{debug, warn};
+use serde_json::{Map, Value as JsonValue};
 use std::fs;
 use std::path::Path;
 
+use super::validation::svg_to_bitmap;
+
+// Legacy on-device location of the state file
+const DEVICE_STATE_FILE: &str = "/home/root/.reader-buddy-symbol-state";
+const STATE_FILE_NAME: &str = "symbol-state";
+// State file key for the sequence used when no document is known
+const GLOBAL_STATE_KEY: &str = "global";
+
+// 3x5 pixel digit glyphs, one row per entry (bit 2 = left column)
+const DIGIT_WIDTH: i32 = 3;
+const DIGIT_HEIGHT: i32 = 5;
+const DIGIT_GLYPHS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111], // 0
+    [0b010, 0b110, 0b010, 0b010, 0b111], // 1
+    [0b111, 0b001, 0b111, 0b100, 0b111], // 2
+    [0b111, 0b001, 0b111, 0b001, 0b111], // 3
+    [0b101, 0b101, 0b111, 0b001, 0b001], // 4
+    [0b111, 0b100, 0b111, 0b001, 0b111], // 5
+    [0b111, 0b100, 0b111, 0b101, 0b111], // 6
+    [0b111, 0b001, 0b010, 0b010, 0b010], // 7
+    [0b111, 0b101, 0b111, 0b101, 0b111], // 8
+    [0b111, 0b101, 0b111, 0b001, 0b111], // 9
+];
+
+/// How reference symbols are drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolStyle {
+    /// Circle with the number from built-in pixel digits
+    Pixel,
+    /// Circle with the number rendered as SVG text (smoother, any number of digits)
+    Font,
+}
+
+impl SymbolStyle {
+    pub fn from_string(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "pixel" => Ok(SymbolStyle::Pixel),
+            "font" => Ok(SymbolStyle::Font),
+            _ => Err(anyhow::anyhow!(
+                "Invalid symbol style: {}. Use pixel or font",
+                s
+            )),
+        }
+    }
+}
+
 /// Pool of reference symbols for marking question-answer pairs
 /// Uses circled numbers: ①②③④⑤⑥⑦⑧⑨⑩
 pub struct SymbolPool {
     current_index: usize,
     symbols: Vec<String>,
     state_file: String,
+    /// Document whose sequence is in use (None = the global sequence)
+    document_id: Option<String>,
 }
 
 impl SymbolPool {
     /// Create a new symbol pool
     pub fn new() -> Self {
+        Self::new_with_index(0)
+    }
+
+    /// Create a new symbol pool starting at a known index (wraps around the pool size)
+    pub fn new_with_index(index: usize) -> Self {
         let symbols = vec![
             "①".to_string(),
             "②".to_string(),
@@ -27,32 +81,127 @@ impl SymbolPool {
             "⑩".to_string(),
         ];
 
-        let state_file = "/home/root/.reader-buddy-symbol-state".to_string();
+        let state_file = Self::default_state_file();
 
         Self {
-            current_index: 0,
+            current_index: index % symbols.len(),
             symbols,
             state_file,
+            document_id: None,
+        }
+    }
+
+    /// Use a different file for persisting the pool state
+    pub fn with_state_file(mut self, state_file: impl Into<String>) -> Self {
+        self.state_file = state_file.into();
+        self
+    }
+
+    /// Persist the pool state inside the given directory
+    pub fn with_state_dir(self, state_dir: impl AsRef<Path>) -> Self {
+        let state_file = state_dir.as_ref().join(STATE_FILE_NAME);
+        self.with_state_file(state_file.to_string_lossy())
+    }
+
+    /// Resolve the default state file location
+    /// Prefers $XDG_STATE_HOME, then the on-device home, then ~/.local/state
+    pub fn default_state_file() -> String {
+        if let Some(state_home) = std::env::var_os("XDG_STATE_HOME").filter(|v| !v.is_empty()) {
+            return Path::new(&state_home)
+                .join("reader-buddy")
+                .join(STATE_FILE_NAME)
+                .to_string_lossy()
+                .to_string();
+        }
+
+        if Path::new("/home/root").exists() {
+            return DEVICE_STATE_FILE.to_string();
+        }
+
+        if let Some(home) = std::env::var_os("HOME") {
+            return Path::new(&home)
+                .join(".local/state/reader-buddy")
+                .join(STATE_FILE_NAME)
+                .to_string_lossy()
+                .to_string();
+        }
+
+        DEVICE_STATE_FILE.to_string()
+    }
+
+    /// Path of the file used to persist the pool state
+    pub fn state_file(&self) -> &str {
+        &self.state_file
+    }
+
+    /// Switch to the sequence kept for `document_id` (None = the global sequence)
+    /// A document seen for the first time starts again at the first symbol
+    pub fn set_document(&mut self, document_id: Option<String>) -> Result<()> {
+        if self.document_id == document_id {
+            return Ok(());
+        }
+        debug!("Switching symbol sequence to {:?}", document_id);
+        self.document_id = document_id;
+        self.current_index = 0;
+        self.load()
+    }
+
+    /// State file key of the sequence in use
+    fn state_key(&self) -> &str {
+        self.document_id.as_deref().unwrap_or(GLOBAL_STATE_KEY)
+    }
+
+    /// Read the state file: a JSON object from document id (or "global") to index
+    fn read_state(&self) -> Result<Map<String, JsonValue>> {
+        if !Path::new(&self.state_file).exists() {
+            return Ok(Map::new());
+        }
+        let content = fs::read_to_string(&self.state_file)?;
+        let content = content.trim();
+
+        // Older versions stored a single global index
+        if let Ok(index) = content.parse::<usize>() {
+            let mut state = Map::new();
+            state.insert(GLOBAL_STATE_KEY.to_string(), index.into());
+            return Ok(state);
+        }
+        match serde_json::from_str(content) {
+            Ok(JsonValue::Object(state)) => Ok(state),
+            _ => {
+                warn!("Ignoring unreadable symbol state in {}", self.state_file);
+                Ok(Map::new())
+            }
         }
     }
 
     /// Load the symbol pool state from disk
     /// Returns the last used index
     pub fn load(&mut self) -> Result<()> {
-        if Path::new(&self.state_file).exists() {
-            let content = fs::read_to_string(&self.state_file)?;
-            if let Ok(index) = content.trim().parse::<usize>() {
-                self.current_index = index % self.symbols.len();
-                debug!("Loaded symbol state: index {}", self.current_index);
-            }
+        let state = self.read_state()?;
+        if let Some(index) = state.get(self.state_key()).and_then(JsonValue::as_u64) {
+            self.current_index = index as usize % self.symbols.len();
+            debug!(
+                "Loaded symbol state for {}: index {}",
+                self.state_key(),
+                self.current_index
+            );
         }
         Ok(())
     }
 
-    /// Save the current symbol pool state to disk
+    /// Save the current symbol pool state to disk, keeping other documents' sequences
     fn save(&self) -> Result<()> {
-        fs::write(&self.state_file, self.current_index.to_string())?;
-        debug!("Saved symbol state: index {}", self.current_index);
+        let mut state = self.read_state()?;
+        state.insert(self.state_key().to_string(), self.current_index.into());
+        if let Some(parent) = Path::new(&self.state_file).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.state_file, JsonValue::Object(state).to_string())?;
+        debug!(
+            "Saved symbol state for {}: index {}",
+            self.state_key(),
+            self.current_index
+        );
         Ok(())
     }
 
@@ -70,28 +219,20 @@ impl SymbolPool {
         Ok(symbol)
     }
 
+    /// Every symbol the pool hands out, in order
+    pub fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
     /// Get the current symbol without advancing
     pub fn current_symbol(&self) -> String {
         self.symbols[self.current_index].clone()
     }
 
     /// Convert symbol to bitmap for rendering
-    /// MVP: Uses simple circle pattern. Future enhancement: render actual Unicode glyphs
-    pub fn symbol_to_bitmap(_symbol: &str, size: u32) -> Vec<Vec<bool>> {
-        // TODO: Future enhancement - render actual Unicode circled numbers (①②③④⑤⑥⑦⑧⑨⑩)
-        // Options for future implementation:
-        // 1. Use font rendering with resvg (like ghostwriter does for SVG text)
-        // 2. Pre-rendered bitmap glyphs embedded in binary
-        // 3. SVG paths for each symbol converted to bitmaps
-        //
-        // For MVP: Simple circle marker works fine for visual reference
-
-        debug!(
-            "Converting symbol to {}x{} bitmap (simple circle for MVP)",
-            size, size
-        );
-
-        // Return a simple circle pattern
+    /// Circled numbers are drawn from built-in digit glyphs, so they render the same
+    /// whatever fonts the device has; any other symbol becomes a plain circle
+    pub fn symbol_to_bitmap(symbol: &str, size: u32) -> Vec<Vec<bool>> {
         let mut bitmap = vec![vec![false; size as usize]; size as usize];
         let center = size as i32 / 2;
         let radius = (size as f32 * 0.4) as i32;
@@ -107,8 +248,118 @@ impl SymbolPool {
             }
         }
 
+        match Self::symbol_number(symbol) {
+            Some(number) => {
+                debug!(
+                    "Converting symbol {} to {}x{} bitmap (built-in digit glyphs)",
+                    symbol, size, size
+                );
+                Self::draw_number(&mut bitmap, number, center, radius - 2);
+            }
+            None => debug!(
+                "Converting symbol {} to {}x{} bitmap (no glyph, plain circle)",
+                symbol, size, size
+            ),
+        }
+
         bitmap
     }
+
+    /// Convert symbol to bitmap by composing a circle with the number as SVG text
+    /// Works for any number (not just those with a circled code point); symbols
+    /// without a number fall back to symbol_to_bitmap
+    pub fn symbol_to_bitmap_font(
+        symbol: &str,
+        size: u32,
+        font: Option<&Path>,
+    ) -> Result<Vec<Vec<bool>>> {
+        let Some(number) = Self::symbol_number(symbol) else {
+            return Ok(Self::symbol_to_bitmap(symbol, size));
+        };
+        debug!(
+            "Converting symbol {} to {}x{} bitmap (SVG text)",
+            symbol, size, size
+        );
+
+        let label = number.to_string();
+        let center = size as f32 / 2.0;
+        let radius = size as f32 * 0.4 - 1.0;
+        // Shrink the text as the number gets longer so it stays inside the circle
+        let font_size = radius * 1.3 / (label.len() as f32).max(1.0).sqrt();
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}">
+  <circle cx="{center}" cy="{center}" r="{radius}" fill="none" stroke="black" stroke-width="2"/>
+  <text x="{center}" y="{center}" font-family="sans-serif" font-size="{font_size}"
+        font-weight="bold" text-anchor="middle" dominant-baseline="central">{label}</text>
+</svg>"#
+        );
+
+        svg_to_bitmap(&svg, font)
+    }
+
+    /// The number a symbol stands for: circled numbers (① = 1 ... ⑳ = 20) or plain digits
+    fn symbol_number(symbol: &str) -> Option<u32> {
+        if let Ok(number) = symbol.parse::<u32>() {
+            return Some(number);
+        }
+
+        let mut chars = symbol.chars();
+        let c = chars.next()?;
+        if chars.next().is_some() {
+            return None;
+        }
+        match c as u32 {
+            code @ 0x2460..=0x2473 => Some(code - 0x2460 + 1),
+            _ => None,
+        }
+    }
+
+    /// Draw `number` centered at (center, center), scaled to fit inside `inner_radius`
+    fn draw_number(bitmap: &mut [Vec<bool>], number: u32, center: i32, inner_radius: i32) {
+        let digits: Vec<usize> = number
+            .to_string()
+            .bytes()
+            .map(|b| (b - b'0') as usize)
+            .collect();
+        let count = digits.len() as i32;
+
+        // Largest scale whose box fits inside the ring with a one-pixel gap
+        let fits = |scale: i32| {
+            let half_width = (count * DIGIT_WIDTH + count - 1) * scale / 2;
+            let half_height = DIGIT_HEIGHT * scale / 2;
+            half_width * half_width + half_height * half_height
+                <= (inner_radius - 1) * (inner_radius - 1)
+        };
+        let scale = (1..=inner_radius).rev().find(|&s| fits(s)).unwrap_or(1);
+
+        let width = (count * DIGIT_WIDTH + count - 1) * scale;
+        let height = DIGIT_HEIGHT * scale;
+        let left = center - width / 2;
+        let top = center - height / 2;
+
+        for (i, &digit) in digits.iter().enumerate() {
+            let digit_left = left + i as i32 * (DIGIT_WIDTH + 1) * scale;
+            for (row, pattern) in DIGIT_GLYPHS[digit].iter().enumerate() {
+                for col in 0..DIGIT_WIDTH {
+                    if pattern & (1 << (DIGIT_WIDTH - 1 - col)) == 0 {
+                        continue;
+                    }
+                    for sy in 0..scale {
+                        for sx in 0..scale {
+                            let x = digit_left + col * scale + sx;
+                            let y = top + row as i32 * scale + sy;
+                            if let Some(pixel) = bitmap
+                                .get_mut(y as usize)
+                                .and_then(|r| r.get_mut(x as usize))
+                            {
+                                *pixel = true;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
 }
 
 impl Default for SymbolPool {
@@ -116,3 +367,48 @@ impl Default for SymbolPool {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYMBOLS: &[&str] = &["①", "②", "③", "⑦", "⑩", "11", "12", "20", "21"];
+
+    fn assert_all_differ(bitmaps: &[Vec<Vec<bool>>]) {
+        for (i, a) in bitmaps.iter().enumerate() {
+            for (j, b) in bitmaps.iter().enumerate().skip(i + 1) {
+                assert_ne!(a, b, "{} and {} render the same", SYMBOLS[i], SYMBOLS[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn digit_glyph_bitmaps_differ_between_numbers() {
+        let bitmaps: Vec<_> = SYMBOLS
+            .iter()
+            .map(|symbol| SymbolPool::symbol_to_bitmap(symbol, 48))
+            .collect();
+        assert_all_differ(&bitmaps);
+    }
+
+    #[test]
+    fn svg_text_bitmaps_differ_between_numbers() {
+        let bitmaps: Vec<_> = SYMBOLS
+            .iter()
+            .map(|symbol| SymbolPool::symbol_to_bitmap_font(symbol, 48, None).unwrap())
+            .collect();
+        assert_all_differ(&bitmaps);
+    }
+
+    #[test]
+    fn symbols_without_a_number_are_a_plain_circle() {
+        assert_eq!(
+            SymbolPool::symbol_to_bitmap("★", 48),
+            SymbolPool::symbol_to_bitmap("♦", 48)
+        );
+        assert_ne!(
+            SymbolPool::symbol_to_bitmap("★", 48),
+            SymbolPool::symbol_to_bitmap("①", 48)
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
+use log::info;
+use resvg::{tiny_skia, usvg};
+use std::path::Path;
+
+/// Font bundled into the binary so SVG text renders on devices without system fonts
+/// (DejaVu Sans; also covers the circled numbers ①-⑩)
+const EMBEDDED_FONT: &[u8] = include_bytes!("../../assets/fonts/DejaVuSans.ttf");
+const EMBEDDED_FONT_FAMILY: &str = "DejaVu Sans";
+
+/// Synthetic page used by --validate-prompt: printed text with one phrase
+/// circled and a question written beside it
+const VALIDATION_PAGE_SVG: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="768" height="1024">
+  <rect width="768" height="1024" fill="white"/>
+  <g font-family="serif" font-size="22" fill="black">
+    <text x="60" y="120">Plants capture energy from sunlight in their leaves.</text>
+    <text x="60" y="155">This process, called photosynthesis, turns water and</text>
+    <text x="60" y="190">carbon dioxide into sugar and releases oxygen.</text>
+    <text x="60" y="225">Most of it happens inside the chloroplasts.</text>
+  </g>
+  <ellipse cx="290" cy="148" rx="135" ry="24" fill="none" stroke="black" stroke-width="3"/>
+  <text x="80" y="320" font-family="sans-serif" font-style="italic" font-size="30"
+        fill="black">What is this?</text>
+</svg>"##;
+
+/// SVG options with the embedded font, system fonts and an optional user font
+///
+/// Generic families (serif, sans-serif) resolve to the user font if given, otherwise
+/// to the embedded font, so text looks the same whatever the device has installed
+pub fn svg_options(font: Option<&Path>) -> Result<usvg::Options<'static>> {
+    let mut options = usvg::Options::default();
+    let fontdb = options.fontdb_mut();
+    fontdb.load_font_data(EMBEDDED_FONT.to_vec());
+    fontdb.load_system_fonts();
+
+    let family = match font {
+        Some(path) => {
+            let loaded = fontdb.len();
+            fontdb.load_font_file(path)?;
+            fontdb
+                .faces()
+                .nth(loaded)
+                .and_then(|face| face.families.first())
+                .map(|(name, _)| name.clone())
+                .ok_or_else(|| anyhow::anyhow!("No usable font in {}", path.display()))?
+        }
+        None => EMBEDDED_FONT_FAMILY.to_string(),
+    };
+    info!(
+        "SVG text font: {} ({} faces available)",
+        family,
+        fontdb.len()
+    );
+    fontdb.set_serif_family(family.clone());
+    fontdb.set_sans_serif_family(family);
+
+    Ok(options)
+}
+
+/// Render an SVG document to a bitmap of its size; true where the drawing is opaque
+pub fn svg_to_bitmap(svg: &str, font: Option<&Path>) -> Result<Vec<Vec<bool>>> {
+    let tree = usvg::Tree::from_str(svg, &svg_options(font)?)?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| anyhow::anyhow!("Failed to allocate SVG bitmap"))?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+
+    Ok(pixmap
+        .pixels()
+        .chunks(size.width() as usize)
+        .map(|row| row.iter().map(|pixel| pixel.alpha() > 128).collect())
+        .collect())
+}
+
+/// Render the validation page to PNG bytes
+pub fn validation_image(font: Option<&Path>) -> Result<Vec<u8>> {
+    let options = svg_options(font)?;
+
+    let tree = usvg::Tree::from_str(VALIDATION_PAGE_SVG, &options)?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| anyhow::anyhow!("Failed to allocate validation image"))?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+
+    Ok(pixmap.encode_png()?)
+}
+
+/// Validation page as a data URI, ready to send to the LLM
+pub fn validation_image_data_uri(font: Option<&Path>) -> Result<String> {
+    Ok(format!(
+        "data:image/png;base64,{}",
+        general_purpose::STANDARD.encode(validation_image(font)?)
+    ))
+}
@@ -0,0 +1,70 @@
+use log::{debug, error, info};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+/// Exit status when the watchdog fires, so a supervisor can tell a hang from an error
+pub const WATCHDOG_EXIT_CODE: i32 = 3;
+
+/// Exits the process when an iteration stops making progress
+///
+/// A stuck LLM call or a blocking device read would otherwise leave the tool dead
+/// until someone restarts it. The watchdog is armed by `pet` and disarmed by
+/// `idle`, so waiting for a trigger (which may take hours) never counts. While
+/// armed, going `timeout` without a `pet` logs the hang and exits with
+/// WATCHDOG_EXIT_CODE for a supervisor (systemd, a shell loop) to restart.
+///
+/// Clones share the same state; the checking thread ends once all are dropped.
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    /// When progress was last reported, None while idle
+    last_pet: Arc<Mutex<Option<Instant>>>,
+}
+
+impl Watchdog {
+    pub fn start(timeout: Duration) -> Self {
+        info!(
+            "Watchdog enabled ({}s without progress exits)",
+            timeout.as_secs()
+        );
+        let last_pet = Arc::new(Mutex::new(None));
+        let state = Arc::downgrade(&last_pet);
+        thread::spawn(move || Self::watch(state, timeout));
+        Self { last_pet }
+    }
+
+    /// Report progress, arming the watchdog if it was idle
+    pub fn pet(&self) {
+        *self.lock() = Some(Instant::now());
+    }
+
+    /// Stop watching until the next `pet`, e.g. while waiting for a trigger
+    pub fn idle(&self) {
+        *self.lock() = None;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Instant>> {
+        // The state is a plain timestamp, so a poisoned lock is still usable
+        self.last_pet.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn watch(state: Weak<Mutex<Option<Instant>>>, timeout: Duration) {
+        loop {
+            sleep(Duration::from_secs(1));
+            let Some(state) = state.upgrade() else {
+                debug!("Watchdog stopped");
+                return;
+            };
+            let last_pet = *state.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(last_pet) = last_pet {
+                if last_pet.elapsed() >= timeout {
+                    error!(
+                        "Watchdog: no progress for {}s; the iteration looks hung, exiting",
+                        last_pet.elapsed().as_secs()
+                    );
+                    std::process::exit(WATCHDOG_EXIT_CODE);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
+use log::{debug, info, warn};
+use serde_json::{json, Value as JsonValue};
+use std::io::Cursor;
+use std::thread::{self, sleep};
+use std::time::Duration;
+
+use crate::analysis::{AnalysisResult, BoundingBox};
+
+/// Posts each analysis result to a URL so other apps can follow along
+///
+/// Requests run on a background thread with a short timeout, so a slow or dead
+/// endpoint never holds up the tablet loop.
+pub struct Webhook {
+    url: String,
+    agent: ureq::Agent,
+    retries: u32,
+    include_images: bool,
+}
+
+impl Webhook {
+    pub fn new(url: String, timeout: Duration) -> Self {
+        let config = ureq::Agent::config_builder()
+            .timeout_global(Some(timeout))
+            .build();
+        Self {
+            url,
+            agent: ureq::Agent::new_with_config(config),
+            retries: 2,
+            include_images: false,
+        }
+    }
+
+    /// Extra attempts after a failed POST
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Attach a PNG crop of each item's outlined region
+    pub fn with_images(mut self, include_images: bool) -> Self {
+        self.include_images = include_images;
+        self
+    }
+
+    pub fn includes_images(&self) -> bool {
+        self.include_images
+    }
+
+    /// Queue `result` for delivery; `page_png` is the screenshot the result was made from
+    pub fn post(&self, result: &AnalysisResult, page_png: Option<&[u8]>) -> Result<()> {
+        let mut payload = serde_json::to_value(result)?;
+        if let (true, Some(page_png)) = (self.include_images, page_png) {
+            let images: Vec<JsonValue> = result
+                .items
+                .iter()
+                .map(|item| match &item.outline_box {
+                    Some(outline) => match crop_png_base64(page_png, outline) {
+                        Ok(image) => JsonValue::String(image),
+                        Err(e) => {
+                            warn!("Could not crop outline for webhook: {}", e);
+                            JsonValue::Null
+                        }
+                    },
+                    None => JsonValue::Null,
+                })
+                .collect();
+            payload["images"] = json!(images);
+        }
+
+        let url = self.url.clone();
+        let agent = self.agent.clone();
+        let retries = self.retries;
+        thread::spawn(move || {
+            for attempt in 0..=retries {
+                match agent.post(url.as_str()).send_json(&payload) {
+                    Ok(_) => {
+                        info!("Posted result to webhook {}", url);
+                        return;
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Webhook POST to {} failed (attempt {}/{}): {}",
+                            url,
+                            attempt + 1,
+                            retries + 1,
+                            e
+                        );
+                        if attempt < retries {
+                            sleep(Duration::from_secs(1 << attempt));
+                        }
+                    }
+                }
+            }
+        });
+        debug!("Queued webhook POST to {}", self.url);
+        Ok(())
+    }
+}
+
+/// Base64 PNG of `region` cut out of a page screenshot, clamped to the page
+fn crop_png_base64(png_data: &[u8], region: &BoundingBox) -> Result<String> {
+    let img = image::load_from_memory(png_data)?;
+    let x = region.x.clamp(0, img.width() as i32) as u32;
+    let y = region.y.clamp(0, img.height() as i32) as u32;
+    let width = (region.width.max(0) as u32).min(img.width() - x);
+    let height = (region.height.max(0) as u32).min(img.height() - y);
+    if width == 0 || height == 0 {
+        anyhow::bail!("outline box is outside the page");
+    }
+
+    let mut png = Vec::new();
+    img.crop_imm(x, y, width, height)
+        .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)?;
+    Ok(general_purpose::STANDARD.encode(&png))
+}